@@ -0,0 +1,26 @@
+//! Compare time of compiling an additive-only command, which builds its tree directly,
+//! with a command of the same items which needs the precedence climber
+//!
+//! Run with `cargo run --release --example compile_additive`.
+
+use std::time::Instant;
+
+use gurgle::Gurgle;
+
+const ROUNDS: usize = 100_000;
+
+fn bench(command: &str) {
+    // first compile initializes the climber, keep it out of the timing
+    Gurgle::compile(command).unwrap();
+
+    let start = Instant::now();
+    let total: usize = (0..ROUNDS)
+        .map(|_| Gurgle::compile(command).unwrap().to_string().len())
+        .sum();
+    println!("{}: {:?}, total {}", command, start.elapsed(), total);
+}
+
+fn main() {
+    bench("3d6+2d4+1+1+1");
+    bench("3d6+2d4+1+1*1");
+}
@@ -0,0 +1,22 @@
+//! Compare time of rolling a command with and without a reusing arena
+//!
+//! Run with `cargo run --release --example roll_in`.
+
+use std::time::Instant;
+
+use gurgle::{Gurgle, RollArena};
+
+const ROUNDS: usize = 1_000_000;
+
+fn main() {
+    let gurgle = Gurgle::compile("4d6+2d8").unwrap();
+
+    let start = Instant::now();
+    let total: i64 = (0..ROUNDS).map(|_| gurgle.roll().value()).sum();
+    println!("roll().value(): {:?}, total {}", start.elapsed(), total);
+
+    let mut arena = RollArena::new();
+    let start = Instant::now();
+    let total: i64 = (0..ROUNDS).map(|_| gurgle.roll_in(&mut arena)).sum();
+    println!("roll_in(arena): {:?}, total {}", start.elapsed(), total);
+}
@@ -1,19 +1,37 @@
 //! check whether a roll result is a success
 
-use std::str::FromStr;
+use alloc::{
+    borrow::ToOwned,
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
 
 use pest::iterators::Pair;
 
+#[cfg(not(any(feature = "std", test)))]
+use crate::float::F64Ext;
 use crate::{
     config::Limit,
+    dist::Budget,
     error::{CompileError, ParseEnumError},
+    expr::AstTreeNode,
     parser::Rule,
 };
 
+// how many times an `and` checker moves at most when searching the nearest passing value
+const MAX_AND_SEARCH_STEPS: usize = 64;
+
 /// Compare operator in [`Checker`]
 ///
 /// [`Checker`]: struct.Checker.html
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Compare {
     /// Grater then or equal
     Gte,
@@ -25,6 +43,8 @@ pub enum Compare {
     Lt,
     /// Equal
     Eq,
+    /// Not equal
+    Ne,
 }
 
 impl FromStr for Compare {
@@ -37,6 +57,7 @@ impl FromStr for Compare {
             "<=" => Self::Lte,
             "<" => Self::Lt,
             "=" => Self::Eq,
+            "!=" => Self::Ne,
             _ => return Err(ParseEnumError),
         };
 
@@ -44,52 +65,433 @@ impl FromStr for Compare {
     }
 }
 
-/// Check if the result of rolling dice is a success(pass)
+impl Compare {
+    /// Check if comparing `result` to `target` gives this compare
+    pub(crate) fn matches(self, result: i64, target: i64) -> bool {
+        match result.cmp(&target) {
+            core::cmp::Ordering::Greater => core::matches!(self, Self::Gte | Self::Gt | Self::Ne),
+            core::cmp::Ordering::Less => core::matches!(self, Self::Lte | Self::Lt | Self::Ne),
+            core::cmp::Ordering::Equal => core::matches!(self, Self::Gte | Self::Lte | Self::Eq),
+        }
+    }
+}
+
+impl Display for Compare {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::Gte => ">=",
+            Self::Gt => ">",
+            Self::Lte => "<=",
+            Self::Lt => "<",
+            Self::Eq => "=",
+            Self::Ne => "!=",
+        })
+    }
+}
+
+/// Parity of a number, used in [`Checker::Parity`]
 ///
-/// `Checker` will compare gurgle execution result to [`target`].
-/// It's a success(pass) if compare result is as same as [`compare`] field.
+/// [`Checker::Parity`]: enum.Checker.html#variant.Parity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Parity {
+    /// Even number
+    Even,
+    /// Odd number
+    Odd,
+}
+
+impl FromStr for Parity {
+    type Err = ParseEnumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parity = match s {
+            "even" => Self::Even,
+            "odd" => Self::Odd,
+            _ => return Err(ParseEnumError),
+        };
+
+        Ok(parity)
+    }
+}
+
+impl Display for Parity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::Even => "even",
+            Self::Odd => "odd",
+        })
+    }
+}
+
+/// Check if the result of rolling dice is a success(pass)
 ///
 /// ## Example
 ///
-/// In gurgle command `3d6 > 10`: `>` is the [`compare`] and `10` is the [`target`].
+/// In gurgle command `3d6 > 10`, checker is a [`Compare`]: `>` is the `compare` and `10` is the `target`.
 /// When sum of 3 dice result grater then 10, it's a success(pass).
 ///
-/// [`compare`]: #structfield.compare
-/// [`target`]: #structfield.target
+/// In gurgle command `3d6 =~1 10`, `1` is the `tolerance`, result `9`, `10` and `11` are all success(pass).
+///
+/// In gurgle command `3d6 even`, checker is a [`Parity`], it's a success(pass) when result is a even number.
+///
+/// In gurgle command `3d6 in 8..12`, checker is a [`Range`], it's a success(pass) when result is between `8` and `12`,
+/// both boundaries included.
+///
+/// In gurgle command `3d6 >= avg(2d4+5)`, `target` is the expected value of `2d4+5`, which is calculated when compiling
+/// and rounded down(floor), so it's `10`, the same as `3d6 >= 10`.
+///
+/// Checkers can be combined by [`And`] and [`Or`], like `1d20 >= 15 and != 17` or `1d20 < 5 or > 15`,
+/// `and` binds tighter than `or`, so `a or b and c` means `a or (b and c)`.
+///
+/// [`Compare`]: #variant.Compare
+/// [`Parity`]: #variant.Parity
+/// [`Range`]: #variant.Range
+/// [`And`]: #variant.And
+/// [`Or`]: #variant.Or
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Checker {
-    /// wanted compare result
-    pub compare: Compare,
-    /// target value
-    pub target: i64,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Checker {
+    /// Compare result to a target value
+    Compare {
+        /// wanted compare result
+        compare: Compare,
+        /// target value
+        target: i64,
+        /// max distance to target which is still treated as equal, boundary included, only used by [`Eq`]
+        ///
+        /// [`Eq`]: enum.Compare.html#variant.Eq
+        tolerance: u64,
+    },
+    /// Check parity of result
+    Parity(Parity),
+    /// Check if result is in a range, both boundaries included, `min` is never greater than `max`
+    Range {
+        /// lower boundary
+        min: i64,
+        /// upper boundary
+        max: i64,
+    },
+    /// Success if both checkers pass
+    And(Box<Self>, Box<Self>),
+    /// Success if any of the checkers passes
+    Or(Box<Self>, Box<Self>),
 }
 
 impl Checker {
-    pub(crate) fn from_pair(pair: Pair<'_, Rule>, limit: &Limit<'_>) -> Result<Self, CompileError> {
+    pub(crate) fn from_pair(
+        pair: Pair<'_, Rule>, limit: &mut Limit<'_>,
+    ) -> Result<Self, CompileError> {
         assert_eq!(pair.as_rule(), Rule::checker);
 
+        // `and` binds tighter, so every `or` finishes a chain of `and`
+        let mut pairs = pair.into_inner();
+        let mut any: Option<Self> = None;
+        let mut all = Self::from_check_pair(pairs.next().unwrap(), limit)?;
+        while let Some(op) = pairs.next() {
+            let right = Self::from_check_pair(pairs.next().unwrap(), limit)?;
+            if op.as_rule() == Rule::check_and {
+                all = Self::And(Box::new(all), Box::new(right));
+            } else {
+                any = Some(match any {
+                    None => all,
+                    Some(left) => Self::Or(Box::new(left), Box::new(all)),
+                });
+                all = right;
+            }
+        }
+        Ok(match any {
+            None => all,
+            Some(left) => Self::Or(Box::new(left), Box::new(all)),
+        })
+    }
+
+    fn from_check_pair(pair: Pair<'_, Rule>, limit: &mut Limit<'_>) -> Result<Self, CompileError> {
+        assert_eq!(pair.as_rule(), Rule::check);
+
         let mut pairs = pair.into_inner();
-        let compare = pairs.next().unwrap().as_str().parse().unwrap();
-        let target = pairs.next().unwrap().as_str().parse::<i64>()?;
+        let first = pairs.next().unwrap();
+        if first.as_rule() == Rule::parity {
+            return Ok(Self::Parity(first.as_str().parse().unwrap()));
+        }
+        if first.as_rule() == Rule::range {
+            return Self::range_from_pair(first, limit);
+        }
+
+        let compare = first.as_str().parse().unwrap();
+        let mut target_pair = pairs.next().unwrap();
+        let mut tolerance = 0;
+        if target_pair.as_rule() == Rule::tolerance {
+            let x = target_pair.as_str().parse::<i64>()?;
+            limit.check_number_item(x)?;
+            tolerance = x.unsigned_abs();
+            target_pair = pairs.next().unwrap();
+        }
+        let target = if target_pair.as_rule() == Rule::average {
+            Self::average_target(target_pair, limit)?
+        } else {
+            limit.parse_number(target_pair.as_str())?
+        };
 
         limit.check_number_item(target)?;
 
-        Ok(Self { compare, target })
+        Ok(Self::Compare {
+            compare,
+            target,
+            tolerance,
+        })
+    }
+
+    fn range_from_pair(pair: Pair<'_, Rule>, limit: &Limit<'_>) -> Result<Self, CompileError> {
+        let mut pairs = pair.into_inner();
+        let mut boundary = || {
+            let x = limit.parse_number(pairs.next().unwrap().as_str())?;
+            limit.check_number_item(x)?;
+            Ok::<_, CompileError>(x)
+        };
+        let (min, max) = (boundary()?, boundary()?);
+        if min > max {
+            return Err(CompileError::EmptyCheckRange);
+        }
+        Ok(Self::Range { min, max })
+    }
+
+    // items in target expr are counted into limit, because calculating its distribution may be costly
+    fn average_target(pair: Pair<'_, Rule>, limit: &mut Limit<'_>) -> Result<i64, CompileError> {
+        let parentheses = pair.into_inner().next().unwrap();
+        let expr = AstTreeNode::from_parentheses_pair(parentheses, limit)?;
+        let mean = expr
+            .moments(&mut Budget::new(limit.max_distribution_steps()))
+            .ok_or(CompileError::TargetExpectationTooComplex)?
+            .mean;
+        Ok(mean.floor() as i64)
     }
 
     /// Check if a rolling result is a success.
     #[must_use]
     pub fn check(&self, result: i64) -> bool {
-        match result.cmp(&self.target) {
-            std::cmp::Ordering::Greater => {
-                std::matches!(self.compare, Compare::Gte | Compare::Gt)
+        match self {
+            Self::Compare {
+                compare: Compare::Eq,
+                target,
+                tolerance,
+            } => result.abs_diff(*target) <= *tolerance,
+            Self::Compare {
+                compare, target, ..
+            } => compare.matches(result, *target),
+            Self::Parity(Parity::Even) => result % 2 == 0,
+            Self::Parity(Parity::Odd) => result % 2 != 0,
+            Self::Range { min, max } => (*min..=*max).contains(&result),
+            Self::And(a, b) => a.check(result) && b.check(result),
+            Self::Or(a, b) => a.check(result) || b.check(result),
+        }
+    }
+
+    /// Get the smallest change of `result` which makes a failed check pass, and keeps it in `min..=max`
+    ///
+    /// It's `None` if check passed, or no value in `min..=max` passes. Positive one is used if both directions
+    /// need the same change.
+    pub(crate) fn shortfall(&self, result: i64, min: i64, max: i64) -> Option<i64> {
+        if self.check(result) {
+            return None;
+        }
+        let up = self.nearest(result, true).filter(|x| *x <= max);
+        let down = self.nearest(result, false).filter(|x| *x >= min);
+        let diff = |x: i64| x.saturating_sub(result);
+        match (up, down) {
+            (Some(up), Some(down)) if diff(down).unsigned_abs() < diff(up).unsigned_abs() => {
+                Some(diff(down))
+            }
+            (Some(up), _) => Some(diff(up)),
+            (None, down) => down.map(diff),
+        }
+    }
+
+    // the nearest value from `x` which passes the check, searching upward if `up`, or downward
+    #[allow(clippy::cast_possible_wrap)] // because tolerance is limited like a number item
+    fn nearest(&self, x: i64, up: bool) -> Option<i64> {
+        let within = |low: i64, high: i64| {
+            if up {
+                (x <= high).then(|| x.max(low))
+            } else {
+                (x >= low).then(|| x.min(high))
+            }
+        };
+        let next = || {
+            if up {
+                x.saturating_add(1)
+            } else {
+                x.saturating_sub(1)
+            }
+        };
+        match self {
+            Self::Compare {
+                compare,
+                target,
+                tolerance,
+            } => match compare {
+                Compare::Gte => within(*target, i64::MAX),
+                Compare::Gt => within(target.saturating_add(1), i64::MAX),
+                Compare::Lte => within(i64::MIN, *target),
+                Compare::Lt => within(i64::MIN, target.saturating_sub(1)),
+                Compare::Eq => within(
+                    target.saturating_sub(*tolerance as i64),
+                    target.saturating_add(*tolerance as i64),
+                ),
+                Compare::Ne if x == *target => Some(next()),
+                Compare::Ne => Some(x),
+            },
+            Self::Parity(_) if self.check(x) => Some(x),
+            Self::Parity(_) => Some(next()),
+            Self::Range { min, max } => within(*min, *max),
+            // move to the nearest passing value of every side in turn, until both sides pass,
+            // steps are limited so a check like `even and odd` does not search forever
+            Self::And(a, b) => {
+                let mut x = x;
+                for _ in 0..MAX_AND_SEARCH_STEPS {
+                    x = b.nearest(a.nearest(x, up)?, up)?;
+                    if self.check(x) {
+                        return Some(x);
+                    }
+                }
+                None
+            }
+            Self::Or(a, b) => match (a.nearest(x, up), b.nearest(x, up)) {
+                (Some(a), Some(b)) if up => Some(a.min(b)),
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            },
+        }
+    }
+
+    // checker is shown like a word after a space, like `3d6 even`, instead of operator like `3d6>10`
+    #[cfg(feature = "detail")]
+    pub(crate) fn is_word(&self) -> bool {
+        match self {
+            Self::Compare { .. } => false,
+            Self::Parity(_) | Self::Range { .. } => true,
+            Self::And(first, _) | Self::Or(first, _) => first.is_word(),
+        }
+    }
+
+    // `!=` right after an exploding dice is ambiguous, `1d20!` and `!=1` would be read as `1d20!!` and `=1`,
+    // so it needs a space before it in command too
+    pub(crate) fn needs_space(&self) -> bool {
+        match self {
+            Self::Compare { compare, .. } => *compare == Compare::Ne,
+            Self::Parity(_) | Self::Range { .. } => true,
+            Self::And(first, _) | Self::Or(first, _) => first.needs_space(),
+        }
+    }
+}
+
+impl Display for Checker {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Compare {
+                compare: Compare::Eq,
+                target,
+                tolerance,
+            } if *tolerance > 0 => {
+                f.write_fmt(format_args!("{}~{} {}", Compare::Eq, tolerance, target))
             }
-            std::cmp::Ordering::Less => {
-                std::matches!(self.compare, Compare::Lte | Compare::Lt)
+            Self::Compare {
+                compare, target, ..
+            } => f.write_fmt(format_args!("{}{}", compare, target)),
+            Self::Parity(parity) => f.write_fmt(format_args!("{}", parity)),
+            Self::Range { min, max } => f.write_fmt(format_args!("in {}..{}", min, max)),
+            Self::And(a, b) => {
+                // `or` inside `and` can't be written in a command, parentheses are only for reading
+                let operand = |c: &Self| match c {
+                    Self::Or(..) => format!("({})", c),
+                    _ => c.to_string(),
+                };
+                f.write_fmt(format_args!("{} and {}", operand(a), operand(b)))
             }
-            std::cmp::Ordering::Equal => {
-                std::matches!(self.compare, Compare::Gte | Compare::Lte | Compare::Eq)
+            Self::Or(a, b) => f.write_fmt(format_args!("{} or {}", a, b)),
+        }
+    }
+}
+
+/// A tier in [`TieredChecker`]
+///
+/// [`TieredChecker`]: struct.TieredChecker.html
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tier {
+    /// result should be greater than or equal to this value to reach this tier
+    pub threshold: i64,
+    /// name of this tier
+    pub label: String,
+}
+
+/// Map a rolling result to a named tier, instead of a simple success or failed
+///
+/// ## Example
+///
+/// In gurgle command `1d20+5 : 10=hit, 20=crit`, result `[10, 20)` is a `hit`,
+/// and result `>= 20` is a `crit`, result `< 10` reaches no tier.
+///
+/// When a result reaches more than one tier, the one with highest threshold wins.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TieredChecker {
+    tiers: Vec<Tier>,
+}
+
+impl TieredChecker {
+    /// Create a tiered checker, order of `tiers` doesn't matter.
+    ///
+    /// ## Errors
+    ///
+    /// If more than one tier have the same threshold.
+    pub fn new(mut tiers: Vec<Tier>) -> Result<Self, CompileError> {
+        tiers.sort_by_key(|t| t.threshold);
+        if tiers.windows(2).any(|w| w[0].threshold == w[1].threshold) {
+            return Err(CompileError::DuplicateTierThreshold);
+        }
+        Ok(Self { tiers })
+    }
+
+    pub(crate) fn from_pair(pair: Pair<'_, Rule>, limit: &Limit<'_>) -> Result<Self, CompileError> {
+        assert_eq!(pair.as_rule(), Rule::tiers);
+
+        let tiers = pair
+            .into_inner()
+            .map(|tier| {
+                let mut pairs = tier.into_inner();
+                let threshold = limit.parse_number(pairs.next().unwrap().as_str())?;
+                limit.check_number_item(threshold)?;
+                let label = pairs.next().unwrap().as_str().to_owned();
+                Ok(Tier { threshold, label })
+            })
+            .collect::<Result<_, CompileError>>()?;
+
+        Self::new(tiers)
+    }
+
+    /// Get all tiers, ordered by threshold from low to high
+    #[must_use]
+    pub fn tiers(&self) -> &[Tier] {
+        &self.tiers
+    }
+
+    /// Get the tier a rolling result reached
+    #[must_use]
+    pub fn tier(&self, result: i64) -> Option<&Tier> {
+        self.tiers.iter().rev().find(|t| result >= t.threshold)
+    }
+}
+
+impl Display for TieredChecker {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(":")?;
+        for (i, tier) in self.tiers.iter().enumerate() {
+            if i != 0 {
+                f.write_str(",")?;
             }
+            f.write_fmt(format_args!(" {}={}", tier.threshold, tier.label))?;
         }
+        Ok(())
     }
 }
@@ -1,9 +1,17 @@
+use alloc::vec::Vec;
+
 use crate::error::CompileError;
+#[cfg(not(any(feature = "std", test)))]
+use crate::float::F64Ext;
 
 pub static DEFAULT_CONFIG: Config = Config::default();
 
 /// Gurgle command limitation configuration
+///
+/// `Config` is always `Hash + Eq`, so it can be used as (part of) a cache key of compiled commands,
+/// like `(source, config)`. New fields will keep this promise, no floating point number will be stored in it.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Config {
     /// How many items can a gurgle expression contains
     pub max_item_count: u64,
@@ -13,6 +21,63 @@ pub struct Config {
     pub max_roll_times: u64,
     /// Max value of a number item
     pub max_number_item_value: u64,
+    /// How many extra dice a single exploding die can roll at most, also how many times a die can roll again by reroll
+    pub max_explode_depth: u64,
+    /// Max magnitude of every operand and result of arithmetic when rolling, see [`Gurgle::try_roll`]
+    ///
+    /// [`Gurgle::try_roll`]: struct.Gurgle.html#method.try_roll
+    pub max_intermediate_magnitude: u64,
+    /// Which digit group separators are accepted in number literals, see [`DigitSeparator`]
+    ///
+    /// [`DigitSeparator`]: enum.DigitSeparator.html
+    pub digit_separator: DigitSeparator,
+    /// How many times a whole command can be repeated, like `6` in `6#(3d6) each`
+    pub max_command_repeat: u64,
+    /// How many calculation steps can be used to get the exact distribution of a command,
+    /// see [`Gurgle::distribution`]
+    ///
+    /// [`Gurgle::distribution`]: struct.Gurgle.html#method.distribution
+    pub max_distribution_steps: u64,
+    /// How deep can parentheses be nested, like `2` for `((1d6+1)*2)+1`
+    pub max_paren_depth: u64,
+}
+
+/// Digit group separators accepted in number literals, like `1_000`
+///
+/// Separators are only for readability, they are stripped before the number is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DigitSeparator {
+    /// No separator is accepted
+    Disabled,
+    /// `_` between any digits, like `1_000` or `10_00`
+    Underscore,
+    /// `_` as above, and also `,` before every group of three digits, like `1,000,000`
+    UnderscoreOrComma,
+}
+
+/// A field which has different values in two configs, see [`Config::diff`]
+///
+/// [`Config::diff`]: struct.Config.html#method.diff
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConfigFieldDiff {
+    /// Field name, like `max_roll_times`
+    pub field: &'static str,
+    /// Value in the config `diff` is called on
+    pub left: ConfigFieldValue,
+    /// Value in the other config
+    pub right: ConfigFieldValue,
+}
+
+/// Value of a config field in [`ConfigFieldDiff`]
+///
+/// [`ConfigFieldDiff`]: struct.ConfigFieldDiff.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigFieldValue {
+    /// A limit, like `max_roll_times`
+    Limit(u64),
+    /// Value of `digit_separator`
+    DigitSeparator(DigitSeparator),
 }
 
 impl Default for Config {
@@ -28,6 +93,12 @@ impl Config {
     /// - max dice sides: 1000
     /// - max roll times: 100
     /// - max number item: 65536
+    /// - max explode depth: 10
+    /// - max intermediate magnitude: `u64::MAX`, that is, only overflow is rejected
+    /// - digit separator: `_` only
+    /// - max command repeat: 20
+    /// - max distribution steps: 10000000
+    /// - max parentheses depth: 16
     #[must_use]
     pub const fn default() -> Self {
         Self {
@@ -35,6 +106,12 @@ impl Config {
             max_dice_sides: 1000,
             max_roll_times: 100,
             max_number_item_value: 65536,
+            max_explode_depth: 10,
+            max_intermediate_magnitude: u64::MAX,
+            digit_separator: DigitSeparator::Underscore,
+            max_command_repeat: 20,
+            max_distribution_steps: crate::dist::DEFAULT_BUDGET,
+            max_paren_depth: 16,
         }
     }
 
@@ -73,12 +150,155 @@ impl Config {
             ..self
         }
     }
+
+    /// Give a new config, which only changes max explode depth with provided value.
+    #[must_use]
+    pub const fn max_explode_depth(self, c: u64) -> Self {
+        Self {
+            max_explode_depth: c,
+            ..self
+        }
+    }
+
+    /// Give a new config, which only changes max intermediate magnitude with provided value.
+    #[must_use]
+    pub const fn max_intermediate_magnitude(self, c: u64) -> Self {
+        Self {
+            max_intermediate_magnitude: c,
+            ..self
+        }
+    }
+
+    /// Give a new config, which only changes accepted digit separator with provided value.
+    #[must_use]
+    pub const fn digit_separator(self, s: DigitSeparator) -> Self {
+        Self {
+            digit_separator: s,
+            ..self
+        }
+    }
+
+    /// Give a new config, which only changes max command repeat with provided value.
+    #[must_use]
+    pub const fn max_command_repeat(self, c: u64) -> Self {
+        Self {
+            max_command_repeat: c,
+            ..self
+        }
+    }
+
+    /// Give a new config, which only changes max distribution steps with provided value.
+    #[must_use]
+    pub const fn max_distribution_steps(self, c: u64) -> Self {
+        Self {
+            max_distribution_steps: c,
+            ..self
+        }
+    }
+
+    /// Give a new config, which only changes max parentheses depth with provided value.
+    #[must_use]
+    pub const fn max_paren_depth(self, c: u64) -> Self {
+        Self {
+            max_paren_depth: c,
+            ..self
+        }
+    }
+
+    /// Compare with `other` config, get every field which has different values, in field declaration order.
+    ///
+    /// The result is empty if and only if two configs are equal.
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> Vec<ConfigFieldDiff> {
+        let limit = |field, get: fn(&Self) -> u64| {
+            let value = |config| ConfigFieldValue::Limit(get(config));
+            (field, value(self), value(other))
+        };
+        [
+            limit("max_item_count", |c| c.max_item_count),
+            limit("max_dice_sides", |c| c.max_dice_sides),
+            limit("max_roll_times", |c| c.max_roll_times),
+            limit("max_number_item_value", |c| c.max_number_item_value),
+            limit("max_explode_depth", |c| c.max_explode_depth),
+            limit("max_intermediate_magnitude", |c| {
+                c.max_intermediate_magnitude
+            }),
+            (
+                "digit_separator",
+                ConfigFieldValue::DigitSeparator(self.digit_separator),
+                ConfigFieldValue::DigitSeparator(other.digit_separator),
+            ),
+            limit("max_command_repeat", |c| c.max_command_repeat),
+            limit("max_distribution_steps", |c| c.max_distribution_steps),
+            limit("max_paren_depth", |c| c.max_paren_depth),
+        ]
+        .iter()
+        .filter(|(_, left, right)| left != right)
+        .map(|&(field, left, right)| ConfigFieldDiff { field, left, right })
+        .collect()
+    }
+
+    /// Give a new config, with all limits multiplied by `factor`.
+    ///
+    /// Result limits are saturated at `u64::MAX`, and never go below 1.
+    #[must_use]
+    pub const fn scale_up(self, factor: u64) -> Self {
+        const fn mul(limit: u64, factor: u64) -> u64 {
+            let x = limit.saturating_mul(factor);
+            if x == 0 {
+                1
+            } else {
+                x
+            }
+        }
+
+        Self {
+            max_item_count: mul(self.max_item_count, factor),
+            max_dice_sides: mul(self.max_dice_sides, factor),
+            max_roll_times: mul(self.max_roll_times, factor),
+            max_number_item_value: mul(self.max_number_item_value, factor),
+            max_explode_depth: mul(self.max_explode_depth, factor),
+            max_intermediate_magnitude: mul(self.max_intermediate_magnitude, factor),
+            digit_separator: self.digit_separator,
+            max_command_repeat: mul(self.max_command_repeat, factor),
+            max_distribution_steps: mul(self.max_distribution_steps, factor),
+            max_paren_depth: mul(self.max_paren_depth, factor),
+        }
+    }
+
+    /// Give a new config, with all limits multiplied by `factor` and rounded, like `scale(0.5)` for halving.
+    ///
+    /// Result limits are saturated at `u64::MAX`, and never go below 1, a NaN `factor` is treated as zero.
+    #[must_use]
+    pub fn scale(self, factor: f64) -> Self {
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            clippy::cast_precision_loss
+        )]
+        // because float to int cast is saturating, and precision of huge limit is not important
+        let mul = |limit: u64| ((limit as f64 * factor).round() as u64).max(1);
+
+        Self {
+            max_item_count: mul(self.max_item_count),
+            max_dice_sides: mul(self.max_dice_sides),
+            max_roll_times: mul(self.max_roll_times),
+            max_number_item_value: mul(self.max_number_item_value),
+            max_explode_depth: mul(self.max_explode_depth),
+            max_intermediate_magnitude: mul(self.max_intermediate_magnitude),
+            digit_separator: self.digit_separator,
+            max_command_repeat: mul(self.max_command_repeat),
+            max_distribution_steps: mul(self.max_distribution_steps),
+            max_paren_depth: mul(self.max_paren_depth),
+        }
+    }
 }
 
 pub struct Limit<'c> {
     config: &'c Config,
     pub item_count: u64,
     pub roll_times: u64,
+    pub paren_depth: u64,
 }
 
 impl<'c> Limit<'c> {
@@ -87,6 +307,7 @@ impl<'c> Limit<'c> {
             config,
             item_count: 0,
             roll_times: 0,
+            paren_depth: 0,
         }
     }
 
@@ -96,17 +317,116 @@ impl<'c> Limit<'c> {
     }
 
     pub fn inc_roll_times(&mut self, times: u64) -> Result<(), CompileError> {
-        self.roll_times += times;
+        self.roll_times = self.roll_times.saturating_add(times);
         self.check_roll_times()
     }
 
+    /// Charge items and roll times used by one repetition of an item for `extra` more repetitions
+    pub fn inc_repeated(&mut self, items: u64, rolls: u64, extra: u64) -> Result<(), CompileError> {
+        self.inc_roll_times(rolls.saturating_mul(extra))?;
+        self.item_count = self.item_count.saturating_add(items.saturating_mul(extra));
+        self.check_item_count()
+    }
+
+    pub const fn enter_parentheses(&mut self) -> Result<(), CompileError> {
+        self.paren_depth += 1;
+        self.check_paren_depth(self.paren_depth)
+    }
+
+    pub const fn exit_parentheses(&mut self) {
+        self.paren_depth -= 1;
+    }
+
+    /// Check nesting depth of parentheses in source command before parsing, so parser does not recurse too deep
+    pub fn check_source_paren_depth(&self, s: &str) -> Result<(), CompileError> {
+        let mut depth = 0_u64;
+        let mut in_label = false;
+        for c in s.chars() {
+            match c {
+                '[' => in_label = true,
+                ']' => in_label = false,
+                '(' if !in_label => {
+                    depth += 1;
+                    self.check_paren_depth(depth)?;
+                }
+                ')' if !in_label => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    const fn check_paren_depth(&self, depth: u64) -> Result<(), CompileError> {
+        if depth > self.config.max_paren_depth {
+            return Err(CompileError::ParenthesesDepthLimitExceeded);
+        }
+        Ok(())
+    }
+
+    pub const fn max_explode_depth(&self) -> u64 {
+        self.config.max_explode_depth
+    }
+
+    pub const fn max_intermediate_magnitude(&self) -> u64 {
+        self.config.max_intermediate_magnitude
+    }
+
+    pub const fn max_distribution_steps(&self) -> u64 {
+        self.config.max_distribution_steps
+    }
+
+    /// Parse a number literal, stripping digit separators allowed by config
+    pub fn parse_number(&self, s: &str) -> Result<i64, CompileError> {
+        let (underscore, comma) = match self.config.digit_separator {
+            DigitSeparator::Disabled => (false, false),
+            DigitSeparator::Underscore => (true, false),
+            DigitSeparator::UnderscoreOrComma => (true, true),
+        };
+        let has_underscore = s.contains('_');
+        let has_comma = s.contains(',');
+        if (has_underscore && !underscore) || (has_comma && !comma) {
+            return Err(CompileError::DigitSeparatorNotAllowed);
+        }
+        if has_underscore || has_comma {
+            Ok(s.replace(&['_', ','][..], "").parse()?)
+        } else {
+            Ok(s.parse()?)
+        }
+    }
+
     pub const fn check_number_item(&self, num: i64) -> Result<(), CompileError> {
-        if num.abs() as u64 > self.config.max_number_item_value {
+        if num.unsigned_abs() > self.config.max_number_item_value {
             return Err(CompileError::NumberItemOutOfRange);
         }
         Ok(())
     }
 
+    pub const fn check_command_repeat(&self, n: i64) -> Result<(), CompileError> {
+        if n <= 0 {
+            return Err(CompileError::RepeatCountNotPositive);
+        }
+        #[allow(clippy::cast_sign_loss)] // because n > 0
+        if n as u64 > self.config.max_command_repeat {
+            return Err(CompileError::CommandRepeatLimitExceeded);
+        }
+        Ok(())
+    }
+
+    pub const fn check_repeat(&self, n: i64) -> Result<(), CompileError> {
+        if n <= 0 {
+            return Err(CompileError::RepeatCountNotPositive);
+        }
+        self.check_dice(n, 1)
+    }
+
+    pub const fn check_select_count(&self, n: i64) -> Result<(), CompileError> {
+        #[allow(clippy::cast_sign_loss)] // because select count has no sign in syntax
+        if n as u64 > self.config.max_roll_times {
+            return Err(CompileError::DiceRollTimesLimitExceeded);
+        }
+        Ok(())
+    }
+
     pub const fn check_dice(&self, times: i64, sided: i64) -> Result<(), CompileError> {
         if times <= 0 || sided <= 0 {
             return Err(CompileError::DiceRollOrSidedNegative);
@@ -3,29 +3,31 @@
 use std::{
     borrow::Cow,
     fmt::{Display, Formatter, Write},
-    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+    sync::{Arc, PoisonError, RwLock},
 };
 
 use once_cell::sync::Lazy;
 
 use crate::{
     checker::{Checker, Compare},
-    expr::{Operator, PostProcessor},
-    roll::{DiceRoll, GurgleRoll, ItemRoll, RollTree, RollTreeNode},
+    expr::PostProcessor,
+    roll::{
+        BatchRoll, DiceRoll, GurgleRoll, ItemRoll, RepeatRoll, RollTree, RollTreeNode, SortOrder,
+    },
 };
 
-static WANTED_LANG: AtomicUsize = AtomicUsize::new(Language::EN.value());
-static CUSTOM_LANG_PTR: AtomicPtr<OutputSpans> =
-    AtomicPtr::new(std::ptr::null::<OutputSpans>() as *mut _);
+// global language is only read when formatting, so changing it does not affect outputs in progress
+static LANG: Lazy<RwLock<Arc<OutputSpans>>> =
+    Lazy::new(|| RwLock::new(Arc::new(OutputSpans::new_en())));
 
-static LANG: Lazy<Cow<'static, OutputSpans>> =
-    Lazy::new(
-        || match Language::from_value(WANTED_LANG.load(Ordering::SeqCst)) {
-            Language::EN => Cow::Owned(OutputSpans::new_en()),
-            Language::ZhCN => Cow::Owned(OutputSpans::new_zh_cn()),
-            Language::Custom => Cow::Borrowed(Language::get_global_custom().unwrap()),
-        },
-    );
+fn global_lang() -> Arc<OutputSpans> {
+    // spans are replaced as a whole, so a poisoned lock still holds a valid value
+    Arc::clone(&LANG.read().unwrap_or_else(PoisonError::into_inner))
+}
+
+fn set_global_lang(spans: OutputSpans) {
+    *LANG.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(spans);
+}
 
 /// Rolling result detailed output language
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -41,63 +43,35 @@ pub enum Language {
 }
 
 impl Language {
-    const fn value(&self) -> usize {
-        match *self {
-            Self::EN => 0,
-            Self::ZhCN => 1,
-            Self::Custom => 999,
-        }
-    }
-
-    fn from_value(value: usize) -> Self {
-        match value {
-            0 => Self::EN,
-            1 => Self::ZhCN,
-            999 => Self::Custom,
-            _ => panic!("Can't convert {} into Language", value),
-        }
-    }
-
     /// Set a predefined language to be used globally
     ///
-    /// You can call this method more then once, only the last value set before the first output will be used.
+    /// You can call this method any times, outputs after it use the new language.
+    /// To output in a language without changing the global one, see [`GurgleRoll::display_with`].
     ///
     /// ## Panics
     ///
     /// If `lang` is `Language::Custom`
+    ///
+    /// [`GurgleRoll::display_with`]: ../roll/struct.GurgleRoll.html#method.display_with
     #[allow(clippy::needless_pass_by_value)] // because language is copy
     pub fn set_global(lang: Self) {
-        if lang == Self::Custom {
-            panic!("Call set global with custom is invalid, you should use `set_global_custom` instead");
-        }
-
-        WANTED_LANG.store(lang.value(), Ordering::SeqCst);
+        let spans = match lang {
+            Self::EN => OutputSpans::new_en(),
+            Self::ZhCN => OutputSpans::new_zh_cn(),
+            Self::Custom => panic!(
+                "Call set global with custom is invalid, you should use `set_global_custom` instead"
+            ),
+        };
+        set_global_lang(spans);
     }
 
     /// Set a custom language to be used globally
     ///
-    /// You can call this method only once.
-    ///
-    /// ## Panics
+    /// Like [`set_global`], you can call this method any times, outputs after it use the new language.
     ///
-    /// If you call this more than once
+    /// [`set_global`]: #method.set_global
     pub fn set_global_custom(s: OutputSpans) {
-        WANTED_LANG.store(Self::Custom.value(), Ordering::SeqCst);
-
-        let p = Box::into_raw(Box::new(s));
-        let last = CUSTOM_LANG_PTR.swap(p, Ordering::SeqCst);
-        if !last.is_null() {
-            panic!("`set_global_custom` can only be called once");
-        }
-    }
-
-    fn get_global_custom() -> Option<&'static OutputSpans> {
-        let p = CUSTOM_LANG_PTR.load(Ordering::SeqCst);
-        if p.is_null() {
-            None
-        } else {
-            Some(unsafe { &*p })
-        }
+        set_global_lang(s);
     }
 }
 
@@ -138,94 +112,598 @@ impl OutputSpans {
     }
 }
 
-impl Display for Checker {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str(match self.compare {
-            Compare::Gte => ">=",
-            Compare::Gt => ">",
-            Compare::Lte => "<=",
-            Compare::Lt => "<",
-            Compare::Eq => "=",
-        })?;
-        f.write_fmt(format_args!("{}", self.target))
+fn checker_step(c: &Checker) -> String {
+    match c {
+        Checker::Compare {
+            compare: Compare::Eq,
+            target,
+            tolerance,
+        } if *tolerance > 0 => format!("{} {} ±{}", Compare::Eq, target, tolerance),
+        Checker::Compare {
+            compare, target, ..
+        } => format!("{} {}", compare, target),
+        Checker::Parity(_) | Checker::Range { .. } => c.to_string(),
+        Checker::And(a, b) => {
+            let operand = |c: &Checker| match c {
+                Checker::Or(..) => format!("({})", checker_step(c)),
+                _ => checker_step(c),
+            };
+            format!("{} and {}", operand(a), operand(b))
+        }
+        Checker::Or(a, b) => format!("{} or {}", checker_step(a), checker_step(b)),
+    }
+}
+
+fn round_delimiters(pp: PostProcessor) -> (Cow<'static, str>, &'static str, &'static str) {
+    match pp {
+        PostProcessor::Sum => ("".into(), "+", ""),
+        PostProcessor::Avg => ("Avg[".into(), ",", "]"),
+        PostProcessor::Max => ("Max[".into(), ",", "]"),
+        PostProcessor::Min => ("Min[".into(), ",", "]"),
+        PostProcessor::ScaledAvg(factor) => (format!("Avg{}[", factor).into(), ",", "]"),
+        PostProcessor::KeepHighest(n) => (format!("Kh{}[", n).into(), ",", "]"),
+        PostProcessor::KeepLowest(n) => (format!("Kl{}[", n).into(), ",", "]"),
+        PostProcessor::DropHighest(n) => (format!("Dh{}[", n).into(), ",", "]"),
+        PostProcessor::DropLowest(n) => (format!("Dl{}[", n).into(), ",", "]"),
+        PostProcessor::Advantage => ("Adv[".into(), ",", "]"),
+        PostProcessor::Disadvantage => ("Dis[".into(), ",", "]"),
+        PostProcessor::CountSuccesses { compare, target } => {
+            (format!("Count{}{}[", compare, target).into(), ",", "]")
+        }
+    }
+}
+
+impl DiceRoll {
+    fn delimiters(&self) -> (Cow<'static, str>, &'static str, &'static str) {
+        if self.is_fudge() {
+            ("F[".into(), ",", "]")
+        } else {
+            round_delimiters(self.post_processor())
+        }
+    }
+
+    // fudge dice use only one delimiter between points, so value is shown like post processed ones
+    fn shows_value(&self) -> bool {
+        self.is_fudge() || self.post_processor() != PostProcessor::Sum
+    }
+
+    fn face(&self, point: u64) -> String {
+        if self.is_fudge() {
+            match point {
+                1 => "-".into(),
+                2 => "0".into(),
+                _ => "+".into(),
+            }
+        } else {
+            point.to_string()
+        }
+    }
+
+    // exploded point is marked like `6!`, rerolled point is shown like `1→5`,
+    // success point of a dice pool is marked like `9*`, point not kept by selection is marked like `~1~`
+    fn faces(&self) -> Vec<String> {
+        self.points()
+            .iter()
+            .zip(self.exploded())
+            .zip(self.rerolled())
+            .zip(self.clamped())
+            .zip(self.kept())
+            .zip(self.successes())
+            .map(|(((((p, exploded), rerolled), clamped), kept), success)| {
+                let mut face = String::new();
+                for discarded in rerolled.iter().chain(clamped) {
+                    face.push_str(&self.face(*discarded));
+                    face.push('→');
+                }
+                face.push_str(&self.face(*p));
+                if *exploded {
+                    face.push('!');
+                }
+                if success {
+                    face.push('*');
+                }
+                if kept {
+                    face
+                } else {
+                    format!("~{}~", face)
+                }
+            })
+            .collect()
     }
 }
 
 impl Display for DiceRoll {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let (prefix, mid, postfix) = match self.post_processor() {
-            PostProcessor::Sum => ("", "+", ""),
-            PostProcessor::Avg => ("Avg[", ",", "]"),
-            PostProcessor::Max => ("Max[", ",", "]"),
-            PostProcessor::Min => ("Min[", ",", "]"),
-        };
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.write_faces(f, self.faces())
+    }
+}
+
+/// Output of a dice rolling result with points sorted, see [`DiceRoll::display_sorted`]
+///
+/// [`DiceRoll::display_sorted`]: ../roll/struct.DiceRoll.html#method.display_sorted
+#[derive(Debug)]
+pub struct DisplaySorted<'a> {
+    dice: &'a DiceRoll,
+    order: SortOrder,
+}
+
+impl Display for DisplaySorted<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let mut faces = self.dice.faces().into_iter().map(Some).collect::<Vec<_>>();
+        let sorted = self
+            .dice
+            .sorted_indices(self.order)
+            .into_iter()
+            .filter_map(|i| faces[i].take())
+            .collect::<Vec<_>>();
+        self.dice.write_faces(f, sorted)
+    }
+}
+
+impl DiceRoll {
+    /// Get output of this rolling result with points sorted in `order`, like `(Dl1[~1~,3,5,6]=14)` for `4d6dl1`
+    ///
+    /// Marks of every point like explosion and reroll go with the point. It's only for showing,
+    /// [`points`] and [`value`] are not changed.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use gurgle::{roll::SortOrder, Gurgle};
+    ///
+    /// let pool = Gurgle::compile("8d6").unwrap();
+    /// let result = pool.roll();
+    /// let dice = result.dice_rolls().next().unwrap();
+    /// println!("{}", dice.display_sorted(SortOrder::Descending));
+    /// ```
+    ///
+    /// [`points`]: #method.points
+    /// [`value`]: #method.value
+    #[must_use]
+    pub const fn display_sorted(&self, order: SortOrder) -> DisplaySorted<'_> {
+        DisplaySorted { dice: self, order }
+    }
+
+    fn write_faces<O: Output + ?Sized>(
+        &self, out: &mut O, faces: Vec<String>,
+    ) -> core::fmt::Result {
+        let (prefix, mid, postfix) = self.delimiters();
 
-        f.write_char('(')?;
-        f.write_str(prefix)?;
-        let last = self.len() - 1;
-        for (i, value) in self.points().iter().enumerate() {
-            f.write_fmt(format_args!("{}", value))?;
-            if i != last {
-                f.write_str(mid)?;
+        if let Some(times) = self.times_roll() {
+            if times.is_tree() {
+                out.plain("(")?;
+                times.write_to(out)?;
+                out.plain(")")?;
+            } else {
+                times.write_to(out)?;
             }
+            out.plain("d")?;
         }
-        f.write_str(postfix)?;
-        if self.post_processor() != PostProcessor::Sum {
-            f.write_fmt(format_args!("={}", self.value()))?;
+
+        out.plain("(")?;
+        out.plain(&prefix)?;
+        for (i, face) in faces.into_iter().enumerate() {
+            if i != 0 {
+                out.plain(mid)?;
+            }
+            out.span(SpanKind::DiceFace, &face)?;
+        }
+        out.plain(postfix)?;
+        if self.shows_value() {
+            out.plain("=")?;
+            out.span(SpanKind::Number, &self.value())?;
         }
-        f.write_char(')')
+        out.plain(")")
     }
 }
 
-impl Display for ItemRoll {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl ItemRoll {
+    fn write_to<O: Output + ?Sized>(&self, out: &mut O) -> core::fmt::Result {
         match self {
-            Self::Number(x) => f.write_fmt(format_args!("{}", x)),
-            Self::Dice(dice) => f.write_fmt(format_args!("{}", dice)),
-            Self::Parentheses(e) => f.write_fmt(format_args!("({})", e.as_ref())),
+            Self::Number(x) => out.span(SpanKind::Number, x),
+            Self::Dice(dice) => dice.write_faces(out, dice.faces()),
+            Self::Parentheses(e) => {
+                out.plain("(")?;
+                e.write_to(out)?;
+                out.plain(")")
+            }
+            Self::Repeat(r) => r.write_to(out),
+            Self::Labeled(item, label) => {
+                item.write_to(out)?;
+                out.plain(" [")?;
+                out.plain(label)?;
+                out.plain("]")
+            }
+        }
+    }
+}
+
+impl Display for ItemRoll {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.write_to(f)
+    }
+}
+
+impl RepeatRoll {
+    fn write_to<O: Output + ?Sized>(&self, out: &mut O) -> core::fmt::Result {
+        let (prefix, mid, postfix) = round_delimiters(self.aggregate());
+
+        out.plain(&self.rolls().len())?;
+        out.plain("#(")?;
+        out.plain(&prefix)?;
+        for (i, roll) in self.rolls().iter().enumerate() {
+            if i != 0 {
+                out.plain(mid)?;
+            }
+            roll.write_to(out)?;
+        }
+        out.plain(postfix)?;
+        if self.aggregate() != PostProcessor::Sum {
+            out.plain("=")?;
+            out.span(SpanKind::Number, &self.value())?;
         }
+        out.plain(")")
+    }
+}
+
+impl Display for RepeatRoll {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.write_to(f)
+    }
+}
+
+impl RollTree {
+    fn write_to<O: Output + ?Sized>(&self, out: &mut O) -> core::fmt::Result {
+        self.left.write_to(out)?;
+        out.plain(" ")?;
+        out.span(SpanKind::Operator, &self.mid)?;
+        out.plain(" ")?;
+        self.right.write_to(out)
     }
 }
 
 impl Display for RollTree {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let op = match self.mid {
-            Operator::Add => "+",
-            Operator::Minus => "-",
-            Operator::Multiply => "*",
-        };
-        f.write_fmt(format_args!("{} {} {}", self.left, op, self.right))
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.write_to(f)
     }
 }
 
-impl Display for RollTreeNode {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl RollTreeNode {
+    fn write_to<O: Output + ?Sized>(&self, out: &mut O) -> core::fmt::Result {
         match self {
-            Self::Leaf(leaf) => f.write_fmt(format_args!("{}", leaf)),
-            Self::Tree(tree) => f.write_fmt(format_args!("{}", tree)),
+            Self::Leaf(leaf) => leaf.write_to(out),
+            Self::Tree(tree) => tree.write_to(out),
         }
     }
 }
 
+impl Display for RollTreeNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.write_to(f)
+    }
+}
+
 impl Display for GurgleRoll<'_> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{}", self.expr()))?;
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.write_detail(f, &global_lang())
+    }
+}
+
+/// Every rolling result on its own line
+impl Display for BatchRoll<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let spans = global_lang();
+        for (i, roll) in self.rolls().iter().enumerate() {
+            if i > 0 {
+                f.write_char('\n')?;
+            }
+            roll.write_detail(f, &spans)?;
+        }
+        Ok(())
+    }
+}
+
+/// Detailed output of a rolling result in a specified language, see [`GurgleRoll::display_with`]
+///
+/// [`GurgleRoll::display_with`]: ../roll/struct.GurgleRoll.html#method.display_with
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayWith<'a, 'g> {
+    roll: &'a GurgleRoll<'g>,
+    spans: &'a OutputSpans,
+}
+
+impl Display for DisplayWith<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.roll.write_detail(f, self.spans)
+    }
+}
 
-        if !std::matches!(self.expr(), RollTreeNode::Leaf(ItemRoll::Number(_))) {
-            f.write_fmt(format_args!(" = {}", self.value()))?;
+impl<'g> GurgleRoll<'g> {
+    /// Get detailed output of this rolling result in language `spans`, global language is not used nor changed
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use gurgle::{detail::OutputSpans, Gurgle};
+    ///
+    /// let attack = Gurgle::compile("1d20 > 25").unwrap();
+    /// let result = attack.roll();
+    /// assert!(result.display_with(&OutputSpans::new_zh_cn()).to_string().ends_with("，失败"));
+    /// ```
+    #[must_use]
+    pub const fn display_with<'a>(&'a self, spans: &'a OutputSpans) -> DisplayWith<'a, 'g> {
+        DisplayWith { roll: self, spans }
+    }
+
+    fn write_detail<O: Output + ?Sized>(
+        &self, out: &mut O, lang: &OutputSpans,
+    ) -> core::fmt::Result {
+        self.expr().write_to(out)?;
+
+        let modified = self.signed_delta();
+        if !core::matches!(self.expr(), RollTreeNode::Leaf(ItemRoll::Number(_))) {
+            let kind = if modified.is_some() {
+                SpanKind::Number
+            } else {
+                SpanKind::Total
+            };
+            out.plain(" = ")?;
+            out.span(kind, &self.raw_value())?;
+        }
+        if let Some((sign, delta)) = modified {
+            out.plain(" ")?;
+            out.span(SpanKind::Operator, &sign)?;
+            out.plain(" ")?;
+            out.span(SpanKind::Number, &delta)?;
+            out.plain(" = ")?;
+            out.span(SpanKind::Total, &self.value())?;
         }
 
         if let Some(c) = self.checker() {
-            f.write_str(&LANG.comma)?;
-            f.write_str(&LANG.target_is)?;
-            f.write_fmt(format_args!("{}", c))?;
-            f.write_str(&LANG.comma)?;
+            out.plain(&lang.comma)?;
+            out.plain(&lang.target_is)?;
+            if c.is_word() {
+                out.plain(" ")?;
+            }
+            out.plain(c)?;
+            out.plain(&lang.comma)?;
             if self.success().unwrap() {
-                f.write_str(&LANG.success)?;
+                out.span(SpanKind::Success, &lang.success)?;
+            } else {
+                out.span(SpanKind::Failure, &lang.failed)?;
+            }
+        }
+
+        if let Some(tier) = self.tier() {
+            out.plain(&lang.comma)?;
+            out.plain(tier)?;
+        }
+        Ok(())
+    }
+}
+
+impl GurgleRoll<'_> {
+    /// Get sign and absolute value of modifier delta, or `None` if value is not modified
+    const fn signed_delta(&self) -> Option<(char, u64)> {
+        match self.modifier_delta() {
+            0 => None,
+            d if d < 0 => Some(('-', d.unsigned_abs())),
+            d => Some(('+', d.unsigned_abs())),
+        }
+    }
+}
+
+impl DiceRoll {
+    fn push_steps(&self, steps: &mut Vec<String>) {
+        if let Some(times) = self.times_roll() {
+            times.push_steps(steps);
+        }
+        let points = self.faces().join(",");
+        let explode = self
+            .explode_mode()
+            .map(|e| e.to_string())
+            .unwrap_or_default();
+        let sided = if self.is_fudge() {
+            "F".to_string()
+        } else {
+            self.sided().to_string()
+        };
+        // both dice of an advantage roll are written as one
+        let times = if self.post_processor().is_advantage() {
+            self.rolled_times() / 2
+        } else {
+            self.rolled_times()
+        };
+        steps.push(format!(
+            "{}d{}{}{}: rolled {} = {}",
+            times,
+            sided,
+            explode,
+            self.post_processor(),
+            points,
+            self.value()
+        ));
+    }
+}
+
+impl ItemRoll {
+    fn push_steps(&self, steps: &mut Vec<String>) {
+        match self {
+            Self::Dice(dice) => dice.push_steps(steps),
+            Self::Number(_) => {}
+            Self::Parentheses(e) => e.push_steps(steps),
+            Self::Repeat(r) => r.push_steps(steps),
+            Self::Labeled(item, _) => item.push_steps(steps),
+        }
+    }
+}
+
+impl RepeatRoll {
+    fn push_steps(&self, steps: &mut Vec<String>) {
+        let mut values = Vec::with_capacity(self.rolls().len());
+        for roll in self.rolls() {
+            roll.push_steps(steps);
+            values.push(roll.value().to_string());
+        }
+        let aggregate = match self.aggregate() {
+            PostProcessor::Sum => "sum".to_string(),
+            aggregate => aggregate.to_string(),
+        };
+        steps.push(format!(
+            "{}#{}: got {} = {}",
+            self.rolls().len(),
+            aggregate,
+            values.join(","),
+            self.value()
+        ));
+    }
+}
+
+impl RollTreeNode {
+    fn push_steps(&self, steps: &mut Vec<String>) {
+        match self {
+            Self::Leaf(leaf) => leaf.push_steps(steps),
+            Self::Tree(tree) => {
+                tree.left.push_steps(steps);
+                tree.right.push_steps(steps);
+                steps.push(format!(
+                    "{} {} {} = {}",
+                    tree.left.value(),
+                    tree.mid,
+                    tree.right.value(),
+                    tree.value()
+                ));
+            }
+        }
+    }
+}
+
+impl GurgleRoll<'_> {
+    /// Explain how the result is calculated step by step, one line for each step
+    ///
+    /// Steps are in post order, that is, a step comes after all steps it depends on:
+    /// dice rounds first, then arithmetic, then checker and tier. For `2d6+1>5`, it may be:
+    ///
+    /// - `2d6: rolled 2,5 = 7`
+    /// - `7 + 1 = 8`
+    /// - `8 > 5: success`
+    #[must_use]
+    pub fn steps(&self) -> Vec<String> {
+        self.steps_with(&global_lang())
+    }
+
+    /// Explain how the result is calculated step by step like [`steps`], in language `lang`
+    ///
+    /// [`steps`]: #method.steps
+    #[must_use]
+    pub fn steps_with(&self, lang: &OutputSpans) -> Vec<String> {
+        let mut steps = Vec::new();
+        self.expr().push_steps(&mut steps);
+        if let Some((sign, delta)) = self.signed_delta() {
+            steps.push(format!(
+                "{} {} {} = {}",
+                self.raw_value(),
+                sign,
+                delta,
+                self.value()
+            ));
+        }
+
+        if let Some(c) = self.checker() {
+            let result = if c.check(self.value()) {
+                &lang.success
             } else {
-                f.write_str(&LANG.failed)?;
+                &lang.failed
+            };
+            steps.push(format!("{} {}: {}", self.value(), checker_step(c), result));
+        }
+
+        if let Some(tier) = self.tier() {
+            steps.push(format!("{}: {}", self.value(), tier));
+        }
+
+        steps
+    }
+}
+
+/// Semantic kind of a [`StyledSpan`], for mapping to a color or style
+///
+/// [`StyledSpan`]: struct.StyledSpan.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpanKind {
+    /// Point of a rolled die
+    DiceFace,
+    /// Arithmetic operator
+    Operator,
+    /// Number item, or value of a post processed dice round
+    Number,
+    /// Final result value
+    Total,
+    /// Check passed word
+    Success,
+    /// Check failed word
+    Failure,
+    /// Anything else, like parentheses, separators and checker
+    Plain,
+}
+
+/// A piece of detailed output string with its semantic kind, see [`GurgleRoll::styled_spans`]
+///
+/// [`GurgleRoll::styled_spans`]: ../roll/struct.GurgleRoll.html#method.styled_spans
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StyledSpan {
+    /// Output text
+    pub text: String,
+    /// Kind of this text
+    pub kind: SpanKind,
+}
+
+// where detailed output is written to, so `Display` and styled spans are written by the same code
+trait Output {
+    fn span<T: Display + ?Sized>(&mut self, kind: SpanKind, text: &T) -> core::fmt::Result;
+
+    fn plain<T: Display + ?Sized>(&mut self, text: &T) -> core::fmt::Result {
+        self.span(SpanKind::Plain, text)
+    }
+}
+
+impl Output for Formatter<'_> {
+    fn span<T: Display + ?Sized>(&mut self, _: SpanKind, text: &T) -> core::fmt::Result {
+        self.write_fmt(format_args!("{}", text))
+    }
+}
+
+// adjacent plain spans are merged into one
+impl Output for Vec<StyledSpan> {
+    fn span<T: Display + ?Sized>(&mut self, kind: SpanKind, text: &T) -> core::fmt::Result {
+        if kind == SpanKind::Plain {
+            if let Some(last) = self.last_mut().filter(|s| s.kind == SpanKind::Plain) {
+                return last.text.write_fmt(format_args!("{}", text));
             }
         }
+        self.push(StyledSpan {
+            text: text.to_string(),
+            kind,
+        });
         Ok(())
     }
 }
+
+impl GurgleRoll<'_> {
+    /// Get detailed output as styled spans, concatenating all texts gives the same string as `Display`
+    #[must_use]
+    pub fn styled_spans(&self) -> Vec<StyledSpan> {
+        self.styled_spans_with(&global_lang())
+    }
+
+    /// Get detailed output as styled spans like [`styled_spans`], in language `lang`
+    ///
+    /// [`styled_spans`]: #method.styled_spans
+    #[must_use]
+    pub fn styled_spans_with(&self, lang: &OutputSpans) -> Vec<StyledSpan> {
+        let mut spans = Vec::new();
+        // writing to a `Vec` never fails
+        self.write_detail(&mut spans, lang).unwrap_or_default();
+        spans
+    }
+}
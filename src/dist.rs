@@ -0,0 +1,626 @@
+//! exact probability distribution of gurgle expression
+
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use core::convert::TryFrom;
+
+#[cfg(not(any(feature = "std", test)))]
+use crate::float::F64Ext;
+use crate::{
+    expr::{
+        AstTree, AstTreeNode, Dice, Explode, ExplodeMode, Item, Operator, PostProcessor, Repeat,
+        Reroll, TimesSpec,
+    },
+    roll::RollArena,
+};
+
+/// How many calculation steps can be used to get a distribution by default
+pub const DEFAULT_BUDGET: u64 = 10_000_000;
+
+/// How many rolls are averaged to estimate expected value, when it can't be calculated exactly
+pub const EXPECTATION_SAMPLES: u64 = 100_000;
+
+/// Calculation steps budget, to avoid spending too much time on a huge state space
+pub struct Budget(u64);
+
+impl Budget {
+    pub const fn new(steps: u64) -> Self {
+        Self(steps)
+    }
+
+    fn spend(&mut self, steps: u64) -> Option<()> {
+        self.0 = self.0.checked_sub(steps)?;
+        Some(())
+    }
+}
+
+/// Probability of every possible value
+pub type Distribution = BTreeMap<i64, f64>;
+
+/// Mean and variance of a random value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Moments {
+    pub mean: f64,
+    pub variance: f64,
+}
+
+impl Moments {
+    const fn constant(x: f64) -> Self {
+        Self {
+            mean: x,
+            variance: 0.0,
+        }
+    }
+
+    fn of(dist: &Distribution) -> Self {
+        #[allow(clippy::cast_precision_loss)] // because values are small
+        let (mean, square) = dist.iter().fold((0.0, 0.0), |(m, s), (v, p)| {
+            (m + *v as f64 * p, s + (*v as f64).powi(2) * p)
+        });
+        Self {
+            mean,
+            variance: mean.mul_add(-mean, square),
+        }
+    }
+}
+
+/// Check if a value in `bounds` may be saturated when rolling, so its moments can't be combined from its parts
+const fn saturates((min, max): (i64, i64)) -> bool {
+    min == i64::MIN || max == i64::MAX
+}
+
+/// Tolerance for float rounding error when comparing probabilities
+const TOLERANCE: f64 = 1e-9;
+
+/// Check if two distributions are the same, ignoring float rounding error
+pub fn same(a: &Distribution, b: &Distribution) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b.iter())
+            .all(|((x, px), (y, py))| x == y && (px - py).abs() <= TOLERANCE)
+}
+
+/// Probability of two independent results being equal
+pub fn tie(a: &Distribution, b: &Distribution) -> f64 {
+    a.iter()
+        .filter_map(|(x, px)| b.get(x).map(|py| px * py))
+        .sum()
+}
+
+/// Probability of result being greater than or equal to every possible value
+pub fn at_least(dist: &Distribution) -> Distribution {
+    let mut sum = 0.0;
+    let mut result: Distribution = dist
+        .iter()
+        .rev()
+        .map(|(v, p)| {
+            sum += p;
+            (*v, sum)
+        })
+        .collect();
+    // float rounding error may make it a bit more than 1
+    if let Some(first) = result.values_mut().next() {
+        *first = 1.0;
+    }
+    result
+}
+
+fn combine<F>(a: &Distribution, b: &Distribution, budget: &mut Budget, f: F) -> Option<Distribution>
+where
+    F: Fn(i64, i64) -> i64,
+{
+    budget.spend((a.len() as u64).saturating_mul(b.len() as u64))?;
+
+    let mut result = Distribution::new();
+    for (x, px) in a {
+        for (y, py) in b {
+            *result.entry(f(*x, *y)).or_default() += px * py;
+        }
+    }
+    Some(result)
+}
+
+/// Distribution of sum of `times` dice with `sided` sides, index `i` means probability of value `i`
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)] // because budget limits the size
+fn sum_of_uniform(times: u64, sided: u64, budget: &mut Budget) -> Option<Vec<f64>> {
+    let max = times.checked_mul(sided)? as usize;
+    budget.spend((max as u64).saturating_mul(times))?;
+
+    let sided = sided as usize;
+    let mut current = vec![0.0; max + 1];
+    current[0] = 1.0;
+    for _ in 0..times {
+        // sliding window sum over previous round, `next[k] = sum(current[k-sided..k]) / sided`
+        let mut next = vec![0.0; max + 1];
+        let mut window = 0.0;
+        for k in 0..=max {
+            if k >= 1 {
+                window += current[k - 1];
+            }
+            if k > sided {
+                window -= current[k - sided - 1];
+            }
+            next[k] = window / sided as f64;
+        }
+        current = next;
+    }
+    Some(current)
+}
+
+/// Distribution of max value of `times` independent values, which have the same distribution `dist`
+#[allow(clippy::cast_possible_wrap)] // because limit checked times is small
+fn max_of(dist: &Distribution, times: u64, budget: &mut Budget) -> Option<Distribution> {
+    budget.spend(dist.len() as u64)?;
+    let mut cdf = 0.0;
+    let mut last = 0.0;
+    let mut result = Distribution::new();
+    for (v, p) in dist {
+        cdf += p;
+        let now = f64::powi(cdf, times as i32);
+        result.insert(*v, now - last);
+        last = now;
+    }
+    Some(result)
+}
+
+/// Distribution of min value of `times` independent values, which have the same distribution `dist`
+#[allow(clippy::cast_possible_wrap)] // because limit checked times is small
+fn min_of(dist: &Distribution, times: u64, budget: &mut Budget) -> Option<Distribution> {
+    budget.spend(dist.len() as u64)?;
+    let mut sf = 0.0;
+    let mut last = 0.0;
+    let mut result = Distribution::new();
+    for (v, p) in dist.iter().rev() {
+        sf += p;
+        let now = f64::powi(sf, times as i32);
+        result.insert(*v, now - last);
+        last = now;
+    }
+    Some(result)
+}
+
+/// Distribution of sum of `times` independent values, which have the same distribution `dist`
+fn sum_of(dist: &Distribution, times: u64, budget: &mut Budget) -> Option<Distribution> {
+    let mut result: Distribution = core::iter::once((0, 1.0)).collect();
+    for _ in 0..times {
+        result = combine(&result, dist, budget, i64::saturating_add)?;
+    }
+    Some(result)
+}
+
+impl Explode {
+    /// Distribution of sum of points generated by a single die
+    #[allow(clippy::cast_possible_wrap, clippy::cast_precision_loss)] // because limit checked sided is small
+    fn die_distribution(self, sided: u64, budget: &mut Budget) -> Option<Distribution> {
+        budget.spend(sided.saturating_mul(self.max_depth.saturating_add(1)))?;
+
+        let p_face = 1.0 / sided as f64;
+        let sided = sided as i64;
+        let mut result = Distribution::new();
+        // probability of reaching current depth, and sum of points before it
+        let (mut p_reach, mut offset) = (1.0, 0);
+        for depth in 0..=self.max_depth {
+            let minus = i64::from(depth > 0 && self.mode == ExplodeMode::Penetrate);
+            let last = depth == self.max_depth;
+            for face in 1..=sided {
+                if face != sided || last {
+                    *result.entry(offset + face - minus).or_default() += p_reach * p_face;
+                }
+            }
+            p_reach *= p_face;
+            offset += sided - minus;
+        }
+        Some(result)
+    }
+}
+
+impl Reroll {
+    /// Distribution of the final point of a single die
+    #[allow(clippy::cast_possible_wrap, clippy::cast_precision_loss)] // because limit checked sided is small
+    fn die_distribution(&self, sided: u64, budget: &mut Budget) -> Option<Distribution> {
+        budget.spend(sided)?;
+
+        let p_face = 1.0 / sided as f64;
+        let rerolled = (1..=sided).filter(|v| self.values.contains(v)).count();
+        let p_again = rerolled as f64 * p_face;
+        let depth = i32::try_from(self.depth()).ok()?;
+        // a point not rolled again can be gotten at any depth, others only at the last depth
+        let p_kept = (0..=depth).map(|d| p_again.powi(d)).sum::<f64>() * p_face;
+        let p_last = p_again.powi(depth) * p_face;
+        Some(
+            (1..=sided)
+                .map(|v| {
+                    let p = if self.values.contains(&v) {
+                        p_last
+                    } else {
+                        p_kept
+                    };
+                    (v as i64, p)
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Dice {
+    /// Distribution of sum of points generated by a single die
+    #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)] // because points are positive and small
+    fn die_distribution(&self, budget: &mut Budget) -> Option<Distribution> {
+        let raw = self.unclamped_die_distribution(budget)?;
+        match self.clamp {
+            None => Some(raw),
+            // points of an exploding die are clamped one by one, not their sum
+            Some(_) if self.explode.is_some() || self.fudge => None,
+            Some(clamp) => {
+                let mut dist = Distribution::new();
+                for (v, p) in raw {
+                    *dist.entry(clamp.apply(v as u64) as i64).or_default() += p;
+                }
+                Some(dist)
+            }
+        }
+    }
+
+    #[allow(clippy::cast_possible_wrap, clippy::cast_precision_loss)] // because limit checked sided is small
+    fn unclamped_die_distribution(&self, budget: &mut Budget) -> Option<Distribution> {
+        if let Some(weights) = &self.weights {
+            // loaded dice which explodes or rolls again is not supported yet
+            if self.explode.is_some() || self.reroll.is_some() {
+                return None;
+            }
+            budget.spend(self.sided)?;
+            let total = weights.iter().map(|w| f64::from(*w)).sum::<f64>();
+            return Some(
+                (1..=self.sided as i64)
+                    .zip(weights)
+                    .filter(|(_, w)| **w > 0)
+                    .map(|(v, w)| (v, f64::from(*w) / total))
+                    .collect(),
+            );
+        }
+        match (self.explode, &self.reroll) {
+            (None, Some(r)) => r.die_distribution(self.sided, budget),
+            // not supported yet
+            (Some(_), Some(_)) => None,
+            (None, None) if self.fudge => {
+                budget.spend(self.sided)?;
+                Some((-1..=1).map(|v| (v, 1.0 / 3.0)).collect())
+            }
+            (None, None) => {
+                budget.spend(self.sided)?;
+                let p = 1.0 / self.sided as f64;
+                Some((1..=self.sided as i64).map(|v| (v, p)).collect())
+            }
+            (Some(e), None) => e.die_distribution(self.sided, budget),
+        }
+    }
+
+    /// Distribution of sum of points of `times` dice
+    #[allow(clippy::cast_possible_wrap)] // because limit checked sided and times are small
+    fn sum_distribution(&self, times: u64, budget: &mut Budget) -> Option<Distribution> {
+        match self.explode {
+            None if self.is_uniform() => Some(
+                sum_of_uniform(times, self.sided, budget)?
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, p)| *p > 0.0)
+                    .map(|(v, p)| (v as i64, p))
+                    .collect(),
+            ),
+            _ => sum_of(&self.die_distribution(budget)?, times, budget),
+        }
+    }
+
+    /// Distribution of successes count of `times` dice, every die succeeds independently
+    #[allow(clippy::cast_sign_loss)] // because points are positive
+    fn successes_distribution(&self, times: u64, budget: &mut Budget) -> Option<Distribution> {
+        let p = self
+            .die_distribution(budget)?
+            .into_iter()
+            .filter(|(v, _)| self.pp.succeeds(*v as u64))
+            .map(|(_, p)| p)
+            .sum::<f64>();
+        let single = vec![(0, 1.0 - p), (1, p)]
+            .into_iter()
+            .filter(|(_, p)| *p > 0.0)
+            .collect();
+        sum_of(&single, times, budget)
+    }
+
+    /// Check if every point of a die is uniform in `1..=sided`, without explosion
+    const fn is_uniform(&self) -> bool {
+        self.explode.is_none()
+            && self.reroll.is_none()
+            && self.clamp.is_none()
+            && !self.fudge
+            && self.weights.is_none()
+    }
+
+    /// Check if every die gives exactly one point, explosion which generates new points makes count not fixed
+    pub(crate) const fn fixed_points_count(&self) -> bool {
+        !core::matches!(
+            self.explode,
+            Some(Explode {
+                mode: ExplodeMode::Standard | ExplodeMode::Penetrate,
+                ..
+            })
+        )
+    }
+
+    #[allow(clippy::cast_possible_wrap)] // because limit checked times and avg factor are small
+    fn distribution_of_times(&self, times: u64, budget: &mut Budget) -> Option<Distribution> {
+        let fixed_count = self.fixed_points_count();
+        match self.pp {
+            PostProcessor::Sum => self.sum_distribution(times, budget),
+            PostProcessor::Avg if fixed_count => {
+                let mut dist = Distribution::new();
+                for (v, p) in self.sum_distribution(times, budget)? {
+                    *dist.entry(v / times as i64).or_default() += p;
+                }
+                Some(dist)
+            }
+            PostProcessor::ScaledAvg(factor) if fixed_count => {
+                let mut dist = Distribution::new();
+                for (v, p) in self.sum_distribution(times, budget)? {
+                    *dist.entry(v * factor as i64 / times as i64).or_default() += p;
+                }
+                Some(dist)
+            }
+            PostProcessor::Max if fixed_count => {
+                max_of(&self.die_distribution(budget)?, times, budget)
+            }
+            // extra points are never greater than the point which explodes, so max point is not changed
+            PostProcessor::Max if self.reroll.is_none() && self.clamp.is_none() => Self {
+                explode: None,
+                ..self.clone()
+            }
+            .distribution_of_times(times, budget),
+            PostProcessor::Min if fixed_count => {
+                min_of(&self.die_distribution(budget)?, times, budget)
+            }
+            // every kept die is the max or min of two dice, including its extra dice of explosion
+            PostProcessor::Advantage => {
+                let pair = max_of(&self.die_distribution(budget)?, 2, budget)?;
+                sum_of(&pair, times, budget)
+            }
+            PostProcessor::Disadvantage => {
+                let pair = min_of(&self.die_distribution(budget)?, 2, budget)?;
+                sum_of(&pair, times, budget)
+            }
+            PostProcessor::CountSuccesses { .. } if fixed_count => {
+                self.successes_distribution(times, budget)
+            }
+            // not supported yet
+            PostProcessor::Avg
+            | PostProcessor::Max
+            | PostProcessor::Min
+            | PostProcessor::ScaledAvg(_)
+            | PostProcessor::KeepHighest(_)
+            | PostProcessor::KeepLowest(_)
+            | PostProcessor::DropHighest(_)
+            | PostProcessor::DropLowest(_)
+            | PostProcessor::CountSuccesses { .. } => None,
+        }
+    }
+
+    /// Mean and variance of this round, without calculating the distribution if possible
+    #[allow(clippy::cast_precision_loss)] // because limit checked sided and times are small
+    pub(crate) fn moments(&self, budget: &mut Budget) -> Option<Moments> {
+        if self.pp != PostProcessor::Sum {
+            return Some(Moments::of(&self.distribution(budget)?));
+        }
+
+        let die = match self.explode {
+            None if self.is_uniform() => {
+                let sided = self.sided as f64;
+                Moments {
+                    mean: sided.mul_add(0.5, 0.5),
+                    variance: sided.mul_add(sided, -1.0) / 12.0,
+                }
+            }
+            _ => Moments::of(&self.die_distribution(budget)?),
+        };
+        let times = match &self.times {
+            TimesSpec::Fixed(n) => Moments::constant(*n as f64),
+            TimesSpec::Expr(e) => e.moments(budget)?,
+        };
+        // sum of random count of independent dice, by law of total variance
+        Some(Moments {
+            mean: times.mean * die.mean,
+            variance: times
+                .mean
+                .mul_add(die.variance, times.variance * die.mean * die.mean),
+        })
+    }
+
+    #[allow(clippy::cast_sign_loss)] // because roll times expr is checked to be positive
+    pub(crate) fn distribution(&self, budget: &mut Budget) -> Option<Distribution> {
+        match &self.times {
+            TimesSpec::Fixed(n) => self.distribution_of_times(*n, budget),
+            TimesSpec::Expr(e) => {
+                let mut result = Distribution::new();
+                for (times, pt) in e.distribution(budget)? {
+                    for (v, p) in self.distribution_of_times(times.max(0) as u64, budget)? {
+                        *result.entry(v).or_default() += pt * p;
+                    }
+                }
+                Some(result)
+            }
+        }
+    }
+}
+
+impl Repeat {
+    #[allow(clippy::cast_precision_loss)] // because limit checked times is small
+    fn moments(&self, budget: &mut Budget) -> Option<Moments> {
+        if self.aggregate != PostProcessor::Sum || saturates(self.bounds()) {
+            return Some(Moments::of(&self.distribution(budget)?));
+        }
+        let item = self.item.moments(budget)?;
+        Some(Moments {
+            mean: item.mean * self.times as f64,
+            variance: item.variance * self.times as f64,
+        })
+    }
+
+    #[allow(clippy::cast_possible_wrap)] // because limit checked times is small
+    fn distribution(&self, budget: &mut Budget) -> Option<Distribution> {
+        let dist = self.item.distribution(budget)?;
+        match self.aggregate {
+            PostProcessor::Avg => {
+                let mut result = Distribution::new();
+                for (v, p) in sum_of(&dist, self.times, budget)? {
+                    *result.entry(v.div_euclid(self.times as i64)).or_default() += p;
+                }
+                Some(result)
+            }
+            PostProcessor::Max => max_of(&dist, self.times, budget),
+            PostProcessor::Min => min_of(&dist, self.times, budget),
+            _ => sum_of(&dist, self.times, budget),
+        }
+    }
+}
+
+impl Item {
+    #[allow(clippy::cast_precision_loss)] // because limit checked number item is small
+    pub(crate) fn moments(&self, budget: &mut Budget) -> Option<Moments> {
+        match self {
+            Self::Dice(d) => d.moments(budget),
+            Self::Number(x) => Some(Moments::constant(*x as f64)),
+            Self::Parentheses(e) => e.moments(budget),
+            Self::Repeat(r) => r.moments(budget),
+            Self::Labeled(item, _) => item.moments(budget),
+        }
+    }
+
+    pub(crate) fn distribution(&self, budget: &mut Budget) -> Option<Distribution> {
+        match self {
+            Self::Dice(d) => d.distribution(budget),
+            Self::Number(x) => Some(core::iter::once((*x, 1.0)).collect()),
+            Self::Parentheses(e) => e.distribution(budget),
+            Self::Repeat(r) => r.distribution(budget),
+            Self::Labeled(item, _) => item.distribution(budget),
+        }
+    }
+}
+
+impl AstTree {
+    pub(crate) fn moments(&self, budget: &mut Budget) -> Option<Moments> {
+        if saturates(self.bounds()) {
+            return Some(Moments::of(&self.distribution(budget)?));
+        }
+        let (l, r) = (self.left.moments(budget)?, self.right.moments(budget)?);
+        // left and right are independent
+        let result = match self.mid {
+            Operator::Add => Moments {
+                mean: l.mean + r.mean,
+                variance: l.variance + r.variance,
+            },
+            Operator::Minus => Moments {
+                mean: l.mean - r.mean,
+                variance: l.variance + r.variance,
+            },
+            Operator::Divide | Operator::Modulo => Moments::of(&self.distribution(budget)?),
+            Operator::Multiply => {
+                let mean = l.mean * r.mean;
+                let square =
+                    l.mean.mul_add(l.mean, l.variance) * r.mean.mul_add(r.mean, r.variance);
+                Moments {
+                    mean,
+                    variance: mean.mul_add(-mean, square),
+                }
+            }
+        };
+        Some(result)
+    }
+
+    pub(crate) fn distribution(&self, budget: &mut Budget) -> Option<Distribution> {
+        let left = self.left.distribution(budget)?;
+        let right = self.right.distribution(budget)?;
+        // same as rolling, dividing by zero gives zero
+        combine(&left, &right, budget, |x, y| {
+            self.mid.saturating_apply(x, y)
+        })
+    }
+}
+
+impl AstTreeNode {
+    /// Estimate mean by averaging `samples` rolls, random source has a fixed seed so estimation is stable
+    #[allow(clippy::cast_precision_loss)] // because estimation does not need to be exact
+    pub(crate) fn sampled_mean(&self, samples: u64) -> f64 {
+        let mut rng = nanorand::WyRand::new_seed(0);
+        let mut arena = RollArena::new();
+        let total: f64 = (0..samples)
+            .map(|_| self.value_in(&mut rng, &mut arena) as f64)
+            .sum();
+        total / samples as f64
+    }
+
+    pub(crate) fn moments(&self, budget: &mut Budget) -> Option<Moments> {
+        match self {
+            Self::Leaf(item) => item.moments(budget),
+            Self::Tree(tree) => tree.moments(budget),
+        }
+    }
+
+    pub(crate) fn distribution(&self, budget: &mut Budget) -> Option<Distribution> {
+        match self {
+            Self::Leaf(item) => item.distribution(budget),
+            Self::Tree(tree) => tree.distribution(budget),
+        }
+    }
+}
+
+impl Dice {
+    /// Probability that no `sided` sided die in this round rolls its max face at first roll
+    #[allow(clippy::cast_precision_loss, clippy::cast_sign_loss)] // because limit checked sided and times are small
+    fn crit_miss(&self, sided: u64, budget: &mut Budget) -> f64 {
+        let miss = if self.sided != sided || self.fudge {
+            1.0
+        } else if let Some(weights) = &self.weights {
+            let total = weights.iter().map(|w| f64::from(*w)).sum::<f64>();
+            1.0 - weights.last().map_or(0.0, |w| f64::from(*w)) / total
+        } else {
+            1.0 - 1.0 / self.sided as f64
+        };
+        match &self.times {
+            TimesSpec::Fixed(n) => miss.powf(self.rolled_dice(*n) as f64),
+            // times and dice in times expr are treated as independent
+            TimesSpec::Expr(e) => {
+                let times = e.distribution(budget).map_or_else(
+                    || miss.powf(e.bounds().1 as f64),
+                    |dist| {
+                        dist.into_iter()
+                            .map(|(t, p)| p * miss.powf(t.max(0) as f64))
+                            .sum()
+                    },
+                );
+                times * e.crit_miss(sided, budget)
+            }
+        }
+    }
+}
+
+impl Item {
+    #[allow(clippy::cast_precision_loss)] // because limit checked times is small
+    fn crit_miss(&self, sided: u64, budget: &mut Budget) -> f64 {
+        match self {
+            Self::Dice(d) => d.crit_miss(sided, budget),
+            Self::Number(_) => 1.0,
+            Self::Parentheses(e) => e.crit_miss(sided, budget),
+            Self::Repeat(r) => r.item.crit_miss(sided, budget).powf(r.times as f64),
+            Self::Labeled(item, _) => item.crit_miss(sided, budget),
+        }
+    }
+}
+
+impl AstTreeNode {
+    /// Probability that no `sided` sided die rolls its max face at first roll
+    pub(crate) fn crit_miss(&self, sided: u64, budget: &mut Budget) -> f64 {
+        match self {
+            Self::Leaf(item) => item.crit_miss(sided, budget),
+            Self::Tree(tree) => {
+                tree.left.crit_miss(sided, budget) * tree.right.crit_miss(sided, budget)
+            }
+        }
+    }
+}
@@ -1,6 +1,9 @@
 //! errors in gurgle command parsing and execution
 
-use std::num::ParseIntError;
+use alloc::{boxed::Box, format, string::String, vec, vec::Vec};
+use core::{fmt::Display, num::ParseIntError};
+
+use pest::error::{ErrorVariant, InputLocation, LineColLocation};
 
 use thiserror::Error;
 
@@ -13,7 +16,7 @@ pub struct ParseEnumError;
 pub enum CompileError {
     /// Invalid syntax
     #[error("invalid gurgle syntax: {0}")]
-    InvalidSyntax(String),
+    InvalidSyntax(Box<SyntaxError>),
     /// Contains invalid number
     #[error("command contains invalid number")]
     ParseNumberError(#[from] ParseIntError),
@@ -32,10 +35,161 @@ pub enum CompileError {
     /// Number item out of range
     #[error("number item out of range")]
     NumberItemOutOfRange,
+    /// Success target of a dice pool, like `8` in `5d10f8`, is not between 1 and dice sides
+    #[error("success target out of dice sides range")]
+    SuccessTargetOutOfRange,
+    /// Keep count of a round is zero, like `3d6kh0`, or drop count is not less than roll times, like `3d6dh3`,
+    /// so the round always sums no point
+    #[error("keep or drop count out of dice roll times range")]
+    SelectCountOutOfRange,
+    /// Expected value of checker target expr, like `avg(2d4+5)`, is too complex to calculate
+    #[error("expected value of target expr is too complex to calculate")]
+    TargetExpectationTooComplex,
+    /// More than one tier have the same threshold
+    #[error("tier threshold duplicated")]
+    DuplicateTierThreshold,
+    /// Number literal contains a digit separator not accepted by config, like `1,000` by default
+    #[error("digit separator not allowed")]
+    DigitSeparatorNotAllowed,
+    /// Repeat count of an item or a whole command is not positive, like `0#1d6`
+    #[error("repeat count must be positive")]
+    RepeatCountNotPositive,
+    /// Repeat a whole command too many times, like `9999#(3d6) each`
+    #[error("command repeat count limit exceeded")]
+    CommandRepeatLimitExceeded,
+    /// Lower boundary of a range checker is greater than upper one, like `3d6 in 12..8`
+    #[error("range checker is empty")]
+    EmptyCheckRange,
+    /// Clamp bound of dice points, like `7` in `4d6mi7`, is not between 1 and dice sides, or min is greater than max
+    #[error("clamp bound out of dice sides range")]
+    ClampOutOfRange,
+    /// Point which makes a die roll again, like `7` in `4d6r7`, is not between 1 and dice sides
+    #[error("reroll point out of dice sides range")]
+    RerollOutOfRange,
+    /// Parentheses are nested too deep, like `((((1))))` with max depth 3
+    #[error("parentheses depth limit exceeded")]
+    ParenthesesDepthLimitExceeded,
+    /// Advantage or disadvantage round rolls times given by an expr, like `(1d4)d20adv`
+    #[error("advantage roll times must be a number")]
+    AdvantageRollTimesNotFixed,
+    /// Weights of a loaded dice are not one for every side, or are all zero, see [`Dice::new_weighted`]
+    ///
+    /// [`Dice::new_weighted`]: ../expr/struct.Dice.html#method.new_weighted
+    #[error("invalid loaded dice weights")]
+    InvalidDiceWeights,
+    /// One of many commands separated by `;` is invalid, see [`Gurgle::compile_many`]
+    ///
+    /// Positions in a syntax error of `error` are relative to that command, `offset` is where it starts in input.
+    ///
+    /// [`Gurgle::compile_many`]: ../struct.Gurgle.html#method.compile_many
+    #[error("command {} is invalid: {error}", index + 1)]
+    InCommand {
+        /// index of the invalid command, from zero, empty ones are counted too
+        index: usize,
+        /// byte offset of the invalid command in input
+        offset: usize,
+        /// why the command is invalid
+        #[source]
+        error: Box<Self>,
+    },
+}
+
+/// Where and why a command has invalid syntax, see [`CompileError::InvalidSyntax`]
+///
+/// Display gives a single line message, like `expected number at line 1, column 6`.
+///
+/// [`CompileError::InvalidSyntax`]: enum.CompileError.html#variant.InvalidSyntax
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SyntaxError {
+    span: (usize, usize),
+    line_col: (usize, usize),
+    expected: Vec<String>,
+    message: String,
+}
+
+impl SyntaxError {
+    /// Byte offset range of the offending part in source command, start inclusive and end exclusive
+    ///
+    /// Most syntax errors are at a single position, then the range is empty, like `(5, 5)`.
+    #[must_use]
+    pub const fn span(&self) -> (usize, usize) {
+        self.span
+    }
+
+    /// Byte offset of the offending position in source command, that is, start of [`span`]
+    ///
+    /// [`span`]: #method.span
+    #[must_use]
+    pub const fn offset(&self) -> usize {
+        self.span.0
+    }
+
+    /// Line and column of the offending position, both start from 1, column counts chars
+    #[must_use]
+    pub const fn line_col(&self) -> (usize, usize) {
+        self.line_col
+    }
+
+    /// Names of syntax parts which are expected at the offending position, like `number` or `dice`
+    #[must_use]
+    pub fn expected(&self) -> &[String] {
+        &self.expected
+    }
+
+    /// Description of the error, without position
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.line_col.0, self.line_col.1
+        )
+    }
+}
+
+impl<R: pest::RuleType> From<pest::error::Error<R>> for SyntaxError {
+    fn from(err: pest::error::Error<R>) -> Self {
+        let span = match err.location {
+            InputLocation::Pos(pos) => (pos, pos),
+            InputLocation::Span(span) => span,
+        };
+        let line_col = match err.line_col {
+            LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+        };
+        let expected = match &err.variant {
+            ErrorVariant::ParsingError { positives, .. } => {
+                positives.iter().map(|r| format!("{:?}", r)).collect()
+            }
+            ErrorVariant::CustomError { .. } => vec![],
+        };
+        Self {
+            span,
+            line_col,
+            expected,
+            message: err.variant.message().into_owned(),
+        }
+    }
 }
 
 impl<R: pest::RuleType> From<pest::error::Error<R>> for CompileError {
     fn from(err: pest::error::Error<R>) -> Self {
-        Self::InvalidSyntax(format!("{}", err))
+        Self::InvalidSyntax(Box::new(err.into()))
     }
 }
+
+/// Execute a compiled gurgle command failed
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum RollError {
+    /// Operand or result of arithmetic is too big
+    #[error("intermediate value magnitude limit exceeded")]
+    IntermediateMagnitudeLimitExceeded,
+    /// Right side of division or modulo is zero
+    #[error("division by zero")]
+    DivisionByZero,
+}
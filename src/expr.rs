@@ -1,26 +1,47 @@
 //! gurgle expression
 
-use std::str::FromStr;
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{
+    convert::TryFrom,
+    fmt::{Display, Formatter, Write},
+    str::FromStr,
+};
 
-use nanorand::Rng;
-use once_cell::sync::Lazy;
+use once_cell::race::OnceBox;
 use pest::{
-    iterators::Pair,
+    iterators::{Pair, Pairs},
     prec_climber::{Assoc, Operator as PCOperator, PrecClimber},
+    Parser,
 };
 
-static CLIMBER: Lazy<PrecClimber<Rule>> = Lazy::new(|| {
-    PrecClimber::new(vec![
-        PCOperator::new(Rule::op_add, Assoc::Left) | PCOperator::new(Rule::op_sub, Assoc::Left),
-        PCOperator::new(Rule::op_multiply, Assoc::Left),
-    ])
-});
+// `OnceBox` instead of `Lazy`, because it works without std
+static CLIMBER: OnceBox<PrecClimber<Rule>> = OnceBox::new();
+
+fn climber() -> &'static PrecClimber<Rule> {
+    CLIMBER.get_or_init(|| {
+        Box::new(PrecClimber::new(vec![
+            PCOperator::new(Rule::op_add, Assoc::Left) | PCOperator::new(Rule::op_sub, Assoc::Left),
+            PCOperator::new(Rule::op_multiply, Assoc::Left)
+                | PCOperator::new(Rule::op_divide, Assoc::Left)
+                | PCOperator::new(Rule::op_modulo, Assoc::Left),
+        ]))
+    })
+}
 
+#[cfg(feature = "std")]
+use crate::rng;
 use crate::{
-    config::Limit,
+    checker::Compare,
+    config::{Limit, DEFAULT_CONFIG},
     error::{CompileError, ParseEnumError},
-    parser::Rule,
-    roll::{DiceRoll, ItemRoll, RollTree, RollTreeNode},
+    parser::{GurgleCommandParser, Rule},
+    rng::RollRng,
+    roll::{aggregate_values, DiceRoll, ItemRoll, RepeatRoll, RollArena, RollTree, RollTreeNode},
     tree::{BinaryTree, BinaryTreeNode},
 };
 
@@ -32,7 +53,16 @@ use crate::{
 /// - `3d6max` means get the max value of those 3 result
 /// - `3d6min` means get the min value of those 3 result
 /// - `3d6avg` means get the avg value of those 3 result
+/// - `3d6avg10` means get the avg value of those 3 result multiplied by 10, to keep 1 decimal digit
+/// - `4d6kh3` means get the sum of highest 3 result, `kl`, `dh` and `dl` are keep lowest, drop highest and drop lowest
+/// - `5d10f8` means count how many results are `>= 8`, other compares are written like `5d10f<3`
+///
+/// If there are not enough points, keeping selects all points, and dropping selects none.
+/// If there is no point at all, like a [`Dice`] built with zero roll times, value is zero for every action.
+///
+/// [`Dice`]: struct.Dice.html
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PostProcessor {
     /// get sum of all roll, default action
     Sum,
@@ -42,6 +72,88 @@ pub enum PostProcessor {
     Max,
     /// get min value of all roll
     Min,
+    /// get avg value of all roll multiplied by a factor, will round down(floor) after multiplying
+    ScaledAvg(u64),
+    /// get sum of highest n roll
+    KeepHighest(u64),
+    /// get sum of lowest n roll
+    KeepLowest(u64),
+    /// get sum of all roll except highest n
+    DropHighest(u64),
+    /// get sum of all roll except lowest n
+    DropLowest(u64),
+    /// roll every die twice and keep the higher one, sum kept dice, like `2d20adv`
+    Advantage,
+    /// roll every die twice and keep the lower one, sum kept dice, like `2d20dis`
+    Disadvantage,
+    /// count how many roll compared to `target` gives `compare`, like a dice pool counting successes
+    CountSuccesses {
+        /// wanted compare result of a single roll
+        compare: Compare,
+        /// target of a single roll, between 1 and dice sides
+        target: u64,
+    },
+}
+
+impl PostProcessor {
+    fn from_pair(pair: Pair<'_, Rule>, limit: &Limit<'_>) -> Result<Self, CompileError> {
+        assert_eq!(pair.as_rule(), Rule::postprocess);
+
+        let s = pair.as_str();
+        for arg in pair.into_inner() {
+            match arg.as_rule() {
+                Rule::scale => limit.check_number_item(arg.as_str().parse()?)?,
+                Rule::select_count => limit.check_select_count(arg.as_str().parse()?)?,
+                // range is checked against dice sides by `check_sided`
+                Rule::success_target => {
+                    arg.as_str().parse::<u64>()?;
+                }
+                Rule::success_compare => {}
+                _ => unreachable!(),
+            }
+        }
+        Ok(s.parse().unwrap())
+    }
+
+    const fn check_sided(self, sided: u64) -> Result<(), CompileError> {
+        match self {
+            Self::CountSuccesses { target, .. } if target == 0 || target > sided => {
+                Err(CompileError::SuccessTargetOutOfRange)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Get how many points are summed if there are `points` points, `None` if this is not a selection like `kh3`
+    pub(crate) fn selected(self, points: u64) -> Option<u64> {
+        match self {
+            Self::KeepHighest(n) | Self::KeepLowest(n) => Some(n.min(points)),
+            Self::DropHighest(n) | Self::DropLowest(n) => Some(points.saturating_sub(n)),
+            _ => None,
+        }
+    }
+
+    /// Check if this is [`Advantage`] or [`Disadvantage`]
+    ///
+    /// [`Advantage`]: #variant.Advantage
+    /// [`Disadvantage`]: #variant.Disadvantage
+    #[must_use]
+    pub const fn is_advantage(self) -> bool {
+        core::matches!(self, Self::Advantage | Self::Disadvantage)
+    }
+
+    /// Check if a single point counts as a success, always `false` if this is not [`CountSuccesses`]
+    ///
+    /// [`CountSuccesses`]: #variant.CountSuccesses
+    #[allow(clippy::cast_possible_wrap)] // because limit checked points and target are small
+    pub(crate) fn succeeds(self, point: u64) -> bool {
+        match self {
+            Self::CountSuccesses { compare, target } => {
+                compare.matches(point as i64, target as i64)
+            }
+            _ => false,
+        }
+    }
 }
 
 impl FromStr for PostProcessor {
@@ -53,6 +165,231 @@ impl FromStr for PostProcessor {
             "avg" => Self::Avg,
             "max" => Self::Max,
             "min" => Self::Min,
+            "adv" => Self::Advantage,
+            "dis" => Self::Disadvantage,
+            s => {
+                let (kind, n) = s.split_at(s.find(|c: char| c.is_ascii_digit()).unwrap_or(0));
+                let n = n.parse().map_err(|_| ParseEnumError)?;
+                match kind {
+                    "avg" => Self::ScaledAvg(n),
+                    "kh" => Self::KeepHighest(n),
+                    "kl" => Self::KeepLowest(n),
+                    "dh" => Self::DropHighest(n),
+                    "dl" => Self::DropLowest(n),
+                    "f" => Self::CountSuccesses {
+                        compare: Compare::Gte,
+                        target: n,
+                    },
+                    kind if kind.starts_with('f') => Self::CountSuccesses {
+                        compare: kind[1..].parse()?,
+                        target: n,
+                    },
+                    _ => return Err(ParseEnumError),
+                }
+            }
+        };
+
+        Ok(res)
+    }
+}
+
+impl Display for PostProcessor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            // sum is the default action, so it can be omitted
+            Self::Sum => "",
+            Self::Avg => "avg",
+            Self::Max => "max",
+            Self::Min => "min",
+            Self::Advantage => "adv",
+            Self::Disadvantage => "dis",
+            Self::ScaledAvg(factor) => return f.write_fmt(format_args!("avg{}", factor)),
+            Self::KeepHighest(n) => return f.write_fmt(format_args!("kh{}", n)),
+            Self::KeepLowest(n) => return f.write_fmt(format_args!("kl{}", n)),
+            Self::DropHighest(n) => return f.write_fmt(format_args!("dh{}", n)),
+            Self::DropLowest(n) => return f.write_fmt(format_args!("dl{}", n)),
+            // `>=` is the default compare, so it can be omitted
+            Self::CountSuccesses {
+                compare: Compare::Gte,
+                target,
+            } => return f.write_fmt(format_args!("f{}", target)),
+            Self::CountSuccesses { compare, target } => {
+                return f.write_fmt(format_args!("f{}{}", compare, target))
+            }
+        })
+    }
+}
+
+/// Which points make a die roll again before it's used, like `4d6r1`
+///
+/// ## Example
+///
+/// - `4d6r1` rolls a die again until it's not 1
+/// - `4d6r1r2` rolls a die again until it's neither 1 nor 2
+/// - `4d6ro1` rolls a die again only once if it's 1, then uses the new point whatever it is
+///
+/// Extra dice generated by explosion are not rolled again.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Reroll {
+    /// points which make a die roll again
+    pub values: Vec<u64>,
+    /// if a die only rolls again once, like `ro1`, or until getting other points, like `r1`
+    pub once: bool,
+    /// how many times a single die can roll again at most, so a `d1r1` will not roll forever
+    pub max_depth: u64,
+}
+
+impl Reroll {
+    /// How many times a single die can roll again at most
+    pub(crate) fn depth(&self) -> u64 {
+        if self.once {
+            self.max_depth.min(1)
+        } else {
+            self.max_depth
+        }
+    }
+
+    // points are checked against `sided`
+    fn from_pair(
+        pair: Pair<'_, Rule>, limit: &Limit<'_>, sided: u64,
+    ) -> Result<Self, CompileError> {
+        assert_eq!(pair.as_rule(), Rule::reroll);
+
+        let pair = pair.into_inner().next().unwrap();
+        let once = pair.as_rule() == Rule::reroll_once;
+        let values = pair
+            .into_inner()
+            .map(|p| p.as_str().parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()?;
+        if values.iter().any(|v| !(1..=sided).contains(v)) {
+            return Err(CompileError::RerollOutOfRange);
+        }
+
+        Ok(Self {
+            values,
+            once,
+            max_depth: limit.max_explode_depth(),
+        })
+    }
+}
+
+impl Display for Reroll {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let mode = if self.once { "ro" } else { "r" };
+        for value in &self.values {
+            f.write_fmt(format_args!("{}{}", mode, value))?;
+        }
+        Ok(())
+    }
+}
+
+/// Bounds of every die point, a point out of bounds is changed to the nearest bound, like `4d6mi2`
+///
+/// ## Example
+///
+/// - `4d6mi2` treats point 1 as 2
+/// - `4d6ma5` treats point 6 as 5
+/// - `4d6mi2ma5` does both
+///
+/// Points are clamped after reroll and explosion, and before post processor, so `4d6mi2kh3` keeps highest 3
+/// clamped points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Clamp {
+    /// the minimum point, `None` if points are not raised
+    pub min: Option<u64>,
+    /// the maximum point, `None` if points are not lowered
+    pub max: Option<u64>,
+}
+
+impl Clamp {
+    /// Get point `point` after clamping
+    #[must_use]
+    pub fn apply(self, point: u64) -> u64 {
+        let point = self.min.map_or(point, |min| point.max(min));
+        self.max.map_or(point, |max| point.min(max))
+    }
+
+    // bounds are checked against `sided`
+    #[allow(clippy::cast_sign_loss)] // because bounds are checked to be positive
+    fn from_pair(
+        pair: Pair<'_, Rule>, limit: &Limit<'_>, sided: i64,
+    ) -> Result<Self, CompileError> {
+        assert_eq!(pair.as_rule(), Rule::clamp);
+
+        let mut clamp = Self {
+            min: None,
+            max: None,
+        };
+        for bound in pair.into_inner() {
+            let value = limit.parse_number(bound.as_str())?;
+            if !(1..=sided).contains(&value) {
+                return Err(CompileError::ClampOutOfRange);
+            }
+            match bound.as_rule() {
+                Rule::clamp_min => clamp.min = Some(value as u64),
+                Rule::clamp_max => clamp.max = Some(value as u64),
+                _ => unreachable!(),
+            }
+        }
+
+        if let (Some(min), Some(max)) = (clamp.min, clamp.max) {
+            if min > max {
+                return Err(CompileError::ClampOutOfRange);
+            }
+        }
+        Ok(clamp)
+    }
+
+    /// Get the minimum and maximum possible value of a single point, which is in `min..=max` before clamping
+    #[allow(clippy::cast_possible_wrap)] // because bounds are checked against sided
+    fn bounds(self, min: i64, max: i64) -> (i64, i64) {
+        let clamp = |x: i64| self.apply(u64::try_from(x).unwrap_or_default()) as i64;
+        (clamp(min), clamp(max))
+    }
+}
+
+impl Display for Clamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        if let Some(min) = self.min {
+            f.write_fmt(format_args!("mi{}", min))?;
+        }
+        if let Some(max) = self.max {
+            f.write_fmt(format_args!("ma{}", max))?;
+        }
+        Ok(())
+    }
+}
+
+/// How an exploding die rolls extra dice when it gets max point
+///
+/// ## Example
+///
+/// For `1d6` rolled `6`, `6`, `2`:
+///
+/// - `1d6!`, standard, extra dice are new points, `6 + 6 + 2 = 14`
+/// - `1d6!!`, compound, extra dice are added to the same point, `14`
+/// - `1d6!p`, penetrate, extra dice are new points but minus 1, `6 + 5 + 1 = 12`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExplodeMode {
+    /// `!`, extra dice are new points
+    Standard,
+    /// `!!`, extra dice are added to the point of the die which explodes
+    Compound,
+    /// `!p`, extra dice are new points, but every extra die point is minus 1
+    Penetrate,
+}
+
+impl FromStr for ExplodeMode {
+    type Err = ParseEnumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let res = match s.to_ascii_lowercase().as_str() {
+            "!" => Self::Standard,
+            "!!" => Self::Compound,
+            "!p" => Self::Penetrate,
             _ => return Err(ParseEnumError),
         };
 
@@ -60,17 +397,121 @@ impl FromStr for PostProcessor {
     }
 }
 
+impl Display for ExplodeMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::Standard => "!",
+            Self::Compound => "!!",
+            Self::Penetrate => "!p",
+        })
+    }
+}
+
+/// Explode rule of a dice round, a die explodes when it gets max point
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Explode {
+    /// how extra dice are rolled, see [`ExplodeMode`]
+    ///
+    /// [`ExplodeMode`]: enum.ExplodeMode.html
+    pub mode: ExplodeMode,
+    /// how many extra dice a single die can roll at most, so a `d1` will not explode forever
+    pub max_depth: u64,
+    /// explode count cap written in command, like the `3` in `1d6!x3`, `max_depth` is it clamped by config
+    pub cap: Option<u64>,
+}
+
+impl Explode {
+    fn from_pair(pair: Pair<'_, Rule>, limit: &Limit<'_>) -> Result<Self, CompileError> {
+        let mut pairs = pair.into_inner();
+        let mode = pairs.next().unwrap().as_str().parse().unwrap();
+        let cap = pairs
+            .next()
+            .map(|p| p.as_str().parse::<u64>())
+            .transpose()?;
+        let max_depth = cap.unwrap_or(u64::MAX).min(limit.max_explode_depth());
+        Ok(Self {
+            mode,
+            max_depth,
+            cap,
+        })
+    }
+
+    /// Get the minimum and maximum possible sum of points generated by a single die
+    #[allow(clippy::cast_possible_wrap)] // because limit checked sided and depth are small
+    const fn die_bounds(self, sided: u64) -> (i64, i64) {
+        let (sided, depth) = (sided as i64, self.max_depth as i64);
+        let max = match self.mode {
+            ExplodeMode::Standard | ExplodeMode::Compound => {
+                sided.saturating_mul(depth.saturating_add(1))
+            }
+            ExplodeMode::Penetrate => sided.saturating_add((sided - 1).saturating_mul(depth)),
+        };
+        // a `d1` always explodes until max depth
+        let min = if sided == 1 { max } else { 1 };
+        (min, max)
+    }
+
+    /// Get the minimum and maximum possible value of a single point
+    #[allow(clippy::cast_possible_wrap)] // because limit checked sided is small
+    const fn point_bounds(self, sided: u64) -> (i64, i64) {
+        match self.mode {
+            ExplodeMode::Compound => self.die_bounds(sided),
+            // a max point is followed by an extra die, which can be zero after minus 1
+            ExplodeMode::Penetrate if self.max_depth > 0 => (0, sided as i64),
+            ExplodeMode::Standard | ExplodeMode::Penetrate => (1, sided as i64),
+        }
+    }
+}
+
+/// How many times a round of dice is rolled
+///
+/// ## Example
+///
+/// - `3d6` rolls a fixed times, `3`
+/// - `(1d4)d6` rolls `1d4` first, then rolls a 6 sided dice that many times
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimesSpec {
+    /// A fixed roll times
+    Fixed(u64),
+    /// Roll times is the result of another expr, rolled before this round
+    Expr(Box<AstTreeNode>),
+}
+
 /// Rule of a round of dice roll
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Dice {
-    /// roll dice how many times
-    pub times: u64,
+    /// roll dice how many times, see [`TimesSpec`]
+    ///
+    /// [`TimesSpec`]: enum.TimesSpec.html
+    pub times: TimesSpec,
     /// side count of this dice
     pub sided: u64,
     /// post processing action after all roll, see [`PostProcessor`]
     ///
     /// [`PostProcessor`]: enum.PostProcessor.html
     pub pp: PostProcessor,
+    /// explode rule, `None` if dice do not explode, see [`Explode`]
+    ///
+    /// [`Explode`]: struct.Explode.html
+    pub explode: Option<Explode>,
+    /// reroll rule, `None` if dice never roll again, see [`Reroll`]
+    ///
+    /// [`Reroll`]: struct.Reroll.html
+    pub reroll: Option<Reroll>,
+    /// bounds of every point, `None` if points are not clamped, see [`Clamp`]
+    ///
+    /// [`Clamp`]: struct.Clamp.html
+    pub clamp: Option<Clamp>,
+    /// if this is a fudge dice like `4dF`, which is rolled as a 3 sided dice, point 1, 2 and 3 mean -1, 0 and +1
+    pub fudge: bool,
+    /// face weights of a loaded dice, `weights[i]` is weight of point `i + 1`, `None` for a fair dice,
+    /// see [`new_weighted`]
+    ///
+    /// [`new_weighted`]: #method.new_weighted
+    pub weights: Option<Vec<u32>>,
 }
 
 impl Dice {
@@ -86,146 +527,1046 @@ impl Dice {
     #[must_use]
     pub const fn new_with_pp(n: u64, m: u64, pp: PostProcessor) -> Self {
         Self {
-            times: n,
+            times: TimesSpec::Fixed(n),
             sided: m,
             pp,
+            explode: None,
+            reroll: None,
+            clamp: None,
+            fudge: false,
+            weights: None,
         }
     }
 
-    #[allow(clippy::cast_sign_loss)] // because times and sided can't be negative after check_dice
-    fn from_pair(pair: Pair<'_, Rule>, limit: &mut Limit<'_>) -> Result<Self, CompileError> {
-        assert_eq!(pair.as_rule(), Rule::dice);
+    /// Create a new fudge dice and roll it `n` times, like `4dF`
+    #[must_use]
+    pub const fn new_fudge(n: u64) -> Self {
+        let mut dice = Self::new(n, 3);
+        dice.fudge = true;
+        dice
+    }
 
-        limit.inc_item_count()?;
+    /// Create a preset of 5e Elven Accuracy, three d20 keeping the highest one, same as `3d20kh1`
+    #[must_use]
+    pub const fn elven_accuracy() -> Self {
+        Self::new_with_pp(3, 20, PostProcessor::KeepHighest(1))
+    }
 
-        let mut pairs = pair.into_inner();
-        let times = pairs.next().unwrap().as_str().parse::<i64>()?;
-        let sided = pairs.next().unwrap().as_str().parse::<i64>()?;
+    /// Create a preset of a d20 rolled with advantage, same as `1d20adv`
+    #[must_use]
+    pub const fn advantage() -> Self {
+        Self::new_with_pp(1, 20, PostProcessor::Advantage)
+    }
 
-        limit.check_dice(times, sided)?;
-        limit.inc_roll_times(times as u64)?;
+    /// Create a preset of a d20 rolled with disadvantage, same as `1d20dis`
+    #[must_use]
+    pub const fn disadvantage() -> Self {
+        Self::new_with_pp(1, 20, PostProcessor::Disadvantage)
+    }
 
-        let pp = pairs
-            .next()
-            .map_or(PostProcessor::Sum, |s| s.as_str().parse().unwrap());
+    /// Create a preset of rolling an ability score, four d6 dropping the lowest one, same as `4d6dl1`
+    #[must_use]
+    pub const fn ability_score() -> Self {
+        Self::new_with_pp(4, 6, PostProcessor::DropLowest(1))
+    }
 
+    /// Create a new loaded `sided` sided dice and roll it `n` times, point `i + 1` is rolled with chance
+    /// `weights[i] / sum(weights)`
+    ///
+    /// Loaded dice can't be written in a command, so it's not kept in canonical or displayed form.
+    /// If all weights are equal, the dice is a fair one, same as [`new`].
+    ///
+    /// ## Errors
+    ///
+    /// When count of `weights` is not `sided`, or all weights are zero.
+    ///
+    /// [`new`]: #method.new
+    pub fn new_weighted(n: u64, sided: u64, weights: Vec<u32>) -> Result<Self, CompileError> {
+        if weights.len() as u64 != sided || weights.iter().all(|w| *w == 0) {
+            return Err(CompileError::InvalidDiceWeights);
+        }
+        let fair = weights.windows(2).all(|w| w[0] == w[1]);
         Ok(Self {
-            times: times as u64,
-            sided: sided as u64,
-            pp,
+            weights: if fair { None } else { Some(weights) },
+            ..Self::new(n, sided)
         })
     }
 
-    /// Roll a round of dice and get a result
+    /// Give a new dice, which rolls again with provided rule
     #[must_use]
-    pub fn roll(&self) -> DiceRoll {
-        let points = (0..self.times)
-            .map(|_| nanorand::tls_rng().generate_range(1..=self.sided))
-            .collect();
-        DiceRoll::new(points, self.pp)
+    pub fn with_reroll(self, reroll: Reroll) -> Self {
+        Self {
+            reroll: Some(reroll),
+            ..self
+        }
     }
-}
 
-/// Item in gurgle expression, can be a number or a dice
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub enum Item {
-    /// A normal number
-    Number(i64),
-    /// A dice
-    Dice(Dice),
-    /// Another expr wrapped by parentheses
-    Parentheses(Box<AstTreeNode>),
-}
+    /// Give a new dice, whose points are clamped by provided bounds
+    #[must_use]
+    pub fn with_clamp(self, clamp: Clamp) -> Self {
+        Self {
+            clamp: Some(clamp),
+            ..self
+        }
+    }
 
-impl Item {
+    /// Give a new dice, which explodes with provided rule
+    #[must_use]
+    pub fn with_explode(self, explode: Explode) -> Self {
+        Self {
+            explode: Some(explode),
+            ..self
+        }
+    }
+
+    /// Get sides count of this dice
+    #[must_use]
+    pub const fn sided(&self) -> u64 {
+        self.sided
+    }
+
+    #[allow(clippy::cast_sign_loss)] // because times and sided can't be negative after check_dice
     fn from_pair(pair: Pair<'_, Rule>, limit: &mut Limit<'_>) -> Result<Self, CompileError> {
-        assert_eq!(pair.as_rule(), Rule::item);
+        assert!(core::matches!(pair.as_rule(), Rule::dice | Rule::group));
 
-        let expr = pair.into_inner().next().unwrap();
+        limit.inc_item_count()?;
 
-        let result = match expr.as_rule() {
-            Rule::number => {
-                limit.inc_item_count()?;
-                let x = expr.as_str().parse::<i64>()?;
-                limit.check_number_item(x)?;
-                Self::Number(x)
+        let mut pairs = pair.into_inner();
+        let (times, max_times) = Self::times_from_pair(pairs.next().unwrap(), limit)?;
+        let sided_pair = pairs.next().unwrap();
+        let fudge = sided_pair.as_rule() == Rule::fudge;
+        let sided = match sided_pair.as_rule() {
+            Rule::fudge => 3,
+            Rule::percentile => 100,
+            _ => limit.parse_number(sided_pair.as_str())?,
+        };
+
+        let mut pp = PostProcessor::Sum;
+        let mut explode = None;
+        let mut reroll = None;
+        let mut clamp = None;
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::reroll => reroll = Some(Reroll::from_pair(pair, limit, sided as u64)?),
+                Rule::postprocess => {
+                    pp = PostProcessor::from_pair(pair, limit)?;
+                    pp.check_sided(sided as u64)?;
+                }
+                Rule::explode => explode = Some(Explode::from_pair(pair, limit)?),
+                Rule::clamp => clamp = Some(Clamp::from_pair(pair, limit, sided)?),
+                _ => unreachable!(),
             }
-            Rule::dice => Self::Dice(Dice::from_pair(expr, limit)?),
-            Rule::parentheses => Self::Parentheses(Box::new(AstTreeNode::from_pair(
-                expr.into_inner().next().unwrap(),
-                limit,
-            )?)),
-            _ => unreachable!(),
+        }
+
+        // both dice of every advantage roll count in limits
+        let max_times = match (pp.is_advantage(), &times) {
+            (false, _) => max_times,
+            (true, TimesSpec::Fixed(_)) => max_times.saturating_mul(2),
+            (true, TimesSpec::Expr(_)) => return Err(CompileError::AdvantageRollTimesNotFixed),
         };
+        limit.check_dice(max_times, sided)?;
+        limit.inc_roll_times(max_times as u64)?;
 
-        Ok(result)
+        let dice = Self {
+            times,
+            sided: sided as u64,
+            pp,
+            explode,
+            reroll,
+            clamp,
+            fudge,
+            weights: None,
+        };
+        dice.check_select_count(max_times as u64)?;
+        Ok(dice)
     }
 
-    /// Get roll result
-    #[must_use]
-    pub fn roll(&self) -> ItemRoll {
-        match self {
-            Self::Dice(d) => ItemRoll::Dice(d.roll()),
-            Self::Number(x) => ItemRoll::Number(*x),
-            Self::Parentheses(e) => ItemRoll::Parentheses(Box::new(e.roll())),
+    // keeping no point, or dropping all points when explosion does not add any, always sums 0
+    const fn check_select_count(&self, max_times: u64) -> Result<(), CompileError> {
+        match self.pp {
+            PostProcessor::KeepHighest(0) | PostProcessor::KeepLowest(0) => {
+                Err(CompileError::SelectCountOutOfRange)
+            }
+            PostProcessor::DropHighest(n) | PostProcessor::DropLowest(n)
+                if n >= max_times && self.fixed_points_count() =>
+            {
+                Err(CompileError::SelectCountOutOfRange)
+            }
+            _ => Ok(()),
         }
     }
 
-    /// Check if this item is a number
-    #[must_use]
-    pub const fn is_number(&self) -> bool {
-        std::matches!(self, Item::Number(_))
+    // give roll times spec and its max value
+    #[allow(clippy::cast_sign_loss)] // because negative times is rejected by check_dice later
+    fn times_from_pair(
+        pair: Pair<'_, Rule>, limit: &mut Limit<'_>,
+    ) -> Result<(TimesSpec, i64), CompileError> {
+        match pair.as_rule() {
+            Rule::number => {
+                let times = limit.parse_number(pair.as_str())?;
+                Ok((TimesSpec::Fixed(times as u64), times))
+            }
+            // `d%` means `1d%`, `d20adv` means `1d20adv`
+            Rule::implicit_times => Ok((TimesSpec::Fixed(1), 1)),
+            Rule::parentheses => {
+                let expr = AstTreeNode::from_parentheses_pair(pair, limit)?;
+                let (min, max) = expr.bounds();
+                if min <= 0 {
+                    return Err(CompileError::DiceRollOrSidedNegative);
+                }
+                Ok((TimesSpec::Expr(Box::new(expr)), max))
+            }
+            _ => unreachable!(),
+        }
     }
 
-    /// Check if this item is a dice
-    #[must_use]
-    pub const fn is_dice(&self) -> bool {
-        std::matches!(self, Item::Dice(_))
+    /// Get the minimum and maximum possible result of this round
+    #[allow(clippy::cast_possible_wrap)] // because limit checked sided, times and avg factor are small
+    pub(crate) fn bounds(&self) -> (i64, i64) {
+        if self.fudge {
+            let max_times = match &self.times {
+                TimesSpec::Fixed(n) => *n as i64,
+                TimesSpec::Expr(e) => e.bounds().1,
+            };
+            return (-max_times, max_times);
+        }
+        match self.pp {
+            // a kept advantage die is in bounds of a single die
+            PostProcessor::Sum | PostProcessor::Advantage | PostProcessor::Disadvantage => {
+                let (min_times, max_times) = match &self.times {
+                    TimesSpec::Fixed(n) => (*n as i64, *n as i64),
+                    TimesSpec::Expr(e) => e.bounds(),
+                };
+                // clamped points of an exploding die are still in bounds of unclamped ones
+                let (die_min, die_max) = self
+                    .explode
+                    .map_or_else(|| self.point_bounds(), |e| e.die_bounds(self.sided));
+                (
+                    min_times.saturating_mul(die_min),
+                    max_times.saturating_mul(die_max),
+                )
+            }
+            PostProcessor::Avg | PostProcessor::Max | PostProcessor::Min => self.point_bounds(),
+            PostProcessor::ScaledAvg(factor) => {
+                let (min, max) = self.point_bounds();
+                (
+                    min.saturating_mul(factor as i64),
+                    max.saturating_mul(factor as i64),
+                )
+            }
+            PostProcessor::KeepHighest(_)
+            | PostProcessor::KeepLowest(_)
+            | PostProcessor::DropHighest(_)
+            | PostProcessor::DropLowest(_) => self.selection_bounds(),
+            PostProcessor::CountSuccesses { .. } => (0, self.points_count_bounds().1 as i64),
+        }
     }
 
-    /// Check if this item is a expr
+    /// Get the minimum possible value of this round, without rolling
     #[must_use]
-    pub const fn is_expr(&self) -> bool {
-        std::matches!(self, Item::Parentheses(_))
+    pub fn min_value(&self) -> i64 {
+        self.bounds().0
     }
 
-    /// Try treat this item as a number
+    /// Get the maximum possible value of this round, without rolling
     #[must_use]
-    pub const fn as_number(&self) -> Option<i64> {
-        match self {
-            Self::Number(x) => Some(*x),
-            _ => None,
-        }
+    pub fn max_value(&self) -> i64 {
+        self.bounds().1
     }
 
-    /// Try treat this item as a dice
-    #[must_use]
-    pub const fn as_dice(&self) -> Option<&Dice> {
-        match self {
-            Self::Dice(dice) => Some(dice),
-            _ => None,
-        }
+    #[allow(clippy::cast_possible_wrap)] // because limit checked values are small
+    fn selection_bounds(&self) -> (i64, i64) {
+        let (min_points, max_points) = self.points_count_bounds();
+        let (point_min, point_max) = self.point_bounds();
+        (
+            point_min.saturating_mul(self.pp.selected(min_points).unwrap() as i64),
+            point_max.saturating_mul(self.pp.selected(max_points).unwrap() as i64),
+        )
     }
 
-    /// Try treat this item as a dice
-    #[must_use]
-    pub const fn as_expr(&self) -> Option<&AstTreeNode> {
-        match self {
-            Self::Parentheses(e) => Some(e),
-            _ => None,
+    /// Get the minimum and maximum possible value of a single point
+    #[allow(clippy::cast_possible_wrap)] // because limit checked sided is small
+    fn point_bounds(&self) -> (i64, i64) {
+        let (min, max) = self
+            .explode
+            .map_or((1, self.sided as i64), |e| e.point_bounds(self.sided));
+        self.clamp.map_or((min, max), |c| c.bounds(min, max))
+    }
+
+    /// Get how many dice are rolled for roll times `n`, every advantage roll is two dice
+    pub(crate) const fn rolled_dice(&self, n: u64) -> u64 {
+        if self.pp.is_advantage() {
+            n.saturating_mul(2)
+        } else {
+            n
         }
     }
-}
 
-/// Operator in gurgle expr
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum Operator {
+    /// Get the minimum and maximum count of points this round generates
+    #[allow(clippy::cast_sign_loss)] // because negative values are clamped to zero
+    fn points_count_bounds(&self) -> (u64, u64) {
+        let (min_times, max_times) = match &self.times {
+            TimesSpec::Fixed(n) => (self.rolled_dice(*n), self.rolled_dice(*n)),
+            TimesSpec::Expr(e) => {
+                let (min, max) = e.bounds();
+                (min.max(0) as u64, max.max(0) as u64)
+            }
+        };
+        // extra points of standard and penetrate explosion are new points, so there may be more points
+        let max_points = match self.explode {
+            Some(Explode {
+                mode: ExplodeMode::Standard | ExplodeMode::Penetrate,
+                max_depth,
+                ..
+            }) => max_times.saturating_mul(max_depth.saturating_add(1)),
+            _ => max_times,
+        };
+        (min_times, max_points)
+    }
+
+    /// Get how many items this round contains, including itself and items in roll times expr
+    pub(crate) fn item_count(&self) -> u64 {
+        match &self.times {
+            TimesSpec::Fixed(_) => 1,
+            TimesSpec::Expr(e) => 1 + e.item_count(),
+        }
+    }
+
+    /// Get the maximum count of dice faces could be generated when rolling this round
+    #[allow(clippy::cast_sign_loss)] // because roll times expr is checked to be positive
+    pub(crate) fn max_generations(&self) -> u64 {
+        let per_die = self
+            .explode
+            .map_or(1, |e| e.max_depth.saturating_add(1))
+            .saturating_add(self.reroll.as_ref().map_or(0, Reroll::depth));
+        match &self.times {
+            TimesSpec::Fixed(n) => self.rolled_dice(*n).saturating_mul(per_die),
+            TimesSpec::Expr(e) => (e.bounds().1.max(0) as u64)
+                .saturating_mul(per_die)
+                .saturating_add(e.max_generations()),
+        }
+    }
+
+    /// Call `f` on this round and every round in roll times expr, the round using roll times expr comes first
+    pub(crate) fn for_each_dice<F: FnMut(&Self)>(&self, f: &mut F) {
+        f(self);
+        if let TimesSpec::Expr(e) = &self.times {
+            e.for_each_dice(f);
+        }
+    }
+
+    /// Roll a round of dice and get a result
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn roll(&self) -> DiceRoll {
+        self.roll_with_rng(&mut rng::CommandRng::default_source())
+    }
+
+    /// Roll a round of dice with random source `rng` and get a result
+    #[must_use]
+    pub fn roll_with_rng<R: RollRng + ?Sized>(&self, rng: &mut R) -> DiceRoll {
+        let times_roll = match &self.times {
+            TimesSpec::Fixed(_) => None,
+            TimesSpec::Expr(e) => Some(Box::new(e.roll_with_rng(rng))),
+        };
+        let mut roll = DiceRoll::new(Vec::new(), self.sided, self.pp, times_roll)
+            .with_fudge(self.fudge)
+            .with_weights(self.weights.clone());
+        self.roll_points(rng, &mut roll);
+        roll
+    }
+
+    /// Roll a round of dice again with random source `rng`, reusing buffers of `roll` which is a result of it
+    pub(crate) fn roll_again_with_rng<R: RollRng + ?Sized>(
+        &self, rng: &mut R, roll: &mut DiceRoll,
+    ) {
+        if let (TimesSpec::Expr(e), Some(times)) = (&self.times, roll.times_roll_mut()) {
+            e.roll_again_with_rng(rng, times);
+        }
+        self.roll_points(rng, roll);
+    }
+
+    /// Roll all dice of `roll` again, its roll times expr should be rolled before
+    fn roll_points<R: RollRng + ?Sized>(&self, rng: &mut R, roll: &mut DiceRoll) {
+        let times = match (&self.times, roll.times_roll()) {
+            (TimesSpec::Fixed(n), _) => self.rolled_dice(*n),
+            (TimesSpec::Expr(_), times) => {
+                times.map_or(0, |t| u64::try_from(t.value()).unwrap_or_default())
+            }
+        };
+        let (points, exploded, rerolled) = roll.clear_points(self.explode.map(|e| e.mode));
+        match self.explode {
+            None => {
+                for _ in 0..times {
+                    let mut discarded = Vec::new();
+                    points.push(self.roll_die(rng, &mut discarded));
+                    rerolled.push(discarded);
+                }
+                exploded.resize(points.len(), false);
+            }
+            Some(explode) => {
+                for _ in 0..times {
+                    let discarded = self.roll_exploding_die(explode, rng, points, exploded);
+                    // discarded points belong to the first point generated by this die
+                    rerolled.push(discarded);
+                    rerolled.resize(points.len(), Vec::new());
+                }
+            }
+        }
+        roll.apply_clamp(self.clamp);
+        rng.round_rolled(roll);
+    }
+
+    /// Roll a round of dice with random source `rng` and only get the value, reusing buffers in `arena`
+    ///
+    /// Rolls the same as [`roll_with_rng`], but `round_rolled` of `rng` is not called.
+    ///
+    /// [`roll_with_rng`]: #method.roll_with_rng
+    pub(crate) fn value_in<R: RollRng + ?Sized>(&self, rng: &mut R, arena: &mut RollArena) -> i64 {
+        let times = match &self.times {
+            TimesSpec::Fixed(n) => self.rolled_dice(*n),
+            TimesSpec::Expr(e) => u64::try_from(e.value_in(rng, arena)).unwrap_or_default(),
+        };
+        // roll times expr is done, so buffers are free to use
+        arena.points.clear();
+        arena.exploded.clear();
+        for _ in 0..times {
+            match self.explode {
+                None => {
+                    arena.discarded.clear();
+                    let point = self.roll_die(rng, &mut arena.discarded);
+                    arena.points.push(point);
+                }
+                Some(explode) => {
+                    self.roll_exploding_die(explode, rng, &mut arena.points, &mut arena.exploded);
+                }
+            }
+        }
+        if let Some(clamp) = self.clamp {
+            for point in &mut arena.points {
+                *point = clamp.apply(*point);
+            }
+        }
+        arena.points_value(self.pp, self.explode.map(|e| e.mode), self.fudge)
+    }
+
+    /// Combine this dice round with `other` into one round, like `2d6` and `3d6` into `5d6`
+    ///
+    /// Returns `None` if they can't be merged, because roll result of the combined round is not
+    /// the same as sum of the two rounds.
+    fn merge(&self, other: &Self) -> Option<Self> {
+        let summed_times = |d: &Self| match d.times {
+            TimesSpec::Fixed(n) if d.pp == PostProcessor::Sum => Some(n),
+            _ => None,
+        };
+        if self.sided != other.sided
+            || self.fudge != other.fudge
+            || self.explode != other.explode
+            || self.reroll != other.reroll
+            || self.clamp != other.clamp
+            || self.weights != other.weights
+        {
+            return None;
+        }
+        let times = summed_times(self)?.checked_add(summed_times(other)?)?;
+        Some(Self {
+            times: TimesSpec::Fixed(times),
+            ..self.clone()
+        })
+    }
+
+    /// Roll a single die, roll it again if reroll rule says so, push discarded points
+    fn roll_die<R: RollRng + ?Sized>(&self, rng: &mut R, discarded: &mut Vec<u64>) -> u64 {
+        let mut point = self.roll_face(rng);
+        if let Some(reroll) = &self.reroll {
+            while (discarded.len() as u64) < reroll.depth() && reroll.values.contains(&point) {
+                discarded.push(point);
+                point = self.roll_face(rng);
+            }
+        }
+        point
+    }
+
+    /// Roll a single die once, respecting face weights of a loaded dice
+    fn roll_face<R: RollRng + ?Sized>(&self, rng: &mut R) -> u64 {
+        roll_face(rng, self.sided, self.weights.as_deref())
+    }
+
+    /// Roll a single die which may explode, push generated points and whether they explode,
+    /// returns points discarded by reroll
+    fn roll_exploding_die<R: RollRng + ?Sized>(
+        &self, explode: Explode, rng: &mut R, points: &mut Vec<u64>, exploded: &mut Vec<bool>,
+    ) -> Vec<u64> {
+        let mut depth = 0;
+        let mut total = 0;
+        let mut discarded = Vec::new();
+        loop {
+            // only the first die can roll again
+            let point = if depth == 0 {
+                self.roll_die(rng, &mut discarded)
+            } else {
+                self.roll_face(rng)
+            };
+            let again = point == self.sided && depth < explode.max_depth;
+            match explode.mode {
+                ExplodeMode::Standard => {
+                    points.push(point);
+                    exploded.push(again);
+                }
+                ExplodeMode::Compound => {
+                    total += point;
+                    if !again {
+                        points.push(total);
+                        exploded.push(depth > 0);
+                    }
+                }
+                ExplodeMode::Penetrate => {
+                    points.push(if depth == 0 { point } else { point - 1 });
+                    exploded.push(again);
+                }
+            }
+            if !again {
+                break;
+            }
+            depth += 1;
+        }
+        discarded
+    }
+}
+
+/// Roll a single die once, respecting face weights of a loaded dice, `None` weights for a fair one
+pub(crate) fn roll_face<R: RollRng + ?Sized>(
+    rng: &mut R, sided: u64, weights: Option<&[u32]>,
+) -> u64 {
+    if let Some(weights) = weights {
+        let total = weights.iter().map(|w| u64::from(*w)).sum();
+        let mut remain = rng.roll_die(total);
+        let mut point = 0;
+        for w in weights {
+            point += 1;
+            if remain <= u64::from(*w) {
+                break;
+            }
+            remain -= u64::from(*w);
+        }
+        point
+    } else {
+        rng.roll_die(sided)
+    }
+}
+
+impl Dice {
+    fn write_canonical(&self, out: &mut String) {
+        match &self.times {
+            TimesSpec::Fixed(n) => out.push_str(&n.to_string()),
+            TimesSpec::Expr(e) => {
+                out.push('(');
+                e.write_canonical(out, true);
+                out.push(')');
+            }
+        }
+        // writing to a string never fails
+        self.write_suffix(out).unwrap();
+    }
+
+    // everything after roll times, like `d6r1!max`
+    fn write_suffix<W: Write>(&self, f: &mut W) -> core::fmt::Result {
+        if self.fudge {
+            return f.write_str("dF");
+        }
+        f.write_fmt(format_args!("d{}", self.sided))?;
+        if let Some(reroll) = &self.reroll {
+            f.write_fmt(format_args!("{}", reroll))?;
+        }
+        if let Some(explode) = &self.explode {
+            f.write_fmt(format_args!("{}", explode.mode))?;
+            if let Some(cap) = explode.cap {
+                f.write_fmt(format_args!("x{}", cap))?;
+            }
+        }
+        if let Some(clamp) = &self.clamp {
+            f.write_fmt(format_args!("{}", clamp))?;
+        }
+        f.write_fmt(format_args!("{}", self.pp))
+    }
+}
+
+impl Display for Dice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match &self.times {
+            TimesSpec::Fixed(n) => f.write_fmt(format_args!("{}", n))?,
+            TimesSpec::Expr(e) => f.write_fmt(format_args!("({})", e))?,
+        }
+        self.write_suffix(f)
+    }
+}
+
+impl FromStr for Dice {
+    type Err = CompileError;
+
+    /// Parse a single dice round, like `3d6max` or `(1d4)d6`, limits of default config are checked
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut limit = Limit::new(&DEFAULT_CONFIG);
+        limit.check_source_paren_depth(s)?;
+        let pair = GurgleCommandParser::parse(Rule::dice_command, s)?
+            .next()
+            .unwrap();
+        Self::from_pair(pair, &mut limit)
+    }
+}
+
+/// An item rolled many times, whose results are aggregated into one value
+///
+/// ## Example
+///
+/// - `3#1d20` means sum of roll 1d20 3 times
+/// - `3#1d20 max` means get the max value of roll 1d20 3 times, `avg`, `min` and `sum` are also supported
+/// - `2#(1d4+1) min` means get the min value of roll (1d4+1) 2 times
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Repeat {
+    /// how many times the item is rolled
+    pub times: u64,
+    /// the repeated item
+    pub item: Item,
+    /// how to aggregate results, only sum, avg, max and min are used
+    pub aggregate: PostProcessor,
+}
+
+impl Repeat {
+    fn from_pair(pair: Pair<'_, Rule>, limit: &mut Limit<'_>) -> Result<Self, CompileError> {
+        assert_eq!(pair.as_rule(), Rule::repeat);
+
+        let mut pairs = pair.into_inner();
+
+        let times = pairs.next().unwrap().as_str().parse::<i64>()?;
+        limit.check_repeat(times)?;
+        #[allow(clippy::cast_sign_loss)] // because limit checked times > 0
+        let times = times as u64;
+
+        let (items, rolls) = (limit.item_count, limit.roll_times);
+        let item = Item::from_inner_pair(pairs.next().unwrap(), limit)?;
+        // every repetition evaluates items and rolls dice of the item again
+        limit.inc_repeated(
+            limit.item_count - items,
+            limit.roll_times - rolls,
+            times - 1,
+        )?;
+
+        let aggregate = pairs
+            .next()
+            .map_or(PostProcessor::Sum, |p| p.as_str().parse().unwrap());
+
+        Ok(Self {
+            times,
+            item,
+            aggregate,
+        })
+    }
+
+    /// Get the minimum and maximum possible value of this repeat
+    pub(crate) fn bounds(&self) -> (i64, i64) {
+        let (min, max) = self.item.bounds();
+        match self.aggregate {
+            #[allow(clippy::cast_possible_wrap)] // because limit checked times is small
+            PostProcessor::Sum => (
+                min.saturating_mul(self.times as i64),
+                max.saturating_mul(self.times as i64),
+            ),
+            _ => (min, max),
+        }
+    }
+
+    /// Get roll result with random source `rng`
+    fn roll_with_rng<R: RollRng + ?Sized>(&self, rng: &mut R) -> RepeatRoll {
+        RepeatRoll::new(
+            (0..self.times)
+                .map(|_| self.item.roll_with_rng(rng))
+                .collect(),
+            self.aggregate,
+        )
+    }
+
+    #[allow(clippy::cast_possible_truncation)] // because limit checked times is small
+    fn roll_again_with_rng<R: RollRng + ?Sized>(&self, rng: &mut R, roll: &mut RepeatRoll) {
+        let rolls = roll.rolls_mut();
+        rolls.truncate(self.times as usize);
+        for r in rolls.iter_mut() {
+            self.item.roll_again_with_rng(rng, r);
+        }
+        while (rolls.len() as u64) < self.times {
+            rolls.push(self.item.roll_with_rng(rng));
+        }
+    }
+}
+
+impl Repeat {
+    fn value_in<R: RollRng + ?Sized>(&self, rng: &mut R, arena: &mut RollArena) -> i64 {
+        aggregate_values(
+            self.aggregate,
+            (0..self.times).map(|_| self.item.value_in(rng, arena)),
+            self.times,
+        )
+    }
+}
+
+impl Repeat {
+    fn write_canonical(&self, out: &mut String) {
+        out.push_str(&self.times.to_string());
+        out.push('#');
+        // item is always wrapped, so aggregate can't be parsed as post processor of dice
+        if self.item.is_expr() {
+            self.item.write_canonical(out);
+        } else {
+            out.push('(');
+            self.item.write_canonical(out);
+            out.push(')');
+        }
+        if self.aggregate != PostProcessor::Sum {
+            out.push_str(&self.aggregate.to_string());
+        }
+    }
+}
+
+impl Display for Repeat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_fmt(format_args!("{}#{}", self.times, self.item))?;
+        if self.aggregate != PostProcessor::Sum {
+            f.write_fmt(format_args!(" {}", self.aggregate))?;
+        }
+        Ok(())
+    }
+}
+
+/// Item in gurgle expression, can be a number or a dice
+#[allow(clippy::large_enum_variant)] // because dice is the most common item
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Item {
+    /// A normal number
+    Number(i64),
+    /// A dice
+    Dice(Dice),
+    /// Another expr wrapped by parentheses
+    Parentheses(Box<AstTreeNode>),
+    /// Another item rolled many times
+    Repeat(Box<Repeat>),
+    /// Another item with a label, like `2d6 [damage]`, the label does not change the value
+    Labeled(Box<Self>, String),
+}
+
+impl Item {
+    fn from_pair(pair: Pair<'_, Rule>, limit: &mut Limit<'_>) -> Result<Self, CompileError> {
+        assert_eq!(pair.as_rule(), Rule::item);
+
+        let mut pairs = pair.into_inner();
+        let item = Self::from_inner_pair(pairs.next().unwrap(), limit)?;
+
+        match pairs.next().map(|p| p.into_inner().as_str().trim()) {
+            Some(label) if !label.is_empty() => Ok(Self::Labeled(Box::new(item), label.into())),
+            _ => Ok(item),
+        }
+    }
+
+    fn from_inner_pair(expr: Pair<'_, Rule>, limit: &mut Limit<'_>) -> Result<Self, CompileError> {
+        let result = match expr.as_rule() {
+            Rule::repeat => Self::Repeat(Box::new(Repeat::from_pair(expr, limit)?)),
+            Rule::number => {
+                limit.inc_item_count()?;
+                let x = limit.parse_number(expr.as_str())?;
+                limit.check_number_item(x)?;
+                Self::Number(x)
+            }
+            Rule::dice => Self::Dice(Dice::from_pair(expr, limit)?),
+            Rule::group if expr.clone().into_inner().count() > 1 => {
+                Self::Dice(Dice::from_pair(expr, limit)?)
+            }
+            Rule::group => {
+                let parentheses = expr.into_inner().next().unwrap();
+                match AstTreeNode::from_parentheses_pair(parentheses, limit)? {
+                    // parentheses around a single item is redundant, unwrap it
+                    AstTreeNode::Leaf(item) => item,
+                    tree @ AstTreeNode::Tree(_) => Self::Parentheses(Box::new(tree)),
+                }
+            }
+            _ => unreachable!(),
+        };
+
+        Ok(result)
+    }
+
+    /// Get the minimum and maximum possible value of this item
+    pub(crate) fn bounds(&self) -> (i64, i64) {
+        match self {
+            Self::Dice(d) => d.bounds(),
+            Self::Number(x) => (*x, *x),
+            Self::Parentheses(e) => e.bounds(),
+            Self::Repeat(r) => r.bounds(),
+            Self::Labeled(item, _) => item.bounds(),
+        }
+    }
+
+    /// Get the minimum possible value of this item, without rolling
+    #[must_use]
+    pub fn min_value(&self) -> i64 {
+        self.bounds().0
+    }
+
+    /// Get the maximum possible value of this item, without rolling
+    #[must_use]
+    pub fn max_value(&self) -> i64 {
+        self.bounds().1
+    }
+
+    /// Get how many number and dice items this item contains
+    pub(crate) fn item_count(&self) -> u64 {
+        match self {
+            Self::Dice(d) => d.item_count(),
+            Self::Number(_) => 1,
+            Self::Parentheses(e) => e.item_count(),
+            Self::Repeat(r) => r.item.item_count().saturating_mul(r.times),
+            Self::Labeled(item, _) => item.item_count(),
+        }
+    }
+
+    /// Get the maximum count of dice faces could be generated when rolling this item
+    pub(crate) fn max_generations(&self) -> u64 {
+        match self {
+            Self::Dice(d) => d.max_generations(),
+            Self::Number(_) => 0,
+            Self::Parentheses(e) => e.max_generations(),
+            Self::Repeat(r) => r.item.max_generations().saturating_mul(r.times),
+            Self::Labeled(item, _) => item.max_generations(),
+        }
+    }
+
+    /// Call `f` on every dice round this item contains, from left to right
+    pub(crate) fn for_each_dice<F: FnMut(&Dice)>(&self, f: &mut F) {
+        match self {
+            Self::Dice(d) => d.for_each_dice(f),
+            Self::Number(_) => {}
+            Self::Parentheses(e) => e.for_each_dice(f),
+            Self::Repeat(r) => r.item.for_each_dice(f),
+            Self::Labeled(item, _) => item.for_each_dice(f),
+        }
+    }
+
+    /// Get roll result
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn roll(&self) -> ItemRoll {
+        self.roll_with_rng(&mut rng::CommandRng::default_source())
+    }
+
+    /// Get roll result with random source `rng`
+    #[must_use]
+    pub fn roll_with_rng<R: RollRng + ?Sized>(&self, rng: &mut R) -> ItemRoll {
+        match self {
+            Self::Dice(d) => ItemRoll::Dice(d.roll_with_rng(rng)),
+            Self::Number(x) => ItemRoll::Number(*x),
+            Self::Parentheses(e) => ItemRoll::Parentheses(Box::new(e.roll_with_rng(rng))),
+            Self::Repeat(r) => ItemRoll::Repeat(r.roll_with_rng(rng)),
+            Self::Labeled(item, label) => {
+                ItemRoll::Labeled(Box::new(item.roll_with_rng(rng)), label.clone())
+            }
+        }
+    }
+
+    /// Roll this item again with random source `rng`, reusing memory of `roll` which is a result of it
+    pub(crate) fn roll_again_with_rng<R: RollRng + ?Sized>(
+        &self, rng: &mut R, roll: &mut ItemRoll,
+    ) {
+        match (self, roll) {
+            (Self::Dice(d), ItemRoll::Dice(r)) => d.roll_again_with_rng(rng, r),
+            (Self::Number(x), ItemRoll::Number(r)) => *r = *x,
+            (Self::Parentheses(e), ItemRoll::Parentheses(r)) => e.roll_again_with_rng(rng, r),
+            (Self::Repeat(rep), ItemRoll::Repeat(r)) => rep.roll_again_with_rng(rng, r),
+            (Self::Labeled(item, _), ItemRoll::Labeled(r, _)) => item.roll_again_with_rng(rng, r),
+            (_, roll) => *roll = self.roll_with_rng(rng),
+        }
+    }
+
+    /// Get only roll result value with random source `rng`, reusing buffers in `arena`
+    pub(crate) fn value_in<R: RollRng + ?Sized>(&self, rng: &mut R, arena: &mut RollArena) -> i64 {
+        match self {
+            Self::Dice(d) => d.value_in(rng, arena),
+            Self::Number(x) => *x,
+            Self::Parentheses(e) => e.value_in(rng, arena),
+            Self::Repeat(r) => r.value_in(rng, arena),
+            Self::Labeled(item, _) => item.value_in(rng, arena),
+        }
+    }
+
+    fn merge_like_dice(&self) -> Self {
+        match self {
+            Self::Parentheses(e) => match e.merge_like_dice() {
+                // merged into a single item, like `(2d6+3d6)`, parentheses become redundant
+                AstTreeNode::Leaf(item) => item,
+                tree @ AstTreeNode::Tree(_) => Self::Parentheses(Box::new(tree)),
+            },
+            Self::Repeat(r) => Self::Repeat(Box::new(Repeat {
+                item: r.item.merge_like_dice(),
+                ..r.as_ref().clone()
+            })),
+            Self::Labeled(item, label) => {
+                Self::Labeled(Box::new(item.merge_like_dice()), label.clone())
+            }
+            _ => self.clone(),
+        }
+    }
+
+    fn simplify(&self) -> Self {
+        match self {
+            Self::Parentheses(e) => match e.simplify() {
+                // parentheses around a single item is redundant, unwrap it like the parser does
+                AstTreeNode::Leaf(item) => item,
+                tree @ AstTreeNode::Tree(_) => Self::Parentheses(Box::new(tree)),
+            },
+            Self::Repeat(r) => Self::Repeat(Box::new(Repeat {
+                item: r.item.simplify(),
+                ..r.as_ref().clone()
+            })),
+            Self::Labeled(item, label) => Self::Labeled(Box::new(item.simplify()), label.clone()),
+            _ => self.clone(),
+        }
+    }
+
+    fn write_canonical(&self, out: &mut String) {
+        match self {
+            Self::Number(x) => out.push_str(&x.to_string()),
+            Self::Dice(dice) => dice.write_canonical(out),
+            Self::Parentheses(e) => {
+                out.push('(');
+                e.write_canonical(out, true);
+                out.push(')');
+            }
+            Self::Repeat(r) => r.write_canonical(out),
+            // label does not change how the item rolls, so it is not part of canonical string
+            Self::Labeled(item, _) => item.write_canonical(out),
+        }
+    }
+
+    /// Check if this item is a number
+    #[must_use]
+    pub fn is_number(&self) -> bool {
+        core::matches!(self.unlabeled(), Self::Number(_))
+    }
+
+    /// Check if this item is a dice
+    #[must_use]
+    pub fn is_dice(&self) -> bool {
+        core::matches!(self.unlabeled(), Self::Dice(_))
+    }
+
+    /// Check if this item is a expr
+    #[must_use]
+    pub fn is_expr(&self) -> bool {
+        core::matches!(self.unlabeled(), Self::Parentheses(_))
+    }
+
+    /// Check if this item is a repeat
+    #[must_use]
+    pub fn is_repeat(&self) -> bool {
+        core::matches!(self.unlabeled(), Self::Repeat(_))
+    }
+
+    /// Get label of this item, if it has one
+    #[must_use]
+    pub fn label(&self) -> Option<&str> {
+        match self {
+            Self::Labeled(_, label) => Some(label),
+            _ => None,
+        }
+    }
+
+    // label only changes output, so type queries look through it
+    fn unlabeled(&self) -> &Self {
+        match self {
+            Self::Labeled(item, _) => item.unlabeled(),
+            _ => self,
+        }
+    }
+
+    /// Try treat this item as a number
+    #[must_use]
+    pub fn as_number(&self) -> Option<i64> {
+        match self.unlabeled() {
+            Self::Number(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Try treat this item as a dice
+    #[must_use]
+    pub fn as_dice(&self) -> Option<&Dice> {
+        match self.unlabeled() {
+            Self::Dice(dice) => Some(dice),
+            _ => None,
+        }
+    }
+
+    /// Try treat this item as a repeat
+    #[must_use]
+    pub fn as_repeat(&self) -> Option<&Repeat> {
+        match self.unlabeled() {
+            Self::Repeat(r) => Some(r),
+            _ => None,
+        }
+    }
+
+    /// Try treat this item as a dice
+    #[must_use]
+    pub fn as_expr(&self) -> Option<&AstTreeNode> {
+        match self.unlabeled() {
+            Self::Parentheses(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Number(x) => f.write_fmt(format_args!("{}", x)),
+            Self::Dice(dice) => f.write_fmt(format_args!("{}", dice)),
+            Self::Parentheses(e) => f.write_fmt(format_args!("({})", e)),
+            Self::Repeat(r) => f.write_fmt(format_args!("{}", r)),
+            Self::Labeled(item, label) => f.write_fmt(format_args!("{} [{}]", item, label)),
+        }
+    }
+}
+
+impl FromStr for Item {
+    type Err = CompileError;
+
+    /// Parse a single item, like `3d6`, `5`, `(1d6+1)` or `3#1d20 max`, limits of default config are checked
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut limit = Limit::new(&DEFAULT_CONFIG);
+        limit.check_source_paren_depth(s)?;
+        let pair = GurgleCommandParser::parse(Rule::item_command, s)?
+            .next()
+            .unwrap();
+        Self::from_pair(pair, &mut limit)
+    }
+}
+
+/// Operator in gurgle expr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Operator {
     /// add left tree result and right tree result
     Add,
     /// subtract the right tree result from the left result
     Minus,
     /// multiply left tree result with right tree result
     Multiply,
+    /// divide left tree result by right tree result, round down(floor), so `-7/2` is `-4` and `7/-2` is `-4`
+    Divide,
+    /// remainder of dividing left tree result by right tree result with [`Divide`],
+    /// which has the same sign as the right result, so `-7%2` is `1` and `7%-2` is `-1`
+    ///
+    /// [`Divide`]: #variant.Divide
+    Modulo,
 }
 
 impl FromStr for Operator {
@@ -236,6 +1577,8 @@ impl FromStr for Operator {
             "+" => Self::Add,
             "-" => Self::Minus,
             "x" | "*" => Self::Multiply,
+            "/" => Self::Divide,
+            "%" => Self::Modulo,
             _ => return Err(ParseEnumError),
         };
 
@@ -243,15 +1586,195 @@ impl FromStr for Operator {
     }
 }
 
+impl Operator {
+    /// Higher value binds tighter
+    const fn precedence(self) -> u8 {
+        match self {
+            Self::Add | Self::Minus => 1,
+            Self::Multiply | Self::Divide | Self::Modulo => 2,
+        }
+    }
+
+    /// Get `Minus` for `Add` and `Add` for `Minus`, others are kept as is
+    const fn flipped(self) -> Self {
+        match self {
+            Self::Add => Self::Minus,
+            Self::Minus => Self::Add,
+            op => op,
+        }
+    }
+
+    /// Apply this operator, `None` if overflow or dividing by zero
+    pub(crate) const fn checked_apply(self, left: i64, right: i64) -> Option<i64> {
+        match self {
+            Self::Add => left.checked_add(right),
+            Self::Minus => left.checked_sub(right),
+            Self::Multiply => left.checked_mul(right),
+            Self::Divide => match left.checked_div(right) {
+                // truncated quotient is 1 bigger than floor one when signs differ and not divisible
+                Some(q) if left % right != 0 && (left < 0) != (right < 0) => Some(q - 1),
+                q => q,
+            },
+            Self::Modulo => match left.checked_rem(right) {
+                Some(r) if r != 0 && (r < 0) != (right < 0) => Some(r + right),
+                r => r,
+            },
+        }
+    }
+
+    /// Apply this operator, a huge result keeps its sign instead of panicking or wrapping around,
+    /// and dividing by zero gives zero
+    pub(crate) const fn saturating_apply(self, left: i64, right: i64) -> i64 {
+        match self {
+            Self::Add => left.saturating_add(right),
+            Self::Minus => left.saturating_sub(right),
+            Self::Multiply => left.saturating_mul(right),
+            _ if right == 0 => 0,
+            // only `i64::MIN / -1` overflows, and its remainder is zero
+            Self::Divide => match self.checked_apply(left, right) {
+                Some(x) => x,
+                None => i64::MAX,
+            },
+            Self::Modulo => match self.checked_apply(left, right) {
+                Some(x) => x,
+                None => 0,
+            },
+        }
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::Add => "+",
+            Self::Minus => "-",
+            Self::Multiply => "*",
+            Self::Divide => "/",
+            Self::Modulo => "%",
+        })
+    }
+}
+
 /// Abstract syntax tree of gurgle expr
 pub type AstTree = BinaryTree<Item, Operator>;
 
 impl AstTree {
+    /// Get the minimum and maximum possible value of this tree
+    pub(crate) fn bounds(&self) -> (i64, i64) {
+        let (l_min, l_max) = self.left.bounds();
+        let (r_min, r_max) = self.right.bounds();
+        match self.mid {
+            Operator::Add => (l_min.saturating_add(r_min), l_max.saturating_add(r_max)),
+            Operator::Minus => (l_min.saturating_sub(r_max), l_max.saturating_sub(r_min)),
+            Operator::Multiply => {
+                let corners = [
+                    l_min.saturating_mul(r_min),
+                    l_min.saturating_mul(r_max),
+                    l_max.saturating_mul(r_min),
+                    l_max.saturating_mul(r_max),
+                ];
+                (
+                    *corners.iter().min().unwrap(),
+                    *corners.iter().max().unwrap(),
+                )
+            }
+            Operator::Divide => Self::divide_bounds((l_min, l_max), (r_min, r_max)),
+            // remainder is between zero and divisor, dividing by zero gives zero
+            Operator::Modulo => (
+                if r_min < 0 { r_min + 1 } else { 0 },
+                if r_max > 0 { r_max - 1 } else { 0 },
+            ),
+        }
+    }
+
+    /// Get the minimum possible value of this tree, without rolling
+    #[must_use]
+    pub fn min_value(&self) -> i64 {
+        self.bounds().0
+    }
+
+    /// Get the maximum possible value of this tree, without rolling
+    #[must_use]
+    pub fn max_value(&self) -> i64 {
+        self.bounds().1
+    }
+
+    // quotient is monotonic on both operands when divisor does not cross zero,
+    // so it's enough to check divisors at range ends and closest to zero
+    fn divide_bounds((l_min, l_max): (i64, i64), (r_min, r_max): (i64, i64)) -> (i64, i64) {
+        let divisors = [r_min, r_max, -1, 1];
+        let mut quotients = divisors
+            .iter()
+            .filter(|d| **d != 0 && (r_min..=r_max).contains(*d))
+            .flat_map(|d| {
+                [
+                    Operator::Divide.saturating_apply(l_min, *d),
+                    Operator::Divide.saturating_apply(l_max, *d),
+                ]
+            })
+            .chain((r_min..=r_max).contains(&0).then_some(0));
+        let first = quotients.next().unwrap_or_default();
+        quotients.fold((first, first), |(min, max), q| (min.min(q), max.max(q)))
+    }
+
+    #[cfg(feature = "std")]
     pub fn roll(&self) -> RollTree {
-        RollTree::new(self.left.roll(), self.right.roll(), self.mid)
+        self.roll_with_rng(&mut rng::CommandRng::default_source())
+    }
+
+    /// Get roll result with random source `rng`
+    #[must_use]
+    pub fn roll_with_rng<R: RollRng + ?Sized>(&self, rng: &mut R) -> RollTree {
+        let left = self.left.roll_with_rng(rng);
+        let right = self.right.roll_with_rng(rng);
+        RollTree::new(left, right, self.mid)
+    }
+
+    fn roll_again_with_rng<R: RollRng + ?Sized>(&self, rng: &mut R, roll: &mut RollTree) {
+        self.left.roll_again_with_rng(rng, &mut roll.left);
+        self.right.roll_again_with_rng(rng, &mut roll.right);
+        roll.mid = self.mid;
+        roll.extra.take();
+    }
+}
+
+impl Display for AstTree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        // parser only keeps parentheses item which operator precedence needs, and a manually built tree may not
+        // have them, so add parentheses when needed, all operators are left associative
+        let need_paren = |node: &AstTreeNode, right: bool| match node {
+            AstTreeNode::Tree(t) => {
+                let (child, parent) = (t.mid.precedence(), self.mid.precedence());
+                child < parent || (right && child == parent)
+            }
+            AstTreeNode::Leaf(_) => false,
+        };
+        let write_side = |f: &mut Formatter<'_>, node: &AstTreeNode, right: bool| {
+            if need_paren(node, right) {
+                f.write_fmt(format_args!("({})", node))
+            } else {
+                f.write_fmt(format_args!("{}", node))
+            }
+        };
+
+        write_side(f, &self.left, false)?;
+        f.write_fmt(format_args!("{}", self.mid))?;
+        write_side(f, &self.right, true)
     }
 }
 
+/// A step in path of [`AstTreeNode`], goes into left or right sub tree
+///
+/// [`AstTreeNode`]: type.AstTreeNode.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Side {
+    /// Left sub tree
+    Left,
+    /// Right sub tree
+    Right,
+}
+
 /// Abstract syntax tree node, can be a leaf or a sub tree
 pub type AstTreeNode = BinaryTreeNode<Item, Operator>;
 
@@ -261,26 +1784,739 @@ impl AstTreeNode {
     ) -> Result<Self, CompileError> {
         let pairs = pair.into_inner();
 
-        CLIMBER.climb(
+        // most commands only contains addition and subtraction,
+        // the tree can be built directly, because all operators are left associative
+        if pairs
+            .clone()
+            .all(|p| core::matches!(p.as_rule(), Rule::item | Rule::op_add | Rule::op_sub))
+        {
+            return Self::from_additive_pairs(pairs, limit);
+        }
+
+        Self::climb(pairs, limit)
+    }
+
+    /// Build a tree of the expr inside parentheses, nesting depth is checked by limit
+    pub(crate) fn from_parentheses_pair(
+        pair: Pair<'_, Rule>, limit: &mut Limit<'_>,
+    ) -> Result<Self, CompileError> {
+        assert_eq!(pair.as_rule(), Rule::parentheses);
+
+        limit.enter_parentheses()?;
+        let result = Self::from_pair(pair.into_inner().next().unwrap(), limit)?;
+        limit.exit_parentheses();
+        Ok(result)
+    }
+
+    /// Build a tree of a single item, like the repeated item of command `6#(3d6+2) each`
+    pub(crate) fn from_item_pair(
+        pair: Pair<'_, Rule>, limit: &mut Limit<'_>,
+    ) -> Result<Self, CompileError> {
+        match Item::from_inner_pair(pair, limit)? {
+            Item::Parentheses(tree) => Ok(*tree),
+            item => Ok(Self::Leaf(item)),
+        }
+    }
+
+    fn from_additive_pairs(
+        mut pairs: Pairs<'_, Rule>, limit: &mut Limit<'_>,
+    ) -> Result<Self, CompileError> {
+        let mut root = Self::Leaf(Item::from_pair(pairs.next().unwrap(), limit)?);
+        while let Some(op) = pairs.next() {
+            let right = Self::Leaf(Item::from_pair(pairs.next().unwrap(), limit)?);
+            root = Self::join(root, right, Operator::from_str(op.as_str()).unwrap());
+        }
+        Ok(root)
+    }
+
+    // parentheses which operator precedence does not need are dropped, like the ones in `(1+2)+(3*4)`,
+    // so a command and its fully parenthesized canonical string give the same tree
+    fn join(left: Self, right: Self, op: Operator) -> Self {
+        let unwrap = |node: Self, right: bool| match node {
+            Self::Leaf(Item::Parentheses(tree)) => match tree.as_ref() {
+                Self::Tree(t)
+                    if t.mid.precedence() > op.precedence()
+                        || (!right && t.mid.precedence() == op.precedence()) =>
+                {
+                    *tree
+                }
+                _ => Self::Leaf(Item::Parentheses(tree)),
+            },
+            node => node,
+        };
+        Self::Tree(AstTree::new(unwrap(left, false), unwrap(right, true), op))
+    }
+
+    fn climb(pairs: Pairs<'_, Rule>, limit: &mut Limit<'_>) -> Result<Self, CompileError> {
+        climber().climb(
             pairs,
             |p| {
                 let item = Item::from_pair(p, limit)?;
                 Ok(Self::Leaf(item))
             },
             |left, op, right| {
-                Ok(Self::Tree(AstTree::new(
+                Ok(Self::join(
                     left?,
                     right?,
                     Operator::from_str(op.as_str()).unwrap(),
-                )))
+                ))
             },
         )
     }
 
+    /// Get the minimum and maximum possible value of this node
+    pub(crate) fn bounds(&self) -> (i64, i64) {
+        match self {
+            Self::Leaf(item) => item.bounds(),
+            Self::Tree(tree) => tree.bounds(),
+        }
+    }
+
+    /// Get the minimum possible value of this node, without rolling
+    #[must_use]
+    pub fn min_value(&self) -> i64 {
+        self.bounds().0
+    }
+
+    /// Get the maximum possible value of this node, without rolling
+    #[must_use]
+    pub fn max_value(&self) -> i64 {
+        self.bounds().1
+    }
+
+    /// Get how many number and dice items this node contains
+    pub(crate) fn item_count(&self) -> u64 {
+        match self {
+            Self::Leaf(item) => item.item_count(),
+            Self::Tree(tree) => tree.left.item_count() + tree.right.item_count(),
+        }
+    }
+
+    /// Get the maximum count of dice faces could be generated when rolling this node
+    pub(crate) fn max_generations(&self) -> u64 {
+        match self {
+            Self::Leaf(item) => item.max_generations(),
+            Self::Tree(tree) => tree.left.max_generations() + tree.right.max_generations(),
+        }
+    }
+
+    /// Call `f` on every dice round this node contains, from left to right
+    pub(crate) fn for_each_dice<F: FnMut(&Dice)>(&self, f: &mut F) {
+        match self {
+            Self::Leaf(item) => item.for_each_dice(f),
+            Self::Tree(tree) => {
+                tree.left.for_each_dice(f);
+                tree.right.for_each_dice(f);
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
     pub fn roll(&self) -> RollTreeNode {
+        self.roll_with_rng(&mut rng::CommandRng::default_source())
+    }
+
+    /// Get roll result with random source `rng`
+    #[must_use]
+    pub fn roll_with_rng<R: RollRng + ?Sized>(&self, rng: &mut R) -> RollTreeNode {
+        match self {
+            Self::Leaf(item) => RollTreeNode::Leaf(item.roll_with_rng(rng)),
+            Self::Tree(tree) => RollTreeNode::Tree(tree.roll_with_rng(rng)),
+        }
+    }
+
+    /// Roll again with random source `rng`, reusing memory of `roll` which is a result of this tree
+    ///
+    /// Random source is used in the same order as [`roll_with_rng`], so the result is the same as a new roll.
+    ///
+    /// [`roll_with_rng`]: #method.roll_with_rng
+    pub(crate) fn roll_again_with_rng<R: RollRng + ?Sized>(
+        &self, rng: &mut R, roll: &mut RollTreeNode,
+    ) {
+        match (self, roll) {
+            (Self::Leaf(item), RollTreeNode::Leaf(r)) => item.roll_again_with_rng(rng, r),
+            (Self::Tree(tree), RollTreeNode::Tree(r)) => tree.roll_again_with_rng(rng, r),
+            (_, roll) => *roll = self.roll_with_rng(rng),
+        }
+    }
+
+    /// Get only roll result value with random source `rng`, reusing buffers in `arena`
+    pub(crate) fn value_in<R: RollRng + ?Sized>(&self, rng: &mut R, arena: &mut RollArena) -> i64 {
+        match self {
+            Self::Leaf(item) => item.value_in(rng, arena),
+            Self::Tree(tree) => {
+                let left = tree.left.value_in(rng, arena);
+                let right = tree.right.value_in(rng, arena);
+                tree.mid.saturating_apply(left, right)
+            }
+        }
+    }
+
+    /// Get a new tree, with adjacent added dice rounds of same kind merged into one, like `2d6+3d6` into `5d6`
+    ///
+    /// Only rounds summed by [`Sum`] post processor with same sided, reroll and explode rule are merged,
+    /// so `2d6+3d6max` and `2d6-3d6` are kept as is. Rounds inside parentheses are merged in their own
+    /// sub expr, parentheses left around a single merged round are removed, like `(2d6+3d6)*2` into `5d6*2`.
+    /// The roll times expr of dice is not touched.
+    ///
+    /// New tree is not checked by any [`Config`] limit.
+    ///
+    /// [`Sum`]: enum.PostProcessor.html#variant.Sum
+    /// [`Config`]: ../struct.Config.html
+    #[must_use]
+    pub fn merge_like_dice(&self) -> Self {
+        let tree = match self {
+            Self::Leaf(item) => return Self::Leaf(item.merge_like_dice()),
+            Self::Tree(tree) => tree,
+        };
+        let left = tree.left.merge_like_dice();
+        let right = tree.right.merge_like_dice();
+        if tree.mid == Operator::Add {
+            if let Self::Leaf(Item::Dice(r)) = &right {
+                match &left {
+                    // 2d6+3d6
+                    Self::Leaf(Item::Dice(l)) => {
+                        if let Some(merged) = l.merge(r) {
+                            return Self::Leaf(Item::Dice(merged));
+                        }
+                    }
+                    // 1+2d6+3d6, parsed as (1+2d6)+3d6
+                    Self::Tree(sub) if sub.mid == Operator::Add => {
+                        if let Self::Leaf(Item::Dice(l)) = sub.right.as_ref() {
+                            if let Some(merged) = l.merge(r) {
+                                return Self::Tree(AstTree::new(
+                                    sub.left.as_ref().clone(),
+                                    Self::Leaf(Item::Dice(merged)),
+                                    Operator::Add,
+                                ));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Self::Tree(AstTree::new(left, right, tree.mid))
+    }
+
+    /// Get a new tree, with constant sub exprs folded into one number, like `1+2+3d6*(2*3)` into `3+3d6*6`
+    ///
+    /// Only [`Add`], [`Minus`] and [`Multiply`] of two numbers are folded. A number at the end of a chain of
+    /// addition and subtraction is folded too, like `3d6+1-4` into `3d6-3`. Parentheses around a folded number
+    /// are removed. Dice rounds, labeled items and the roll times expr of dice are not touched.
+    ///
+    /// Folding is skipped if it may overflow, so the new tree rolls exactly the same as this one,
+    /// with the same random source it gets the same result.
+    ///
+    /// New tree is not checked by any [`Config`] limit.
+    ///
+    /// [`Add`]: enum.Operator.html#variant.Add
+    /// [`Minus`]: enum.Operator.html#variant.Minus
+    /// [`Multiply`]: enum.Operator.html#variant.Multiply
+    /// [`Config`]: ../struct.Config.html
+    #[must_use]
+    pub fn simplify(&self) -> Self {
+        let tree = match self {
+            Self::Leaf(item) => return Self::Leaf(item.simplify()),
+            Self::Tree(tree) => tree,
+        };
+        let left = tree.left.simplify();
+        let right = tree.right.simplify();
+        let foldable =
+            |op| core::matches!(op, Operator::Add | Operator::Minus | Operator::Multiply);
+        match (&left, &right) {
+            // 1+2
+            (Self::Leaf(Item::Number(l)), Self::Leaf(Item::Number(r))) if foldable(tree.mid) => {
+                if let Some(x) = tree.mid.checked_apply(*l, *r) {
+                    return Self::Leaf(Item::Number(x));
+                }
+            }
+            // 3d6+1-4, parsed as (3d6+1)-4
+            (Self::Tree(sub), Self::Leaf(Item::Number(b)))
+                if tree.mid.precedence() == 1 && sub.mid.precedence() == 1 =>
+            {
+                if let Self::Leaf(Item::Number(a)) = sub.right.as_ref() {
+                    if let Some(folded) = Self::fold_chain(&sub.left, sub.mid, *a, tree.mid, *b) {
+                        return folded;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Self::Tree(AstTree::new(left, right, tree.mid))
+    }
+
+    // fold `x op1 a op2 b` into `x op1 (a op b)`, only if no step can overflow, so result is exactly the same
+    fn fold_chain(x: &Self, op1: Operator, a: i64, op2: Operator, b: i64) -> Option<Self> {
+        let folded = if op1 == op2 {
+            a.checked_add(b)?
+        } else {
+            a.checked_sub(b)?
+        };
+        let bounds: [i64; 2] = x.bounds().into();
+        for bound in bounds {
+            op2.checked_apply(op1.checked_apply(bound, a)?, b)?;
+            op1.checked_apply(bound, folded)?;
+        }
+        let (op, folded) = match folded {
+            n if n < 0 => (op1.flipped(), n.checked_neg()?),
+            n => (op1, n),
+        };
+        Some(Self::Tree(AstTree::new(
+            x.clone(),
+            Self::Leaf(Item::Number(folded)),
+            op,
+        )))
+    }
+
+    /// Write canonical string of this node to `out`, every sub tree is wrapped in parentheses except `root`
+    pub(crate) fn write_canonical(&self, out: &mut String, root: bool) {
+        match self {
+            Self::Leaf(item) => item.write_canonical(out),
+            Self::Tree(tree) => {
+                if !root {
+                    out.push('(');
+                }
+                tree.left.write_canonical(out, false);
+                out.push_str(&tree.mid.to_string());
+                tree.right.write_canonical(out, false);
+                if !root {
+                    out.push(')');
+                }
+            }
+        }
+    }
+
+    /// Get the node at `path`
+    ///
+    /// Empty path is this node itself, every [`Side`] goes into left or right sub tree of current node.
+    /// Parentheses are transparent, so in `(1+2)*3`, `[Left, Right]` is `2`.
+    /// Roll times expr of dice can't be reached.
+    ///
+    /// Returns `None` if path goes into a leaf node.
+    ///
+    /// [`Side`]: enum.Side.html
+    #[must_use]
+    pub fn get_path(&self, path: &[Side]) -> Option<&Self> {
+        let mut node = self;
+        for side in path {
+            let tree = match node {
+                Self::Tree(tree) => tree,
+                Self::Leaf(Item::Parentheses(e)) => e.as_tree()?,
+                Self::Leaf(_) => return None,
+            };
+            node = match side {
+                Side::Left => &tree.left,
+                Side::Right => &tree.right,
+            };
+        }
+        Some(node)
+    }
+
+    fn get_path_mut(&mut self, path: &[Side]) -> Option<&mut Self> {
+        let mut node = self;
+        for side in path {
+            let tree = match node {
+                Self::Tree(tree) => tree,
+                Self::Leaf(Item::Parentheses(e)) => match e.as_mut() {
+                    Self::Tree(tree) => tree,
+                    Self::Leaf(_) => return None,
+                },
+                Self::Leaf(_) => return None,
+            };
+            node = match side {
+                Side::Left => &mut tree.left,
+                Side::Right => &mut tree.right,
+            };
+        }
+        Some(node)
+    }
+
+    /// Get every dice round in this tree, from left to right in command
+    ///
+    /// Rounds inside parentheses and repeats are included, but rounds in roll times expr(like the `1d4` in `(1d4)d6`)
+    /// are not, a repeated round is given only once.
+    pub fn dices(&self) -> impl Iterator<Item = &Dice> {
+        Dices {
+            stack: vec![DicesStep::Node(self)],
+        }
+    }
+
+    /// Get a new tree, with node at `path` replaced by `node`, see [`get_path`] for path encoding
+    ///
+    /// New tree is not checked by any [`Config`] limit.
+    ///
+    /// Returns `None` if path goes into a leaf node.
+    ///
+    /// [`get_path`]: #method.get_path
+    /// [`Config`]: ../struct.Config.html
+    #[must_use]
+    pub fn replace_path(&self, path: &[Side], node: Self) -> Option<Self> {
+        let mut result = self.clone();
+        *result.get_path_mut(path)? = node;
+        Some(result)
+    }
+}
+
+// walk the tree without recursion, like `RollTree::evaluate`
+struct Dices<'a> {
+    stack: Vec<DicesStep<'a>>,
+}
+
+enum DicesStep<'a> {
+    Node(&'a AstTreeNode),
+    Item(&'a Item),
+}
+
+impl<'a> Iterator for Dices<'a> {
+    type Item = &'a Dice;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(step) = self.stack.pop() {
+            match step {
+                DicesStep::Node(AstTreeNode::Leaf(item)) => self.stack.push(DicesStep::Item(item)),
+                DicesStep::Node(AstTreeNode::Tree(tree)) => {
+                    self.stack.push(DicesStep::Node(&tree.right));
+                    self.stack.push(DicesStep::Node(&tree.left));
+                }
+                DicesStep::Item(Item::Dice(dice)) => return Some(dice),
+                DicesStep::Item(Item::Number(_)) => {}
+                DicesStep::Item(Item::Parentheses(e)) => self.stack.push(DicesStep::Node(e)),
+                DicesStep::Item(Item::Repeat(r)) => self.stack.push(DicesStep::Item(&r.item)),
+                DicesStep::Item(Item::Labeled(item, _)) => self.stack.push(DicesStep::Item(item)),
+            }
+        }
+        None
+    }
+}
+
+impl Display for AstTreeNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         match self {
-            Self::Leaf(item) => RollTreeNode::Leaf(item.roll()),
-            Self::Tree(tree) => RollTreeNode::Tree(tree.roll()),
+            Self::Leaf(item) => f.write_fmt(format_args!("{}", item)),
+            Self::Tree(tree) => f.write_fmt(format_args!("{}", tree)),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use pest::Parser;
+
+    use super::*;
+    use crate::{config::DEFAULT_CONFIG, parser::GurgleCommandParser, rng::Sequence};
+
+    #[test]
+    fn test_presets() {
+        let dice = |s| match crate::Gurgle::compile(s).unwrap().expr() {
+            AstTreeNode::Leaf(Item::Dice(d)) => d.clone(),
+            _ => unreachable!(),
+        };
+
+        let elven = Dice::elven_accuracy();
+        assert_eq!(elven, dice("3d20kh1"));
+        let roll = elven.roll_with_rng(&mut Sequence(vec![7, 18, 3].into_iter()));
+        assert_eq!(roll.points(), &[7, 18, 3]);
+        assert_eq!(roll.value(), 18);
+
+        assert_eq!(Dice::advantage(), dice("1d20adv"));
+        assert_eq!(Dice::advantage().to_string(), "1d20adv");
+        assert_eq!(Dice::disadvantage(), dice("1d20dis"));
+        assert_eq!(Dice::ability_score(), dice("4d6dl1"));
+    }
+
+    #[test]
+    fn test_path() {
+        let compile = |s| crate::Gurgle::compile(s).unwrap().expr().clone();
+
+        let expr = compile("1d6+2d4");
+        assert_eq!(expr.get_path(&[]), Some(&expr));
+        assert_eq!(
+            expr.get_path(&[Side::Right]),
+            Some(&AstTreeNode::Leaf(Item::Dice(Dice::new(2, 4))))
+        );
+        assert_eq!(expr.get_path(&[Side::Right, Side::Left]), None);
+
+        let replaced = expr
+            .replace_path(&[Side::Right], AstTreeNode::Leaf(Item::Number(3)))
+            .unwrap();
+        assert_eq!(replaced, compile("1d6+3"));
+        assert_eq!(expr, compile("1d6+2d4"));
+        assert_eq!(
+            expr.replace_path(&[Side::Left, Side::Left], compile("1")),
+            None
+        );
+
+        let expr = compile("(1+2)*3");
+        assert_eq!(
+            expr.get_path(&[Side::Left, Side::Right]),
+            Some(&compile("2"))
+        );
+        let replaced = expr
+            .replace_path(&[Side::Left, Side::Right], compile("2d6"))
+            .unwrap();
+        assert_eq!(replaced, compile("(1+2d6)*3"));
+    }
+
+    #[test]
+    fn test_dice_from_str() {
+        let dice: Dice = "3d6max".parse().unwrap();
+        assert_eq!(dice, Dice::new_with_pp(3, 6, PostProcessor::Max));
+        assert_eq!(" 4D6kh3 ".parse::<Dice>().unwrap().to_string(), "4d6kh3");
+        assert_eq!("(1d4)d6".parse::<Dice>().unwrap().to_string(), "(1d4)d6");
+        assert_eq!("d%".parse::<Dice>().unwrap(), Dice::new(1, 100));
+
+        for invalid in &["3d6xyz", "3d6+1", "5", "(1d6)", "3d6 max", ""] {
+            assert!(
+                core::matches!(invalid.parse::<Dice>(), Err(CompileError::InvalidSyntax(_))),
+                "{}",
+                invalid
+            );
+        }
+        assert_eq!(
+            "1000d6".parse::<Dice>().unwrap_err(),
+            CompileError::DiceRollTimesLimitExceeded
+        );
+
+        assert_eq!("5".parse::<Item>().unwrap(), Item::Number(5));
+        assert_eq!("3d6".parse::<Item>().unwrap(), Item::Dice(Dice::new(3, 6)));
+        assert_eq!("(1d6+1)".parse::<Item>().unwrap().to_string(), "(1d6+1)");
+        assert_eq!(
+            "3#1d20 max".parse::<Item>().unwrap().to_string(),
+            "3#1d20 max"
+        );
+        assert_eq!("2d6 [fire]".parse::<Item>().unwrap().label(), Some("fire"));
+        assert!("1d6+1".parse::<Item>().is_err());
+        assert_eq!(
+            "100000".parse::<Item>().unwrap_err(),
+            CompileError::NumberItemOutOfRange
+        );
+    }
+
+    #[test]
+    fn test_simplify() {
+        use crate::Gurgle;
+
+        let compile = |s| Gurgle::compile(s).unwrap().expr().clone();
+        let simplified = |s| compile(s).simplify().to_string();
+
+        assert_eq!(
+            compile("1+2*3-4").simplify(),
+            AstTreeNode::Leaf(Item::Number(3))
+        );
+        assert_eq!(compile("(1+2)*(3+4)").simplify(), compile("21"));
+        assert_eq!(simplified("1 + 2 + 3d6 - 1"), "3+3d6-1");
+        assert_eq!(simplified("3d6+1-4"), "3d6-3");
+        assert_eq!(simplified("3d6-1-4+2"), "3d6-3");
+        assert_eq!(simplified("3d6+(2*3)"), "3d6+6");
+        assert_eq!(simplified("2d6*(2+1)+3#(1+1d4+2)"), "2d6*3+3#(1+1d4+2)");
+
+        for command in &[
+            "3d6",
+            "1d6*2*3",
+            "1+3d6+1",
+            "10/3+1d4",
+            "7%4*1d4",
+            "5 [bonus]+1+1d4",
+            "(1d4)d6+1",
+            "1d4*2+1",
+        ] {
+            assert_eq!(
+                simplified(command),
+                compile(command).to_string(),
+                "{}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn test_simplify_rolls_the_same() {
+        use crate::{dist, Gurgle, RollArena};
+
+        let compile = |s| Gurgle::compile(s).unwrap().expr().clone();
+
+        // folding which may overflow is skipped, so saturated value is kept
+        let number = |x| AstTreeNode::Leaf(Item::Number(x));
+        let huge = AstTreeNode::Tree(AstTree::new(
+            AstTreeNode::Leaf(Item::Dice(Dice::new(1, 2))),
+            number(i64::MAX),
+            Operator::Multiply,
+        ));
+        let huge = AstTreeNode::Tree(AstTree::new(
+            AstTreeNode::Tree(AstTree::new(huge, number(1), Operator::Add)),
+            number(2),
+            Operator::Minus,
+        ));
+        assert_eq!(huge.simplify(), huge);
+
+        for command in &[
+            "1 + 2 + 3d6 - 1",
+            "3d6+1-4",
+            "(2*3)+2d4*(1+1)",
+            "2d6max+1-2",
+        ] {
+            let node = compile(command);
+            let simple = node.simplify();
+            let budget = || dist::Budget::new(dist::DEFAULT_BUDGET);
+            assert!(dist::same(
+                &node.distribution(&mut budget()).unwrap(),
+                &simple.distribution(&mut budget()).unwrap()
+            ));
+            assert_eq!(node.bounds(), simple.bounds());
+
+            let mut a = nanorand::WyRand::new_seed(7);
+            let mut b = nanorand::WyRand::new_seed(7);
+            let mut arena = RollArena::new();
+            for _ in 0..100 {
+                assert_eq!(
+                    node.value_in(&mut a, &mut arena),
+                    simple.value_in(&mut b, &mut arena),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_merge_like_dice() {
+        let compile = |s| crate::Gurgle::compile(s).unwrap().expr().clone();
+        let merged = |s| compile(s).merge_like_dice().to_string();
+
+        assert_eq!(compile("2d6+3d6").merge_like_dice(), compile("5d6"));
+        assert_eq!(merged("2d6+3d6+1d6"), "6d6");
+        assert_eq!(merged("1+2d6+3d6*2"), "1+2d6+3d6*2");
+        assert_eq!(merged("1+2d6+3d6-4"), "1+5d6-4");
+        assert_eq!(merged("(2d6+3d6)*2+1d4"), "5d6*2+1d4");
+        assert_eq!(compile("(2d6+3d6)*2").merge_like_dice(), compile("5d6*2"));
+        assert_eq!(merged("(2d6+3d6+1)*2"), "(5d6+1)*2");
+        assert_eq!(merged("2d6!+3d6!+3#(1d8+1d8)"), "5d6!+3#2d8");
+        assert_eq!(compile("3#(1d8+1d8)").merge_like_dice(), compile("3#(2d8)"));
+
+        for command in &[
+            "2d6+3d6max",
+            "2d6-3d6",
+            "1-2d6+3d6",
+            "2*2d6+3d6",
+            "2d6+1+3d6",
+            "2d6+3d8",
+            "2d6r1+3d6",
+            "2d6!+3d6",
+            "(1d4)d6+3d6",
+        ] {
+            assert_eq!(merged(command), compile(command).to_string(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_weighted_dice() {
+        use crate::dist;
+
+        let loaded = Dice::new_weighted(3, 3, vec![1, 0, 3]).unwrap();
+        let roll = loaded.roll_with_rng(&mut Sequence(vec![1, 2, 4].into_iter()));
+        assert_eq!(roll.points(), &[1, 3, 3]);
+        assert_eq!(roll.value(), 7);
+        assert_eq!(loaded.to_string(), "3d3");
+
+        let fair = Dice::new_weighted(2, 6, vec![5; 6]).unwrap();
+        assert_eq!(fair, Dice::new(2, 6));
+
+        let budget = || dist::Budget::new(dist::DEFAULT_BUDGET);
+        let dist = Dice::new_weighted(1, 3, vec![1, 0, 3])
+            .unwrap()
+            .distribution(&mut budget())
+            .unwrap();
+        assert_eq!(dist.into_iter().collect::<Vec<_>>(), [(1, 0.25), (3, 0.75)]);
+        let sum = loaded.distribution(&mut budget()).unwrap();
+        assert!((sum[&9] - 27.0 / 64.0).abs() < 1e-9);
+        assert!(loaded
+            .clone()
+            .with_reroll(Reroll {
+                values: vec![1],
+                once: true,
+                max_depth: 1,
+            })
+            .distribution(&mut budget())
+            .is_none());
+        let exploding_max = Dice {
+            pp: PostProcessor::Max,
+            ..Dice::new_weighted(2, 3, vec![1, 0, 3])
+                .unwrap()
+                .with_explode(Explode {
+                    mode: ExplodeMode::Standard,
+                    max_depth: 2,
+                    cap: None,
+                })
+        };
+        let dist = exploding_max.distribution(&mut budget()).unwrap();
+        assert_eq!(
+            dist.into_iter().collect::<Vec<_>>(),
+            [(1, 0.0625), (3, 0.9375)]
+        );
+        assert_eq!(loaded.merge(&Dice::new(3, 3)), None);
+        assert!(loaded.merge(&loaded).is_some());
+
+        for weights in &[vec![1, 2], vec![0, 0, 0], vec![]] {
+            assert_eq!(
+                Dice::new_weighted(1, 3, weights.clone()),
+                Err(CompileError::InvalidDiceWeights)
+            );
+        }
+    }
+
+    #[test]
+    fn test_explode_mode() {
+        let roll = |mode, max_depth| {
+            let dice = Dice::new(2, 6).with_explode(Explode {
+                mode,
+                max_depth,
+                cap: None,
+            });
+            dice.roll_with_rng(&mut Sequence(vec![6, 6, 2, 6, 6, 6, 4].into_iter()))
+        };
+
+        let standard = roll(ExplodeMode::Standard, 10);
+        assert_eq!(standard.points(), &[6, 6, 2, 6, 6, 6, 4]);
+        assert_eq!(standard.value(), 36);
+        assert_eq!(standard.rolled_times(), 2);
+
+        let compound = roll(ExplodeMode::Compound, 10);
+        assert_eq!(compound.points(), &[14, 22]);
+        assert_eq!(compound.exploded(), &[true, true]);
+        assert_eq!(compound.value(), 36);
+
+        let penetrate = roll(ExplodeMode::Penetrate, 10);
+        assert_eq!(penetrate.points(), &[6, 5, 1, 6, 5, 5, 3]);
+        assert_eq!(penetrate.value(), 31);
+
+        let capped = roll(ExplodeMode::Standard, 1);
+        assert_eq!(capped.points(), &[6, 6, 2]);
+        assert_eq!(capped.exploded(), &[true, false, false]);
+        assert_eq!(capped.rolled_times(), 2);
+    }
+
+    #[test]
+    fn test_additive_fast_path_same_as_climber() {
+        for s in &[
+            "1",
+            "3d6+2d4+1+1+1",
+            "3d6-2d4+1-1+(1d4+1)d6",
+            "-1+-2-3d6max--4",
+        ] {
+            let pairs = GurgleCommandParser::parse(Rule::expr, s)
+                .unwrap()
+                .next()
+                .unwrap()
+                .into_inner();
+
+            let mut limit = Limit::new(&DEFAULT_CONFIG);
+            let fast = AstTreeNode::from_additive_pairs(pairs.clone(), &mut limit).unwrap();
+            let fast_item_count = limit.item_count;
+
+            let mut limit = Limit::new(&DEFAULT_CONFIG);
+            let climbed = AstTreeNode::climb(pairs, &mut limit).unwrap();
+
+            assert_eq!(fast, climbed);
+            assert_eq!(fast_item_count, limit.item_count);
         }
     }
 }
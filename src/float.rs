@@ -0,0 +1,37 @@
+//! float math which only std provides, implemented with `libm` for `no_std` build
+
+/// Same methods as inherent ones of `f64` in std
+pub trait F64Ext {
+    fn floor(self) -> Self;
+    fn round(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn powf(self, n: Self) -> Self;
+    fn mul_add(self, a: Self, b: Self) -> Self;
+}
+
+impl F64Ext for f64 {
+    fn floor(self) -> Self {
+        libm::floor(self)
+    }
+
+    fn round(self) -> Self {
+        libm::round(self)
+    }
+
+    fn sqrt(self) -> Self {
+        libm::sqrt(self)
+    }
+
+    fn powi(self, n: i32) -> Self {
+        libm::pow(self, n.into())
+    }
+
+    fn powf(self, n: Self) -> Self {
+        libm::pow(self, n)
+    }
+
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        libm::fma(self, a, b)
+    }
+}
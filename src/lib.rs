@@ -5,15 +5,18 @@
 //! ### Only need result value
 //!
 //! ```rust
+//! # #[cfg(feature = "std")] {
 //! let attack = "3d6+2d4+1";
 //! println!("roll your attack({}), result: {}", attack, gurgle::roll(attack).unwrap());
 //!
 //! // output: roll your attack(3d6+2d4+1), result: 16
+//! # }
 //! ```
 //!
 //! ### Need check if rolling result is success(pass)
 //!
 //! ```rust
+//! # #[cfg(feature = "std")] {
 //! use gurgle::Gurgle;
 //!
 //! let attack = "3d6+2d4+1>15";
@@ -27,11 +30,13 @@
 //! );
 //!
 //! // output: roll your attack(3d6+2d4+1>15), result: 16, success
+//! # }
 //! ```
 //!
 //! ### Need get rolling result of every dice
 //!
 //! ```rust
+//! # #[cfg(feature = "detail")] {
 //! use gurgle::Gurgle;
 //!
 //! let attack = "3d6+2d4+1>15";
@@ -41,6 +46,7 @@
 //! println!("roll your attack({}), result: {}", attack, result);
 //!
 //! // output: roll your attack(3d6+2d4+1>15), result: (4+3+1) + (1+3) + 1 = 15, target is >15, failed
+//! # }
 //! ```
 //!
 //! Notice: `Display` trait for rolling result is implemented only if
@@ -49,16 +55,84 @@
 //! You can see source code `detail.rs` for how to can walk through result tree
 //! and construct you own output message format.
 //!
+//! With feature `serde`, compiled command, its expression tree, checker, [`Config`] and rolling results
+//! can be serialized. [`Gurgle`] is serialized as its command string, and deserializing it compiles
+//! the string with default config, so limits are still checked. Expression tree types can be
+//! deserialized directly too, but they are not checked by any limit. Rolling results are serialize only.
+//!
+//! Without feature `std`(which is enabled by default), this crate is `no_std` and only needs `alloc`.
+//! There is no default random source then, so use methods like [`Gurgle::roll_with_rng`] with a seeded
+//! `nanorand::WyRand` or your own [`RollRng`], and rolling results can't be shared between threads.
+//! Feature `detail` needs `std`.
+//!
 //! ## Command Syntax
 //!
 //! A Gurgle command is consists of two parts: dice expression([`AstTreeNode`]) and a optional [`Checker`].
 //!
 //! Dice expression is addition or minus of one or more item, item can be a const number or a dice rolling round.
 //!
+//! Numbers can use `_` as digit separator, like `1_000`, see [`Config::digit_separator`] for accepting `,` too.
+//!
 //! Dice rolling round can be write as `x`d`y`: `x` is rolling times, `y` is dice sided,
 //! so it means rolling a `y` sided dice `x` times and sum the result points.
 //!
-//! In addition to summing, a dice rolling round can use `avg`, `max`, and `min` to get the final result of this round.
+//! In addition to summing, a dice rolling round can use `avg`, `max`, and `min` to get the final result of this round,
+//! `avg` can have a factor to keep decimal digits in integer, like `3d6avg10` gives the avg multiplied by 10.
+//!
+//! A round can also sum only some of its points: `4d6kh3` keeps highest 3, `4d6dl1` drops lowest 1,
+//! `kl` and `dh` are keep lowest and drop highest.
+//!
+//! Advantage rolls every die twice and keeps the higher one, `1d20adv`(or just `d20adv`) is like `2d20kh1`,
+//! and `1d20dis` keeps the lower one. `2d6adv` sums the kept die of two pairs, and with explosion, like `1d6!adv`,
+//! extra dice belong to the die which explodes. Both dice count in roll times limit.
+//!
+//! A dice pool can count successes instead of summing: `5d10f8` counts points `>= 8`, and `5d10f<3` counts points `< 3`,
+//! target should be between 1 and dice sides. With a checker, `5d10f8 >= 3` means at least 3 successes.
+//! For Shadowrun like pools, [`GurgleRoll::glitch`] tells if half or more dice show 1, and if it's critical with no hit.
+//!
+//! A die can explode when it gets max point, that is, roll an extra die, add `!` after dice sided to enable it:
+//!
+//! - `3d6!`, standard, extra die is a new point
+//! - `3d6!!`, compound, extra die is added to the same point
+//! - `3d6!p`, penetrate, extra die is a new point, but minus 1
+//!
+//! A die explodes [`Config::max_explode_depth`] times at most, so `1d1!` will not roll forever.
+//! A command can set a lower count for a round by `x` after explode mode, like `1d6!x3` and `1d6!!x2`,
+//! a count greater than config is clamped to it.
+//!
+//! A die can also roll again when it gets some points, add `r` and the point after dice sided:
+//!
+//! - `4d6r1`, roll again until it's not 1, `4d6r1r2` for not 1 nor 2
+//! - `4d6ro1`, roll again only once if it's 1
+//!
+//! Rerolling is limited by [`Config::max_explode_depth`] too, and it comes before explosion, like `4d6r1!`.
+//!
+//! Every die point can be clamped, `4d6mi2` treats points below 2 as 2, and `4d6ma5` treats points above 5 as 5.
+//! Clamp comes after explosion and before post processor, like `4d6!mi2kh3`.
+//!
+//! Percentile dice can be written as `d%` or `3d%`, which are the same as `1d100` and `3d100`.
+//!
+//! Fudge dice, which have faces `-1`, `0` and `+1`, are written as `4dF`, so result can be negative.
+//! They are rolled as 3 sided dice, and do not support reroll, explode or post processor.
+//!
+//! An item can be rolled many times and aggregated by `sum`(default), `avg`, `max` or `min`, write as `n#item aggregate`:
+//!
+//! - `3#1d20 max`, roll 1d20 3 times and get the max value
+//! - `2#(1d4+1) min`, repeated item can be a expr in parentheses
+//!
+//! An item can have a label in brackets after it, like `1d20+5 [to hit] + 2d6 [damage]`, which is shown in
+//! rolling result output but does not change the value. Label is plain text, can't contain brackets.
+//!
+//! ```rust
+//! use gurgle::Gurgle;
+//!
+//! let attack = Gurgle::compile("2d6 [damage]").unwrap();
+//! let damage = attack.expr().as_leaf().unwrap();
+//! assert_eq!(damage.label(), Some("damage"));
+//! assert_eq!(damage.as_dice().unwrap().sided(), 6);
+//! ```
+//!
+//! Many commands separated by `;`, like `3d6; 1d20+5; 2d8`, can be compiled at once by [`Gurgle::compile_many`].
 //!
 //! Some example for easily understand:
 //!
@@ -71,7 +145,11 @@
 //! - ❌️ `0d-10`, `x` and `y` can't be zero or negative value
 //! - ✅️ `2d10-3d2-1`, minus ok
 //! - ✅️ `2d10*3+4`, multiply ok
+//! - ✅️ `2d10/3+4%3`, division and modulo ok, division rounds down(floor) and remainder has the sign of divisor,
+//!   dividing by zero gives `0`, or an error in [`Gurgle::try_roll`]
 //! - ✅️ `(2d6+1)*4+1`, parentheses ok
+//! - ✅️ `(1d4)d6`, roll times can be another expr in parentheses, rolled first
+//! - ✅️ `3d6!max`, explode goes before post processor
 //!
 //! And you can add checker, it a compare with a value, that is, right side of a (in)equation:
 //!
@@ -80,6 +158,28 @@
 //! - `<=10`
 //! - `<10`
 //! - `=10`
+//! - `=~1 10`, equal with a tolerance, result in `9..=11` passes
+//! - `!=10`, any result except `10` passes
+//! - `even` or `odd`, check parity of result
+//! - `in 8..12`, result in range passes, both boundaries included, lower one can't be greater than upper one
+//! - `>=avg(2d4+5)`, compare with expected value of another expr, rounded down(floor), so it's the same as `>=10`
+//! - `>=15 and !=17`, `<5 or >15`, combine checkers, `and` binds tighter than `or`
+//!
+//! Instead of a checker, or in addition to it, you can map result to named tiers:
+//!
+//! - `1d20+5 : 10=hit, 20=crit`, result `>= 10` is a `hit`, `>= 20` is a `crit`, otherwise no tier reached
+//!
+//! When more than one tiers are reached, the one with highest threshold wins.
+//!
+//! At the end of command, you can add a random seed, so rolling result will always be the same:
+//!
+//! - `3d6 @seed=42`
+//!
+//! A whole command can be repeated, every repetition is rolled and checked independently,
+//! see [`Gurgle::roll_repeated`]:
+//!
+//! - `6#(3d6+2) each`, six independent results, unlike `6#(3d6+2)`, which is a single sum of them
+//! - `4#1d20 each >= 11`
 //!
 //! A full example: `3d6+(2d4+1)*2+1 > 20`.
 //!
@@ -88,10 +188,18 @@
 //! So it's the same as: `3d6+(2d4+1)*2+1>20`.
 //!
 //! [`AstTreeNode`]: expr/type.AstTreeNode.html
+//! [`Config::max_explode_depth`]: struct.Config.html#structfield.max_explode_depth
+//! [`Config::digit_separator`]: struct.Config.html#structfield.digit_separator
+//! [`Gurgle::roll_with_rng`]: struct.Gurgle.html#method.roll_with_rng
+//! [`Gurgle::roll_repeated`]: struct.Gurgle.html#method.roll_repeated
+//! [`RollRng`]: trait.RollRng.html
 //! [`Checker`]: checker/struct.Checker.html
+//! [`Gurgle::try_roll`]: struct.Gurgle.html#method.try_roll
+//! [`GurgleRoll::glitch`]: roll/struct.GurgleRoll.html#method.glitch
 
 // ===== lint config =====
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![deny(clippy::all, clippy::pedantic, clippy::nursery)]
 #![deny(missing_debug_implementations, rust_2018_idioms)]
 #![deny(missing_docs)]
@@ -104,40 +212,75 @@
 
 // ===== mods =====
 
+extern crate alloc;
+
 pub mod checker;
 mod config;
 #[cfg(feature = "detail")]
 pub mod detail;
+mod dist;
 pub mod error;
 pub mod expr;
+#[cfg(not(feature = "std"))]
+mod float;
 mod parser;
+mod rng;
 pub mod roll;
+#[cfg(feature = "serde")]
+mod serde_impl;
+#[cfg(feature = "test-util")]
+pub mod test_support;
 mod tree;
 
 // ===== uses =====
 
+use alloc::{boxed::Box, collections::BTreeMap, string::String, vec::Vec};
+use core::fmt::{Debug, Display, Formatter};
+
 use config::Limit;
 use pest::Parser;
 
+// test harness links std, which gives inherent float methods
+#[cfg(not(any(feature = "std", test)))]
+use crate::float::F64Ext;
 use crate::{
-    checker::Checker,
+    checker::{Checker, TieredChecker},
     error::CompileError,
-    expr::AstTreeNode,
+    expr::{AstTreeNode, Item, TimesSpec},
     parser::{GurgleCommandParser, Rule},
     roll::GurgleRoll,
 };
+#[cfg(feature = "std")]
+use crate::{
+    error::RollError,
+    rng::RoundObserved,
+    roll::{BatchRoll, DiceRoll, RollStats},
+};
 
 // ===== pub uses =====
 
-pub use {config::Config, expr::Dice};
+pub use {
+    config::{Config, ConfigFieldDiff, ConfigFieldValue, DigitSeparator},
+    expr::Dice,
+    rng::RollRng,
+    roll::{Crit, Evaluation, Glitch, RollArena},
+};
 
 // ===== implement =====
 
 /// A Compiled gurgle command
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+///
+/// `Display` output is the canonical command string, like `3d6+1>10`, which compiles to an equal command.
+/// `Debug` output shows it too, like `Gurgle("3d6+1>10")`, use `{:#?}` for all fields in detail.
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Gurgle {
     expr: AstTreeNode,
     checker: Option<Checker>,
+    tiers: Option<TieredChecker>,
+    seed: Option<u64>,
+    repeat: Option<u64>,
+    max_intermediate_magnitude: u64,
+    max_distribution_steps: u64,
 }
 
 impl Gurgle {
@@ -149,18 +292,39 @@ impl Gurgle {
     #[allow(clippy::missing_panics_doc)] // because unreachable branch is indeed unreachable
     pub fn compile_with_config(s: &str, config: &Config) -> Result<Self, CompileError> {
         let mut limit = Limit::new(config);
+        limit.check_source_paren_depth(s)?;
         let pairs = GurgleCommandParser::parse(Rule::command, s)?;
 
         let mut expr = None;
         let mut checker = None;
+        let mut tiers = None;
+        let mut seed = None;
+        let mut repeat = None;
 
         for pair in pairs {
             match pair.as_rule() {
                 Rule::expr => {
                     expr.replace(AstTreeNode::from_pair(pair, &mut limit)?);
                 }
+                Rule::command_repeat => {
+                    let mut pairs = pair.into_inner();
+                    let times = pairs.next().unwrap().as_str().parse::<i64>()?;
+                    limit.check_command_repeat(times)?;
+                    #[allow(clippy::cast_sign_loss)] // because limit checked times > 0
+                    repeat.replace(times as u64);
+                    expr.replace(AstTreeNode::from_item_pair(
+                        pairs.next().unwrap(),
+                        &mut limit,
+                    )?);
+                }
                 Rule::checker => {
-                    checker.replace(Checker::from_pair(pair, &limit)?);
+                    checker.replace(Checker::from_pair(pair, &mut limit)?);
+                }
+                Rule::tiers => {
+                    tiers.replace(TieredChecker::from_pair(pair, &limit)?);
+                }
+                Rule::seed => {
+                    seed.replace(pair.as_str().parse::<u64>()?);
                 }
                 Rule::EOI => {}
                 _ => unreachable!(),
@@ -170,6 +334,11 @@ impl Gurgle {
         Ok(Self {
             expr: expr.unwrap(),
             checker,
+            tiers,
+            seed,
+            repeat,
+            max_intermediate_magnitude: limit.max_intermediate_magnitude(),
+            max_distribution_steps: limit.max_distribution_steps(),
         })
     }
 
@@ -185,6 +354,55 @@ impl Gurgle {
         Self::compile_with_config(s, &config::DEFAULT_CONFIG)
     }
 
+    /// Compile string `s` to many gurgle commands separated by `;`, like `3d6; 1d20+5; 2d8`, using `config`
+    ///
+    /// Every command is compiled independently, so limits in `config` apply to each one but not the total.
+    /// Empty commands, like the one after a trailing `;`, are skipped. A `;` inside a label like `[a;b]`
+    /// does not separate commands.
+    ///
+    /// ## Errors
+    ///
+    /// [`CompileError::InCommand`] with index and offset of the first invalid command, and why it's invalid.
+    /// If there is no command at all, it's the error of compiling the empty first one.
+    ///
+    /// [`CompileError::InCommand`]: error/enum.CompileError.html#variant.InCommand
+    pub fn compile_many_with_config(s: &str, config: &Config) -> Result<Vec<Self>, CompileError> {
+        let in_command = |index, offset, error| CompileError::InCommand {
+            index,
+            offset,
+            error: Box::new(error),
+        };
+        let mut commands = Vec::new();
+        for (index, (offset, command)) in split_commands(s).enumerate() {
+            if command.trim().is_empty() {
+                continue;
+            }
+            let gurgle = Self::compile_with_config(command, config)
+                .map_err(|e| in_command(index, offset, e))?;
+            commands.push(gurgle);
+        }
+        if commands.is_empty() {
+            return Err(in_command(
+                0,
+                0,
+                Self::compile_with_config("", config).unwrap_err(),
+            ));
+        }
+        Ok(commands)
+    }
+
+    /// Compile string `s` to many gurgle commands separated by `;`, using [default config]
+    ///
+    /// ## Errors
+    ///
+    /// See [`compile_many_with_config`].
+    ///
+    /// [default config]: struct.config.html#method.default
+    /// [`compile_many_with_config`]: #method.compile_many_with_config
+    pub fn compile_many(s: &str) -> Result<Vec<Self>, CompileError> {
+        Self::compile_many_with_config(s, &config::DEFAULT_CONFIG)
+    }
+
     /// Get the gurgle expression ast tree root node for walk through
     #[must_use]
     pub const fn expr(&self) -> &AstTreeNode {
@@ -197,137 +415,3431 @@ impl Gurgle {
         self.checker.as_ref()
     }
 
-    /// Rolling the compiled command and get result
+    /// Get the gurgle tiered checker
     #[must_use]
-    pub fn roll(&self) -> GurgleRoll<'_> {
-        GurgleRoll::new(self.expr.roll(), self.checker())
+    pub const fn tiered_checker(&self) -> Option<&TieredChecker> {
+        self.tiers.as_ref()
     }
-}
 
-/// Compile then execute a gurgle command immediately, get result value
-///
-/// This function only gives you dice result value, but not check result.
-/// If you need success check, use [`Gurgle::roll`] instead.
-///
-/// ## Errors
-///
-/// If compile `s` as a gurgle command failed, see [`Gurgle::compile`].
-///
-/// [`Gurgle::roll`]: struct.Gurgle.html#method.roll
-/// [`Gurgle::compile`]: struct.Gurgle.html#method.compile
-pub fn roll(s: &str) -> Result<i64, CompileError> {
-    Gurgle::compile(s).map(|x| x.roll().value())
-}
+    /// Get how many times the whole command is repeated, like `6` in `6#(3d6) each`, see [`roll_repeated`]
+    ///
+    /// [`roll_repeated`]: #method.roll_repeated
+    #[must_use]
+    pub const fn repeat(&self) -> Option<u64> {
+        self.repeat
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Get the random seed specified in command, like `3d6 @seed=42`
+    #[must_use]
+    pub const fn seed(&self) -> Option<u64> {
+        self.seed
+    }
 
-    #[test]
-    fn test_parser_correct() {
-        assert!(Gurgle::compile("1d6+1").is_ok());
-        assert!(Gurgle::compile("3d6+2d10+1").is_ok());
-        assert!(Gurgle::compile("3d6max+2d10min+1").is_ok());
-        assert!(Gurgle::compile("3d6max+2d10min+1>=10").is_ok());
-        assert!(Gurgle::compile("3d6max+2d10min+1>=-10").is_ok());
-        assert!(Gurgle::compile("100d1000+-1").is_ok());
-        assert!(Gurgle::compile("100d1000*5").is_ok());
-        assert!(Gurgle::compile("10d1000x1d10").is_ok());
-        assert!(Gurgle::compile("(10d1000)+(1)").is_ok());
-        assert!(Gurgle::compile("3d6 + (2d4 + 1) * 2 + 1>20").is_ok());
-        assert!(Gurgle::compile("3d6+(2d4+1)*2+1 >20").is_ok());
-        assert!(Gurgle::compile("3d6+(2d4+1)*2+1> 20").is_ok());
-        assert!(Gurgle::compile("3d6+(2d4+1)*2+1 > 20").is_ok());
+    /// Get how many items(numbers and dices) the expression contains
+    ///
+    /// Items in parentheses and in roll times expr are counted too, items in a repeat are counted for every repetition,
+    /// so this is the same value as what is checked with [`Config::max_item_count`] when compiling.
+    ///
+    /// [`Config::max_item_count`]: struct.Config.html#structfield.max_item_count
+    #[must_use]
+    pub fn item_count(&self) -> u64 {
+        self.expr.item_count()
     }
 
-    #[test]
-    fn test_parser_invalid() {
-        assert!(std::matches!(
-            Gurgle::compile("+").unwrap_err(),
-            CompileError::InvalidSyntax(_)
-        ));
-        assert!(std::matches!(
-            Gurgle::compile("good").unwrap_err(),
-            CompileError::InvalidSyntax(_)
-        ));
-        assert!(std::matches!(
-            Gurgle::compile("3d6+2p10+1").unwrap_err(),
-            CompileError::InvalidSyntax(_)
-        ));
-        assert!(std::matches!(
-            Gurgle::compile("3d6max+2d10min+1avg").unwrap_err(),
-            CompileError::InvalidSyntax(_)
-        ));
-        assert!(std::matches!(
-            Gurgle::compile("3d6+(1").unwrap_err(),
-            CompileError::InvalidSyntax(_),
-        ));
-        assert!(std::matches!(
-            Gurgle::compile("3d6 max+2d10min+1avg").unwrap_err(),
-            CompileError::InvalidSyntax(_)
-        ));
-        assert!(std::matches!(
-            Gurgle::compile("3d6+100000000000000000000000000").unwrap_err(),
-            CompileError::ParseNumberError(_),
-        ));
+    /// Check if any dice round in command has `sided` sides, rounds in roll times expr are included
+    ///
+    /// Fudge dice have no sides count, so `4dF` does not contain size 3.
+    #[must_use]
+    pub fn contains_dice_size(&self, sided: u64) -> bool {
+        let mut found = false;
+        self.expr
+            .for_each_dice(&mut |d| found |= !d.fudge && d.sided() == sided);
+        found
     }
 
-    #[test]
-    fn test_compile_error() {
-        assert_eq!(
-            Gurgle::compile("10d-10").unwrap_err(),
-            CompileError::DiceRollOrSidedNegative,
-        );
-        assert_eq!(
-            Gurgle::compile("-10d10").unwrap_err(),
-            CompileError::DiceRollOrSidedNegative,
-        );
-        assert_eq!(
-            Gurgle::compile(
-                "3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+1"
-            )
-            .unwrap_err(),
-            CompileError::ItemCountLimitExceeded,
-        );
-        assert_eq!(
-            Gurgle::compile("10d1001").unwrap_err(),
-            CompileError::DiceSidedCountLimitExceeded,
-        );
-        assert_eq!(
-            Gurgle::compile("1001d10").unwrap_err(),
-            CompileError::DiceRollTimesLimitExceeded,
-        );
-        assert_eq!(
-            Gurgle::compile("1000d10+1d10").unwrap_err(),
-            CompileError::DiceRollTimesLimitExceeded,
-        );
-        assert_eq!(
-            Gurgle::compile("65537").unwrap_err(),
-            CompileError::NumberItemOutOfRange,
-        );
-        assert_eq!(
-            Gurgle::compile("-65537").unwrap_err(),
-            CompileError::NumberItemOutOfRange,
-        );
+    /// Check if every dice round in command has a sides count in `allowed`, like a d6 only table
+    ///
+    /// Rounds in roll times expr are included, fudge dice rounds are skipped,
+    /// a command without any dice always passes.
+    #[must_use]
+    pub fn only_uses_sizes(&self, allowed: &[u64]) -> bool {
+        let mut only = true;
+        self.expr
+            .for_each_dice(&mut |d| only &= d.fudge || allowed.contains(&d.sided()));
+        only
     }
 
-    #[test]
-    fn test_roll() {
-        // detail::Language::set_global(detail::Language::ZhCN);
-        // detail::Language::set_global_custom(detail::OutputSpans {
-        //     comma: "| ".into(),
-        //     target_is: "we want".into(),
-        //     success: "passed".into(),
-        //     failed: "over".into(),
-        // });
-        let attack = Gurgle::compile("3d6min+3d6avg+3d6max+3d6+(2d4+1)*2+1>15").unwrap();
-        let result = attack.roll();
+    /// Get the largest roll times among all dice rounds in command, like for pre-sizing a faces display area
+    ///
+    /// Roll times expr is counted by its max possible value, exploded extra points are not counted,
+    /// see [`max_dice_generations`] for that. A command without any dice gives 0.
+    ///
+    /// [`max_dice_generations`]: #method.max_dice_generations
+    #[must_use]
+    #[allow(clippy::cast_sign_loss)] // because roll times expr is checked to be positive
+    pub fn max_pool_size(&self) -> u64 {
+        let mut size = 0;
+        self.expr.for_each_dice(&mut |d| {
+            let times = match &d.times {
+                TimesSpec::Fixed(n) => *n,
+                TimesSpec::Expr(e) => e.bounds().1 as u64,
+            };
+            size = size.max(times);
+        });
+        size
+    }
 
-        #[cfg(feature = "detail")]
-        println!("attack rolling result is: {}", result);
+    /// Get canonical string of the dice expression, which is stable and unambiguous, like a dedup or storage key
+    ///
+    /// Unlike `Display`, which only writes necessary parentheses, every sub expression is wrapped
+    /// in parentheses, and no whitespace is written, so `1+2*3` gives `1+(2*3)`. Compiling it again gives
+    /// an expression with the same canonical string, which rolls the same.
+    ///
+    /// Only the dice expression is included, checker, tiers and seed are not.
+    #[must_use]
+    pub fn to_canonical_ast(&self) -> String {
+        let mut result = String::new();
+        self.expr.write_canonical(&mut result, true);
+        result
+    }
 
-        println!("attack = {}", result.value());
-        assert!(result.value() >= 13);
-        assert_eq!(result.success().unwrap(), result.value() > 15);
+    /// Get the minimum possible result value of this command, without rolling
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use gurgle::Gurgle;
+    ///
+    /// let attack = Gurgle::compile("2d10-3d2-1").unwrap();
+    /// assert_eq!(attack.min_result(), -5);
+    /// assert_eq!(attack.max_result(), 16);
+    /// ```
+    #[must_use]
+    pub fn min_result(&self) -> i64 {
+        self.expr.min_value()
+    }
+
+    /// Get the maximum possible result value of this command, without rolling, see [`min_result`]
+    ///
+    /// [`min_result`]: #method.min_result
+    #[must_use]
+    pub fn max_result(&self) -> i64 {
+        self.expr.max_value()
+    }
+
+    /// Get the maximum count of dice faces this command could generate in one roll, without rolling
+    ///
+    /// This is the worst case, for example, `(1d4)d6` is counted as 5:
+    /// one for the `1d4`, and four for the `d6` when `1d4` gives its max value.
+    #[must_use]
+    pub fn max_dice_generations(&self) -> u64 {
+        self.expr.max_generations()
+    }
+
+    /// Get the most likely result value of this command, without rolling
+    ///
+    /// If more than one value have the highest probability, the lowest one is returned.
+    ///
+    /// Returns `None` if the state space of this command is too large to calculate.
+    #[must_use]
+    pub fn mode(&self) -> Option<i64> {
+        let dist = self.distribution()?;
+        let mut mode: Option<(i64, f64)> = None;
+        for (value, p) in dist {
+            // tolerance for float rounding error, so ties are resolved to lowest value
+            if !core::matches!(mode, Some((_, best)) if p <= best * (1.0 + 1e-9)) {
+                mode.replace((value, p));
+            }
+        }
+        mode.map(|(value, _)| value)
+    }
+
+    /// Get the expected(average) result value of this command, without rolling it many times
+    ///
+    /// Summed dice rounds use exact formulas, like `times * (sided + 1) / 2` for `3d6`,
+    /// other rounds(`avg`, `max`, `min` and `f`) are calculated exactly from their distribution.
+    /// Results of operators are combined from means of both sides, `/` and `%` use their distribution too,
+    /// and so do sub exprs which may saturate at `i64` bounds, so the mean is of saturated values, like rolling.
+    ///
+    /// If the exact value can't be calculated, because the state space is too large, or the command uses
+    /// keep/drop modifiers or a round both explodes and rerolls, it's approximated by the average of
+    /// 100000 rolls with a fixed seed, so the result is stable between calls.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use gurgle::Gurgle;
+    ///
+    /// assert!((Gurgle::compile("3d6+1").unwrap().expected_value() - 11.5).abs() < 1e-9);
+    /// ```
+    #[must_use]
+    pub fn expected_value(&self) -> f64 {
+        self.expr.moments(&mut self.budget()).map_or_else(
+            || self.expr.sampled_mean(dist::EXPECTATION_SAMPLES),
+            |m| m.mean,
+        )
+    }
+
+    /// Get the variance of result value, without rolling
+    ///
+    /// Summed dice rounds use exact formulas, like `(sided^2 - 1) / 12` for a single die,
+    /// other rounds(`avg`, `max` and `min`) are calculated from their distribution.
+    ///
+    /// Returns `None` if the state space of this command is too large to calculate.
+    #[must_use]
+    pub fn variance(&self) -> Option<f64> {
+        self.expr
+            .moments(&mut self.budget())
+            .map(|m| m.variance.max(0.0))
+    }
+
+    /// Get the standard deviation of result value, without rolling
+    ///
+    /// See [`variance`].
+    ///
+    /// [`variance`]: #method.variance
+    #[must_use]
+    pub fn std_dev(&self) -> Option<f64> {
+        self.variance().map(f64::sqrt)
+    }
+
+    /// Get the probability of result being greater than or equal to every possible value, without rolling
+    ///
+    /// It's like a "chance to hit DC" curve, for `1d6`, that's `1 => 1.0, 2 => 5/6, ..., 6 => 1/6`.
+    ///
+    /// Returns `None` if the state space of this command is too large to calculate.
+    #[must_use]
+    pub fn cumulative(&self) -> Option<BTreeMap<i64, f64>> {
+        Some(dist::at_least(&self.distribution()?))
+    }
+
+    /// Check if this command and `other` have identical result distributions, without rolling
+    ///
+    /// That is, an opposed roll between them is fair.
+    ///
+    /// Returns `false` if the state space of either command is too large to calculate.
+    #[must_use]
+    pub fn symmetric_with(&self, other: &Self) -> bool {
+        match (self.distribution(), other.distribution()) {
+            (Some(a), Some(b)) => dist::same(&a, &b),
+            _ => false,
+        }
+    }
+
+    /// Get the probability of this command and `other` giving the same result value, without rolling
+    ///
+    /// Returns `None` if the state space of either command is too large to calculate.
+    #[must_use]
+    pub fn tie_probability(&self, other: &Self) -> Option<f64> {
+        Some(dist::tie(&self.distribution()?, &other.distribution()?))
+    }
+
+    /// Get the probability that at least one `sided` sided die rolls its max face, like a natural 20, without rolling
+    ///
+    /// Dice are independent, so it's `1 - (1 - 1 / sided)^n` for `n` dice, like `1 - (19/20)^2` for `2d20kh1`.
+    /// Dice in roll times expr and repeats are counted too, fudge dice are not. Only the first roll of every die
+    /// counts, exploded and rerolled points are not.
+    ///
+    /// Roll times expr is taken by its distribution, independent of its own dice, or its max value if the state
+    /// space is too large, which gives an upper bound.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use gurgle::Gurgle;
+    ///
+    /// assert!((Gurgle::compile("1d20+5").unwrap().crit_probability(20) - 0.05).abs() < 1e-9);
+    /// ```
+    #[must_use]
+    pub fn crit_probability(&self, sided: u64) -> f64 {
+        1.0 - self.expr.crit_miss(sided, &mut self.budget())
+    }
+
+    /// Get the probability of a roll passing the checker, without rolling
+    ///
+    /// That's the sum of probability of every result value which [`Checker::check`] accepts.
+    ///
+    /// Returns `None` if this command has no checker, or the state space is too large to calculate.
+    ///
+    /// [`Checker::check`]: checker/enum.Checker.html#method.check
+    #[must_use]
+    pub fn success_probability(&self) -> Option<f64> {
+        let checker = self.checker.as_ref()?;
+        let dist = self.distribution()?;
+        // sum of an empty f64 iterator is -0.0, fold from 0.0 to get 0.0 for impossible checks
+        Some(
+            dist.into_iter()
+                .filter(|(v, _)| checker.check(*v))
+                .fold(0.0, |acc, (_, p)| acc + p),
+        )
+    }
+
+    /// Get the exact probability of every possible result value, without rolling
+    ///
+    /// Every summed `xdy` round is the convolution of `x` uniform distributions over `1..=y`, operators combine
+    /// distributions of both sides, and numbers are constants. Other rounds(`avg`, `kh3`, `f8`, etc) and repeats
+    /// are calculated from their own distribution too. Modifier and checker are not applied.
+    ///
+    /// Calculation is limited by [`Config::max_distribution_steps`] used when compiling, so a huge state space,
+    /// like `100d1000`, is rejected instead of using too much time or memory.
+    ///
+    /// Returns `None` if the state space of this command is too large to calculate.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use gurgle::Gurgle;
+    ///
+    /// let dist = Gurgle::compile("3d6").unwrap().distribution().unwrap();
+    /// let chance: f64 = dist.range(15..).map(|(_, p)| p).sum();
+    /// assert!((chance - 20.0 / 216.0).abs() < 1e-9);
+    /// ```
+    ///
+    /// [`Config::max_distribution_steps`]: struct.Config.html#structfield.max_distribution_steps
+    #[must_use]
+    pub fn distribution(&self) -> Option<BTreeMap<i64, f64>> {
+        self.expr.distribution(&mut self.budget())
+    }
+
+    const fn budget(&self) -> dist::Budget {
+        dist::Budget::new(self.max_distribution_steps)
+    }
+
+    /// Rolling the compiled command and get result
+    ///
+    /// If the command specified a [`seed`], a new random generator is created from it for every call,
+    /// so the result is always the same.
+    ///
+    /// Intermediate values are not checked, use [`try_roll`] if command may overflow.
+    ///
+    /// Every call builds a new result, use [`GurgleRoll::reroll`] to roll again reusing a result's memory.
+    ///
+    /// [`seed`]: #method.seed
+    /// [`try_roll`]: #method.try_roll
+    /// [`GurgleRoll::reroll`]: roll/struct.GurgleRoll.html#method.reroll
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn roll(&self) -> GurgleRoll<'_> {
+        let mut rng = self.rng();
+        self.roll_with_rng(&mut rng).seeded_by(&rng)
+    }
+
+    /// Rolling the compiled command and get result, checking magnitude of every intermediate value
+    ///
+    /// Seed is respected as in [`roll`].
+    ///
+    /// ## Errors
+    ///
+    /// If any operand or result of arithmetic exceeds [`Config::max_intermediate_magnitude`] used when compiling,
+    /// like `1000*1000*1000-1000*1000*1000` with a limit of `1000000`, even if the final value is small.
+    ///
+    /// If any divisor is zero, like `1d6/(1d2-1)` rolls `1d2` a `1`, which gives `0` in [`roll`].
+    ///
+    /// [`roll`]: #method.roll
+    /// [`Config::max_intermediate_magnitude`]: struct.Config.html#structfield.max_intermediate_magnitude
+    #[cfg(feature = "std")]
+    pub fn try_roll(&self) -> Result<GurgleRoll<'_>, RollError> {
+        let result = self.roll();
+        result
+            .expr()
+            .checked_value(self.max_intermediate_magnitude)?;
+        Ok(result)
+    }
+
+    /// Rolling the compiled command and only get result value, reusing buffers in `arena`
+    ///
+    /// It's the same as `roll().value()`, seed is respected too, but no rolling result tree is created,
+    /// so rolling many times with the same `arena` does not allocate memory for every roll.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use gurgle::{Gurgle, RollArena};
+    ///
+    /// let attack = Gurgle::compile("4d6+2d8").unwrap();
+    /// let mut arena = RollArena::new();
+    /// let total: i64 = (0..1000).map(|_| attack.roll_in(&mut arena)).sum();
+    /// assert!((6000..=40000).contains(&total));
+    /// ```
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn roll_in(&self, arena: &mut RollArena) -> i64 {
+        self.expr.value_in(&mut self.rng(), arena)
+    }
+
+    /// Rolling the compiled command `n` times, and only get result values
+    ///
+    /// If the command specified a [`seed`], a single random generator is created from it for all rolling,
+    /// so the values are different from each other but always the same sequence.
+    ///
+    /// [`seed`]: #method.seed
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn roll_many_values(&self, n: usize) -> Vec<i64> {
+        self.roll_n(n).collect()
+    }
+
+    /// Rolling the compiled command `n` times lazily, and only get result values
+    ///
+    /// Like [`roll_in`], no rolling result tree is created, and buffers are reused between rolls,
+    /// so it's faster for Monte-Carlo simulation. Seed is respected like [`roll_many_values`].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use gurgle::Gurgle;
+    ///
+    /// let attack = Gurgle::compile("1d20+5").unwrap();
+    /// let hits = attack.roll_n(1000).filter(|v| *v >= 15).count();
+    /// assert!(hits <= 1000);
+    /// ```
+    ///
+    /// [`roll_in`]: #method.roll_in
+    /// [`roll_many_values`]: #method.roll_many_values
+    #[cfg(feature = "std")]
+    pub fn roll_n(&self, n: usize) -> impl Iterator<Item = i64> + '_ {
+        let mut rng = self.rng();
+        let mut arena = RollArena::new();
+        (0..n).map(move |_| self.expr.value_in(&mut rng, &mut arena))
+    }
+
+    /// Rolling the compiled command `n` times, and get statistics of result values, see [`RollStats`]
+    ///
+    /// Values are rolled by [`roll_n`], so seed is respected too.
+    ///
+    /// [`RollStats`]: roll/struct.RollStats.html
+    /// [`roll_n`]: #method.roll_n
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn roll_stats(&self, n: usize) -> RollStats {
+        self.roll_n(n).collect()
+    }
+
+    /// Rolling the compiled command `count` times, like generating a full ability array, and get all results
+    ///
+    /// Seed is respected like [`roll_many_values`], results are different from each other but always
+    /// the same sequence.
+    ///
+    /// [`roll_many_values`]: #method.roll_many_values
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn roll_array(&self, count: usize) -> Vec<GurgleRoll<'_>> {
+        let mut rng = self.rng();
+        (0..count)
+            .map(|_| self.roll_with_rng(&mut rng).seeded_by(&rng))
+            .collect()
+    }
+
+    /// Rolling the compiled command as many times as it's repeated, like `6#(3d6) each` gives 6 results
+    ///
+    /// Every repetition is rolled independently and checked by the checker, seed is respected like [`roll_array`].
+    /// A command without repeat gives a single result, and [`roll`] always rolls a single repetition.
+    ///
+    /// Results are given as a batch like [`roll_batch`], whose `Display` shows every result on its own line.
+    ///
+    /// [`roll_array`]: #method.roll_array
+    /// [`roll`]: #method.roll
+    /// [`roll_batch`]: #method.roll_batch
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn roll_repeated(&self) -> BatchRoll<'_> {
+        #[allow(clippy::cast_possible_truncation)] // because limit checked repeat is small
+        self.roll_batch(self.repeat.unwrap_or(1) as usize)
+    }
+
+    /// Rolling the compiled command `count` times like [`roll_array`], and get results as a batch
+    ///
+    /// See [`BatchRoll::summary`] for how many of them passed the checker.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use gurgle::Gurgle;
+    ///
+    /// let save = Gurgle::compile("1d20>=11").unwrap();
+    /// let summary = save.roll_batch(100).summary();
+    /// assert_eq!(summary.total, 100);
+    /// assert!(summary.pass_count <= 100);
+    /// ```
+    ///
+    /// [`roll_array`]: #method.roll_array
+    /// [`BatchRoll::summary`]: roll/struct.BatchRoll.html#method.summary
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn roll_batch(&self, count: usize) -> BatchRoll<'_> {
+        BatchRoll::new(self.roll_array(count))
+    }
+
+    /// Rolling the compiled command and get result, checked by `checker` instead of the compiled one
+    ///
+    /// Seed is respected as in [`roll`].
+    ///
+    /// [`roll`]: #method.roll
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn roll_checked<'a>(&'a self, checker: &'a Checker) -> GurgleRoll<'a> {
+        let mut rng = self.rng();
+        GurgleRoll::new(
+            self.expr.roll_with_rng(&mut rng),
+            &self.expr,
+            Some(checker),
+            self.tiered_checker(),
+        )
+        .seeded_by(&rng)
+    }
+
+    /// Rolling the compiled command and get result, `f` is called once for every dice round after it's fully rolled
+    ///
+    /// Dice rounds in roll times expr are reported before the round using it, like `1d4` before `(1d4)d6`.
+    ///
+    /// Seed is respected as in [`roll`].
+    ///
+    /// [`roll`]: #method.roll
+    #[cfg(feature = "std")]
+    pub fn roll_with_round_observer<F: FnMut(&DiceRoll)>(&self, f: F) -> GurgleRoll<'_> {
+        let mut rng = self.rng();
+        let result = self.roll_with_rng(&mut RoundObserved::new(&mut rng, f));
+        result.seeded_by(&rng)
+    }
+
+    /// Rolling the compiled command and get result, with result value changed by modifier `f`, like a temporary buff
+    ///
+    /// Result value is `f(value)`, checker and tiers use the modified value,
+    /// detailed output shows the raw value and how much it's changed, like `(4+5) = 9 + 2 = 11`.
+    ///
+    /// Seed is respected as in [`roll`].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use gurgle::Gurgle;
+    ///
+    /// let attack = Gurgle::compile("1d20>=15").unwrap();
+    /// let result = attack.roll_with_modifier(|x| x + 2);
+    /// assert_eq!(result.value(), result.raw_value() + 2);
+    /// ```
+    ///
+    /// [`roll`]: #method.roll
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn roll_with_modifier<F: Fn(i64) -> i64>(&self, f: F) -> GurgleRoll<'_> {
+        self.roll().with_modifier(f)
+    }
+
+    #[cfg(feature = "std")]
+    fn rng(&self) -> rng::CommandRng {
+        rng::CommandRng::new(self.seed)
+    }
+
+    /// Rolling the compiled command with random source `rng` and get result, like a seeded `nanorand::WyRand`
+    ///
+    /// Seed in command is ignored, because `rng` is provided.
+    #[must_use]
+    pub fn roll_with_rng<R: RollRng + ?Sized>(&self, rng: &mut R) -> GurgleRoll<'_> {
+        GurgleRoll::new(
+            self.expr.roll_with_rng(rng),
+            &self.expr,
+            self.checker(),
+            self.tiered_checker(),
+        )
+    }
+}
+
+impl Gurgle {
+    fn write_command(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match (self.repeat, &self.expr) {
+            (None, expr) => f.write_fmt(format_args!("{}", expr))?,
+            (Some(n), AstTreeNode::Leaf(item @ (Item::Number(_) | Item::Dice(_)))) => {
+                f.write_fmt(format_args!("{}#{} each", n, item))?;
+            }
+            (Some(n), expr) => f.write_fmt(format_args!("{}#({}) each", n, expr))?,
+        }
+        match &self.checker {
+            Some(checker) if checker.needs_space() => f.write_fmt(format_args!(" {}", checker))?,
+            Some(checker) => f.write_fmt(format_args!("{}", checker))?,
+            None => {}
+        }
+        if let Some(tiers) = &self.tiers {
+            f.write_fmt(format_args!(" {}", tiers))?;
+        }
+        if let Some(seed) = self.seed {
+            f.write_fmt(format_args!(" @seed={}", seed))?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Gurgle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        if f.alternate() {
+            f.debug_struct("Gurgle")
+                .field("expr", &self.expr)
+                .field("checker", &self.checker)
+                .field("tiers", &self.tiers)
+                .field("seed", &self.seed)
+                .field("repeat", &self.repeat)
+                .field(
+                    "max_intermediate_magnitude",
+                    &self.max_intermediate_magnitude,
+                )
+                .field("max_distribution_steps", &self.max_distribution_steps)
+                .finish()
+        } else {
+            f.write_str("Gurgle(\"")?;
+            self.write_command(f)?;
+            f.write_str("\")")
+        }
+    }
+}
+
+impl Display for Gurgle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.write_command(f)
+    }
+}
+
+/// Compile then execute a gurgle command immediately, get result value
+///
+/// This function only gives you dice result value, but not check result.
+/// If you need success check, use [`Gurgle::roll`] instead.
+///
+/// ## Errors
+///
+/// If compile `s` as a gurgle command failed, see [`Gurgle::compile`].
+///
+/// [`Gurgle::roll`]: struct.Gurgle.html#method.roll
+/// [`Gurgle::compile`]: struct.Gurgle.html#method.compile
+#[cfg(feature = "std")]
+pub fn roll(s: &str) -> Result<i64, CompileError> {
+    Gurgle::compile(s).map(|x| x.roll().value())
+}
+
+/// Compile then execute many gurgle commands separated by `;` immediately, get result value of each one
+///
+/// ## Errors
+///
+/// If compile `s` as gurgle commands failed, see [`Gurgle::compile_many`].
+///
+/// [`Gurgle::compile_many`]: struct.Gurgle.html#method.compile_many
+#[cfg(feature = "std")]
+pub fn roll_many(s: &str) -> Result<Vec<i64>, CompileError> {
+    Gurgle::compile_many(s).map(|commands| commands.iter().map(|x| x.roll().value()).collect())
+}
+
+/// Compile `s` with `config`, roll it once, and get everything about the roll, see [`Evaluation`]
+///
+/// ## Errors
+///
+/// If compile `s` as a gurgle command failed, see [`Gurgle::compile_with_config`].
+///
+/// [`Evaluation`]: struct.Evaluation.html
+/// [`Gurgle::compile_with_config`]: struct.Gurgle.html#method.compile_with_config
+#[cfg(feature = "std")]
+pub fn evaluate(s: &str, config: &Config) -> Result<Evaluation, CompileError> {
+    let gurgle = Gurgle::compile_with_config(s, config)?;
+    let result = gurgle.roll();
+    Ok(Evaluation {
+        command: gurgle.to_string(),
+        value: result.value(),
+        success: result.success(),
+        tier: result.tier().map(str::to_owned),
+        #[cfg(feature = "detail")]
+        detail: Some(result.to_string()),
+        #[cfg(not(feature = "detail"))]
+        detail: None,
+    })
+}
+
+/// Get checker of every command in a batch, in the same order, `None` for commands without a checker
+///
+/// Useful for showing a summary table of targets of many commands.
+#[must_use]
+pub fn checkers(commands: &[Gurgle]) -> Vec<Option<&Checker>> {
+    commands.iter().map(Gurgle::checker).collect()
+}
+
+/// Compile then execute a gurgle command immediately, get detail output in language `spans`
+///
+/// Global language set by [`Language::set_global`] is not used nor changed.
+///
+/// ## Errors
+///
+/// If compile `s` as a gurgle command failed, see [`Gurgle::compile`].
+///
+/// [`Language::set_global`]: detail/enum.Language.html#method.set_global
+/// [`Gurgle::compile`]: struct.Gurgle.html#method.compile
+#[cfg(feature = "detail")]
+pub fn roll_detail_in(s: &str, spans: &detail::OutputSpans) -> Result<String, CompileError> {
+    let gurgle = Gurgle::compile(s)?;
+    let result = gurgle.roll();
+    Ok(result.display_with(spans).to_string())
+}
+
+// split `s` by `;` out of labels, with byte offset of every part
+fn split_commands(s: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut in_label = false;
+    let mut start = 0;
+    let mut ends = s
+        .char_indices()
+        .filter(move |(_, c)| {
+            match c {
+                '[' => in_label = true,
+                ']' => in_label = false,
+                _ => {}
+            }
+            *c == ';' && !in_label
+        })
+        .map(|(i, _)| i)
+        .chain(core::iter::once(s.len()));
+    core::iter::from_fn(move || {
+        let end = ends.next()?;
+        let part = (start, &s[start..end]);
+        start = end + 1;
+        Some(part)
+    })
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::rng::Sequence;
+
+    #[test]
+    fn test_parser_correct() {
+        assert!(Gurgle::compile("1d6+1").is_ok());
+        assert!(Gurgle::compile("3d6+2d10+1").is_ok());
+        assert!(Gurgle::compile("3d6max+2d10min+1").is_ok());
+        assert!(Gurgle::compile("3d6max+2d10min+1>=10").is_ok());
+        assert!(Gurgle::compile("3d6max+2d10min+1>=-10").is_ok());
+        assert!(Gurgle::compile("100d1000+-1").is_ok());
+        assert!(Gurgle::compile("100d1000*5").is_ok());
+        assert!(Gurgle::compile("10d1000x1d10").is_ok());
+        assert!(Gurgle::compile("(10d1000)+(1)").is_ok());
+        assert!(Gurgle::compile("3d6 + (2d4 + 1) * 2 + 1>20").is_ok());
+        assert!(Gurgle::compile("3d6+(2d4+1)*2+1 >20").is_ok());
+        assert!(Gurgle::compile("3d6+(2d4+1)*2+1> 20").is_ok());
+        assert!(Gurgle::compile("3d6+(2d4+1)*2+1 > 20").is_ok());
+        assert!(Gurgle::compile("(1d4)d6").is_ok());
+        assert!(Gurgle::compile("( 1d4 + 1 )d6max").is_ok());
+        assert!(Gurgle::compile("3d6!").is_ok());
+        assert!(Gurgle::compile("3d6!!max").is_ok());
+        assert!(Gurgle::compile("(1d4)d6!p+1").is_ok());
+    }
+
+    #[test]
+    fn test_parser_invalid() {
+        assert!(std::matches!(
+            Gurgle::compile("+").unwrap_err(),
+            CompileError::InvalidSyntax(_)
+        ));
+        assert!(std::matches!(
+            Gurgle::compile("good").unwrap_err(),
+            CompileError::InvalidSyntax(_)
+        ));
+        assert!(std::matches!(
+            Gurgle::compile("3d6+2p10+1").unwrap_err(),
+            CompileError::InvalidSyntax(_)
+        ));
+        assert!(std::matches!(
+            Gurgle::compile("3d6max+2d10min+1avg").unwrap_err(),
+            CompileError::InvalidSyntax(_)
+        ));
+        assert!(std::matches!(
+            Gurgle::compile("3d6+(1").unwrap_err(),
+            CompileError::InvalidSyntax(_),
+        ));
+        assert!(std::matches!(
+            Gurgle::compile("3d6 max+2d10min+1avg").unwrap_err(),
+            CompileError::InvalidSyntax(_)
+        ));
+        assert!(std::matches!(
+            Gurgle::compile("3d6+100000000000000000000000000").unwrap_err(),
+            CompileError::ParseNumberError(_),
+        ));
+    }
+
+    #[test]
+    fn test_compile_error() {
+        assert_eq!(
+            Gurgle::compile("10d-10").unwrap_err(),
+            CompileError::DiceRollOrSidedNegative,
+        );
+        assert_eq!(
+            Gurgle::compile("-10d10").unwrap_err(),
+            CompileError::DiceRollOrSidedNegative,
+        );
+        assert_eq!(
+            Gurgle::compile(
+                "3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+3d6+1"
+            )
+            .unwrap_err(),
+            CompileError::ItemCountLimitExceeded,
+        );
+        assert_eq!(
+            Gurgle::compile("10d1001").unwrap_err(),
+            CompileError::DiceSidedCountLimitExceeded,
+        );
+        assert_eq!(
+            Gurgle::compile("1001d10").unwrap_err(),
+            CompileError::DiceRollTimesLimitExceeded,
+        );
+        assert_eq!(
+            Gurgle::compile("1000d10+1d10").unwrap_err(),
+            CompileError::DiceRollTimesLimitExceeded,
+        );
+        assert_eq!(
+            Gurgle::compile("(1d4-1)d6").unwrap_err(),
+            CompileError::DiceRollOrSidedNegative,
+        );
+        assert_eq!(
+            Gurgle::compile("(1d101)d6").unwrap_err(),
+            CompileError::DiceRollTimesLimitExceeded,
+        );
+        assert_eq!(
+            Gurgle::compile("65537").unwrap_err(),
+            CompileError::NumberItemOutOfRange,
+        );
+        assert_eq!(
+            Gurgle::compile("-65537").unwrap_err(),
+            CompileError::NumberItemOutOfRange,
+        );
+    }
+
+    #[test]
+    fn test_roll() {
+        // detail::Language::set_global(detail::Language::ZhCN);
+        // detail::Language::set_global_custom(detail::OutputSpans {
+        //     comma: "| ".into(),
+        //     target_is: "we want".into(),
+        //     success: "passed".into(),
+        //     failed: "over".into(),
+        // });
+        let attack = Gurgle::compile("3d6min+3d6avg+3d6max+3d6+(2d4+1)*2+1>15").unwrap();
+        let result = attack.roll();
+
+        #[cfg(feature = "detail")]
+        println!("attack rolling result is: {}", result);
+
+        println!("attack = {}", result.value());
+        assert!(result.value() >= 13);
+        assert_eq!(result.success().unwrap(), result.value() > 15);
+    }
+
+    #[test]
+    fn test_chained_dice() {
+        use core::convert::TryFrom;
+
+        let dice = Gurgle::compile("(1d4)d6").unwrap();
+        for _ in 0..100 {
+            let result = dice.roll();
+            let dice_roll = result.expr().as_leaf().unwrap().as_dice().unwrap();
+            let times = dice_roll.times_roll().unwrap().value();
+            assert!((1..=4).contains(&times));
+            assert_eq!(dice_roll.len(), usize::try_from(times).unwrap());
+            assert!(dice_roll.points().iter().all(|p| (1..=6).contains(p)));
+        }
+    }
+
+    #[test]
+    fn test_item_count() {
+        assert_eq!(Gurgle::compile("3d6+2+1d4").unwrap().item_count(), 3);
+        assert_eq!(Gurgle::compile("3d6+(2+1d4)*2").unwrap().item_count(), 4);
+        assert_eq!(Gurgle::compile("(1d4+1)d6").unwrap().item_count(), 3);
+    }
+
+    #[test]
+    fn test_categorize() {
+        let bands = [(1..=2, "leg"), (3..=4, "arm"), (6..=6, "head")];
+        let location = Gurgle::compile("1d6").unwrap();
+        for _ in 0..100 {
+            let result = location.roll();
+            let expected = match result.value() {
+                1 | 2 => Some("leg"),
+                3 | 4 => Some("arm"),
+                6 => Some("head"),
+                _ => None,
+            };
+            assert_eq!(result.categorize(&bands), expected);
+        }
+        assert_eq!(
+            Gurgle::compile("5").unwrap().roll().categorize(&bands),
+            None
+        );
+    }
+
+    #[test]
+    fn test_config_hash_eq() {
+        use std::{
+            collections::hash_map::DefaultHasher,
+            hash::{Hash, Hasher},
+        };
+
+        fn assert_cache_key<K: Hash + Eq>() {}
+        assert_cache_key::<Config>();
+        assert_cache_key::<(String, Config)>();
+
+        let hash = |c: &Config| {
+            let mut hasher = DefaultHasher::new();
+            c.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        let a = Config::default().max_roll_times(200);
+        let b = Config::default().max_roll_times(200);
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert_ne!(a, Config::default());
+    }
+
+    #[test]
+    fn test_max_dice_generations() {
+        assert_eq!(Gurgle::compile("1").unwrap().max_dice_generations(), 0);
+        assert_eq!(
+            Gurgle::compile("3d6+2d4max")
+                .unwrap()
+                .max_dice_generations(),
+            5
+        );
+        assert_eq!(
+            Gurgle::compile("(2d6)*3+(1d4)d6")
+                .unwrap()
+                .max_dice_generations(),
+            7
+        );
+        let config = Config::default().max_explode_depth(2);
+        assert_eq!(
+            Gurgle::compile_with_config("3d6!", &config)
+                .unwrap()
+                .max_dice_generations(),
+            9
+        );
+    }
+
+    #[test]
+    fn test_single_item_parentheses() {
+        assert_eq!(
+            Gurgle::compile("(3d6)").unwrap(),
+            Gurgle::compile("3d6").unwrap()
+        );
+        assert_eq!(
+            Gurgle::compile("((1))+(2d4max)*2").unwrap(),
+            Gurgle::compile("1+2d4max*2").unwrap()
+        );
+        assert_ne!(
+            Gurgle::compile("(1+2)*3").unwrap(),
+            Gurgle::compile("1+2*3").unwrap()
+        );
+        assert!(Gurgle::compile("(3d6)")
+            .unwrap()
+            .expr()
+            .as_leaf()
+            .unwrap()
+            .is_dice());
+    }
+
+    #[test]
+    fn test_mode() {
+        assert_eq!(Gurgle::compile("2d6").unwrap().mode(), Some(7));
+        assert_eq!(Gurgle::compile("1d6").unwrap().mode(), Some(1));
+        assert_eq!(Gurgle::compile("3d6").unwrap().mode(), Some(10));
+        assert_eq!(Gurgle::compile("2d6max").unwrap().mode(), Some(6));
+        assert_eq!(Gurgle::compile("2d6min+1").unwrap().mode(), Some(2));
+        assert_eq!(Gurgle::compile("5").unwrap().mode(), Some(5));
+        let config = Config::default().max_roll_times(200);
+        let huge = Gurgle::compile_with_config("100d1000*100d1000", &config).unwrap();
+        assert_eq!(huge.mode(), None);
+    }
+
+    #[test]
+    fn test_tiered_checker() {
+        let attack = Gurgle::compile("1d20+5 : 10=hit, 20=crit").unwrap();
+        for _ in 0..100 {
+            let result = attack.roll();
+            let expected = match result.value() {
+                x if x >= 20 => Some("crit"),
+                x if x >= 10 => Some("hit"),
+                _ => None,
+            };
+            assert_eq!(result.tier(), expected);
+            assert_eq!(result.success(), None);
+        }
+
+        let middle = Gurgle::compile("12 >= 10 : 20=crit,10=hit,1=miss").unwrap();
+        assert_eq!(middle.roll().tier(), Some("hit"));
+        assert_eq!(middle.roll().success(), Some(true));
+        assert_eq!(middle.tiered_checker().unwrap().tiers()[0].label, "miss");
+
+        assert_eq!(
+            Gurgle::compile("1d20 : 10=hit, 10=crit").unwrap_err(),
+            CompileError::DuplicateTierThreshold,
+        );
+        assert!(std::matches!(
+            Gurgle::compile("1d20 :").unwrap_err(),
+            CompileError::InvalidSyntax(_),
+        ));
+    }
+
+    #[test]
+    fn test_debug_output() {
+        let attack = Gurgle::compile("3d6max + (2d4+1) * 2 + -1 > 10").unwrap();
+        assert_eq!(
+            format!("{:?}", attack),
+            "Gurgle(\"3d6max+(2d4+1)*2+-1>10\")"
+        );
+        assert!(format!("{:#?}", attack).contains("checker"));
+
+        let exploding = Gurgle::compile("3d6! + 2d6!!max + 1d6!p").unwrap();
+        assert_eq!(
+            format!("{:?}", exploding),
+            "Gurgle(\"3d6!+2d6!!max+1d6!p\")"
+        );
+
+        let tiered = Gurgle::compile("(1d4)d20 : 20=crit, 10=hit").unwrap();
+        assert_eq!(
+            format!("{:?}", tiered),
+            "Gurgle(\"(1d4)d20 : 10=hit, 20=crit\")"
+        );
+    }
+
+    #[test]
+    fn test_command_seed() {
+        let seeded = Gurgle::compile("100d1000 @seed=1").unwrap();
+        assert_eq!(seeded.seed(), Some(1));
+        let first = seeded.roll();
+        for _ in 0..10 {
+            let again = seeded.roll();
+            assert_eq!(again.value(), first.value());
+            assert_eq!(
+                again.expr().as_leaf().unwrap().as_dice().unwrap().points(),
+                first.expr().as_leaf().unwrap().as_dice().unwrap().points(),
+            );
+        }
+
+        let other = Gurgle::compile("100d1000 @seed=2").unwrap();
+        assert_ne!(other.roll().value(), first.value());
+
+        let full = Gurgle::compile("3d6 > 10 : 15=great @ seed = 42").unwrap();
+        assert_eq!(
+            format!("{:?}", full),
+            "Gurgle(\"3d6>10 : 15=great @seed=42\")"
+        );
+        assert_eq!(Gurgle::compile("3d6").unwrap().seed(), None);
+        assert!(std::matches!(
+            Gurgle::compile("3d6 @seed=-1").unwrap_err(),
+            CompileError::InvalidSyntax(_),
+        ));
+    }
+
+    #[test]
+    fn test_hits_and_glitch() {
+        use crate::{expr::PostProcessor, roll::DiceRoll};
+
+        let normal = DiceRoll::new(vec![1, 5, 3, 6], 6, PostProcessor::Sum, None);
+        assert_eq!(normal.hits(5), 2);
+        assert!(!normal.is_glitch());
+
+        let glitch = DiceRoll::new(vec![1, 1, 6, 3], 6, PostProcessor::Sum, None);
+        assert_eq!(glitch.hits(5), 1);
+        assert!(glitch.is_glitch());
+        assert!(!glitch.is_critical_glitch(5));
+
+        let critical = DiceRoll::new(vec![1, 1, 4, 3], 6, PostProcessor::Sum, None);
+        assert_eq!(critical.hits(5), 0);
+        assert!(critical.is_glitch());
+        assert!(critical.is_critical_glitch(5));
+
+        let fine = DiceRoll::new(vec![1, 2, 4, 3], 6, PostProcessor::Sum, None);
+        assert!(!fine.is_glitch());
+        assert!(!fine.is_critical_glitch(5));
+        // glitch is only an outcome of success counting pools
+        assert_eq!(critical.glitch(), None);
+
+        let pool = Gurgle::compile("4d6f5 >= 1").unwrap();
+        let roll = |points: Vec<u64>| pool.roll_with_rng(&mut Sequence(points.into_iter()));
+        let glitch = roll(vec![1, 1, 6, 3]);
+        assert!(glitch.success().unwrap());
+        assert_eq!(glitch.glitch(), Some(Glitch::Glitch));
+        let critical = roll(vec![1, 1, 4, 3]);
+        assert!(!critical.success().unwrap());
+        assert_eq!(critical.glitch(), Some(Glitch::Critical));
+        assert_eq!(roll(vec![1, 2, 5, 6]).glitch(), None);
+        let both = Gurgle::compile("2d6f5 + 2d6f5").unwrap();
+        let result = both.roll_with_rng(&mut Sequence(vec![1, 5, 1, 1].into_iter()));
+        assert_eq!(result.glitch(), Some(Glitch::Critical));
+    }
+
+    #[test]
+    fn test_symmetric_with() {
+        let d20 = Gurgle::compile("1d20").unwrap();
+        assert!(d20.symmetric_with(&Gurgle::compile("1d20").unwrap()));
+        assert!(d20.symmetric_with(&Gurgle::compile("21-1d20").unwrap()));
+        assert!(!d20.symmetric_with(&Gurgle::compile("1d20+1").unwrap()));
+        assert!(!d20.symmetric_with(&Gurgle::compile("2d10").unwrap()));
+
+        let tie = d20.tie_probability(&d20).unwrap();
+        assert!((tie - 0.05).abs() < 1e-9);
+        let tie = d20
+            .tie_probability(&Gurgle::compile("1d20+20").unwrap())
+            .unwrap();
+        assert!(tie.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_config_scale() {
+        let default = Config::default();
+
+        let premium = default.clone().scale_up(2);
+        assert_eq!(premium.max_roll_times, default.max_roll_times * 2);
+        assert_eq!(premium.max_item_count, default.max_item_count * 2);
+        assert_eq!(premium, default.clone().scale(2.0));
+
+        let basic = default.clone().scale(0.5);
+        assert_eq!(basic.max_roll_times, default.max_roll_times / 2);
+        assert_eq!(basic.max_dice_sides, default.max_dice_sides / 2);
+
+        assert_eq!(default.clone().scale_up(0).max_roll_times, 1);
+        assert_eq!(default.clone().scale(0.0).max_item_count, 1);
+        assert_eq!(default.clone().scale(f64::NAN).max_item_count, 1);
+        assert_eq!(default.clone().scale_up(u64::MAX).max_roll_times, u64::MAX);
+        assert_eq!(default.scale(f64::INFINITY).max_roll_times, u64::MAX);
+    }
+
+    #[test]
+    fn test_round_observer() {
+        let mut rounds = Vec::new();
+        let gurgle = Gurgle::compile("3d6+2d4").unwrap();
+        let result = gurgle
+            .roll_with_round_observer(|roll| rounds.push((roll.points().len(), roll.value())));
+        assert_eq!(rounds.len(), 2);
+        assert_eq!(rounds[0].0, 3);
+        assert_eq!(rounds[1].0, 2);
+        assert_eq!(rounds[0].1 + rounds[1].1, result.value());
+
+        let mut count = 0;
+        let _ = Gurgle::compile("(1d4)d6+1")
+            .unwrap()
+            .roll_with_round_observer(|_| count += 1);
+        assert_eq!(count, 2);
+
+        let seeded = Gurgle::compile("3d6 @seed=42").unwrap();
+        assert_eq!(
+            seeded.roll_with_round_observer(|_| {}).value(),
+            seeded.roll().value()
+        );
+    }
+
+    #[test]
+    fn test_cumulative() {
+        let d6 = Gurgle::compile("1d6").unwrap().cumulative().unwrap();
+        assert_eq!(d6.len(), 6);
+        assert!((d6[&1] - 1.0).abs() < 1e-9);
+        assert!((d6[&2] - 5.0 / 6.0).abs() < 1e-9);
+        assert!((d6[&6] - 1.0 / 6.0).abs() < 1e-9);
+
+        let two_d6 = Gurgle::compile("2d6").unwrap().cumulative().unwrap();
+        assert!((two_d6[&2] - 1.0).abs() < 1e-9);
+        assert!((two_d6[&12] - 1.0 / 36.0).abs() < 1e-9);
+        assert!(two_d6
+            .values()
+            .zip(two_d6.values().skip(1))
+            .all(|(a, b)| a >= b));
+    }
+
+    #[test]
+    fn test_apply_advantage_bounds() {
+        // new points are in bounds of the command, an extra die of penetrate explosion is minus 1 too
+        for command in &["1d1!p", "2d6!p", "3d4!pmi2", "1d6!!ma4"] {
+            let gurgle = Gurgle::compile(command).unwrap();
+            for _ in 0..100 {
+                let roll = gurgle.roll();
+                let adv = roll.apply_advantage_to(0).unwrap();
+                assert!(adv.value() >= roll.value());
+                assert!(adv.value() <= gurgle.max_result(), "{}", command);
+                assert!(adv.value() >= gurgle.min_result(), "{}", command);
+                let (before, after) = (
+                    roll.dice_rolls().next().unwrap(),
+                    adv.dice_rolls().next().unwrap(),
+                );
+                assert_eq!(before.exploded(), after.exploded());
+            }
+        }
+
+        // new points are clamped like the first roll
+        let capped = Gurgle::compile("1d6ma3").unwrap();
+        for _ in 0..100 {
+            let adv = capped.roll().apply_advantage_to(0).unwrap();
+            let dice = adv.dice_rolls().next().unwrap();
+            assert!(dice.points().iter().all(|p| *p <= 3));
+            for (point, original) in dice.points().iter().zip(dice.clamped()) {
+                if let Some(original) = original {
+                    assert!(*original > 3 && *point == 3);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_apply_advantage() {
+        let gurgle = Gurgle::compile("1d4+1d20+3").unwrap();
+        for _ in 0..100 {
+            let roll = gurgle.roll();
+            let adv = roll.apply_advantage_to(1).unwrap();
+            assert!(adv.value() >= roll.value());
+
+            let get_dice = |r: &GurgleRoll<'_>, left: bool| {
+                let tree = r.expr().as_tree().unwrap().left.as_tree().unwrap();
+                let node = if left { &tree.left } else { &tree.right };
+                node.as_leaf().unwrap().as_dice().unwrap().points()[0]
+            };
+            assert_eq!(get_dice(&adv, true), get_dice(&roll, true));
+            assert!(get_dice(&adv, false) >= get_dice(&roll, false));
+        }
+        assert!(gurgle.roll().apply_advantage_to(2).is_none());
+
+        let chained = Gurgle::compile("(1d4)d6min").unwrap();
+        let roll = chained.roll();
+        let adv = roll.apply_advantage_to(0).unwrap();
+        assert!(adv.value() >= roll.value());
+        assert!(roll.apply_advantage_to(1).is_none());
+
+        // seeded command gives the same advantage result every time, and not just the same points again
+        let seeded = Gurgle::compile("10d20 @seed=7").unwrap();
+        let points = |r: &GurgleRoll<'_>| r.dice_rolls().next().unwrap().points().to_vec();
+        let adv = seeded.roll().apply_advantage_to(0).unwrap();
+        assert_eq!(
+            points(&adv),
+            points(&seeded.roll().apply_advantage_to(0).unwrap())
+        );
+        assert_ne!(points(&adv), points(&seeded.roll()));
+        let twice = adv.apply_advantage_to(0).unwrap();
+        assert_eq!(
+            points(&twice),
+            points(
+                &seeded
+                    .roll()
+                    .apply_advantage_to(0)
+                    .unwrap()
+                    .apply_advantage_to(0)
+                    .unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_config_diff() {
+        let default = Config::default();
+        assert!(default.diff(&Config::default()).is_empty());
+
+        let doubled = default.clone().max_roll_times(default.max_roll_times * 2);
+        assert_eq!(
+            default.diff(&doubled),
+            vec![ConfigFieldDiff {
+                field: "max_roll_times",
+                left: ConfigFieldValue::Limit(100),
+                right: ConfigFieldValue::Limit(200),
+            }],
+        );
+
+        let diff = default.diff(&default.clone().max_item_count(1).max_dice_sides(2));
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff[0].field, "max_item_count");
+        assert_eq!(diff[1].field, "max_dice_sides");
+    }
+
+    #[cfg(feature = "detail")]
+    #[test]
+    fn test_roll_detail_in() {
+        use crate::detail::OutputSpans;
+
+        let command = "3d6+1>10 @seed=42";
+        let en = roll_detail_in(command, &OutputSpans::new_en()).unwrap();
+        let zh = roll_detail_in(command, &OutputSpans::new_zh_cn()).unwrap();
+        assert_ne!(en, zh);
+
+        let result = if en.ends_with("success") {
+            ("success", "通过")
+        } else {
+            ("failed", "失败")
+        };
+        let localized = en
+            .replace(", ", "，")
+            .replace("target is", "目标为")
+            .replace(result.0, result.1);
+        assert_eq!(localized, zh);
+
+        assert_eq!(
+            roll_detail_in("3d", &OutputSpans::new_en()).unwrap_err(),
+            Gurgle::compile("3d").unwrap_err()
+        );
+    }
+
+    #[cfg(feature = "detail")]
+    #[test]
+    fn test_roll_steps() {
+        let gurgle = Gurgle::compile("2d6+1>5 : 8=high").unwrap();
+        let roll = gurgle.roll();
+        let dice = roll.expr().as_tree().unwrap().left.as_leaf().unwrap();
+        let dice = dice.as_dice().unwrap();
+        let value = roll.value();
+
+        let mut expected = vec![
+            format!(
+                "2d6: rolled {},{} = {}",
+                dice.points()[0],
+                dice.points()[1],
+                dice.value()
+            ),
+            format!("{} + 1 = {}", dice.value(), value),
+            format!(
+                "{} > 5: {}",
+                value,
+                if value > 5 { "success" } else { "failed" }
+            ),
+        ];
+        if value >= 8 {
+            expected.push(format!("{}: high", value));
+        }
+        assert_eq!(roll.steps(), expected);
+
+        let chained = Gurgle::compile("(1d1+1)d1max").unwrap().roll().steps();
+        assert_eq!(
+            chained,
+            vec!["1d1: rolled 1 = 1", "1 + 1 = 2", "2d1max: rolled 1,1 = 1"]
+        );
+    }
+
+    #[test]
+    fn test_explode() {
+        let config = Config::default().max_explode_depth(3);
+        let always = Gurgle::compile_with_config("2d1!", &config).unwrap();
+        let roll = always.roll();
+        assert_eq!(roll.value(), 8);
+        let dice = roll.expr().as_leaf().unwrap().as_dice().unwrap();
+        assert_eq!(dice.rolled_times(), 2);
+        assert_eq!(
+            dice.exploded(),
+            &[true, true, true, false, true, true, true, false]
+        );
+
+        let compound = Gurgle::compile_with_config("2d1!!", &config).unwrap();
+        let compound = compound.roll();
+        let dice = compound.expr().as_leaf().unwrap().as_dice().unwrap();
+        assert_eq!(dice.points(), &[4, 4]);
+        assert_eq!(dice.exploded(), &[true, true]);
+
+        let penetrate = Gurgle::compile_with_config("2d1!p", &config).unwrap();
+        assert_eq!(penetrate.roll().value(), 2);
+
+        let d6 = Gurgle::compile("1d6!").unwrap();
+        for _ in 0..100 {
+            let value = d6.roll().value();
+            assert!((1..=66).contains(&value) && value % 6 != 0);
+        }
+
+        let cumulative = d6.cumulative().unwrap();
+        assert!((cumulative[&7] - 1.0 / 6.0).abs() < 1e-9);
+        assert!(!cumulative.contains_key(&6));
+        assert_eq!(Gurgle::compile("2d6!!max").unwrap().mode(), Some(5));
+        assert_eq!(Gurgle::compile("2d6!min").unwrap().mode(), None);
+    }
+
+    #[test]
+    fn test_reroll() {
+        let points = || Sequence(vec![1, 5, 3, 6, 1, 1, 2].into_iter());
+
+        let always = Gurgle::compile("4d6r1").unwrap();
+        let result = always.roll_with_rng(&mut points());
+        let dice = result.expr().as_leaf().unwrap().as_dice().unwrap();
+        assert_eq!(dice.points(), &[5, 3, 6, 2]);
+        assert_eq!(dice.rerolled(), &[vec![1], vec![], vec![], vec![1, 1]]);
+        assert_eq!(result.value(), 16);
+        #[cfg(feature = "detail")]
+        assert_eq!(dice.to_string(), "(1→5+3+6+1→1→2)");
+
+        let once = Gurgle::compile("4d6ro1").unwrap();
+        let result = once.roll_with_rng(&mut points());
+        let dice = result.expr().as_leaf().unwrap().as_dice().unwrap();
+        assert_eq!(dice.points(), &[5, 3, 6, 1]);
+        assert_eq!(dice.rerolled(), &[vec![1], vec![], vec![], vec![1]]);
+
+        let exploding = Gurgle::compile("2d6r1!").unwrap();
+        let result = exploding.roll_with_rng(&mut Sequence(vec![1, 6, 2, 3].into_iter()));
+        let dice = result.expr().as_leaf().unwrap().as_dice().unwrap();
+        assert_eq!(dice.points(), &[6, 2, 3]);
+        assert_eq!(dice.rerolled(), &[vec![1], vec![], vec![]]);
+
+        let config = Config::default().max_explode_depth(3);
+        let d1 = Gurgle::compile_with_config("1d1r1", &config).unwrap();
+        let result = d1.roll();
+        let dice = result.expr().as_leaf().unwrap().as_dice().unwrap();
+        assert_eq!(dice.points(), &[1]);
+        assert_eq!(dice.rerolled(), &[vec![1, 1, 1]]);
+        assert_eq!(d1.max_dice_generations(), 4);
+        assert_eq!(Gurgle::compile("2d6ro1").unwrap().max_dice_generations(), 4);
+
+        for command in &["4d6r1r2", "4d6ro1!kh3", "(1d4)d6r6max"] {
+            let gurgle = Gurgle::compile(command).unwrap();
+            assert_eq!(format!("{:?}", gurgle), format!("Gurgle(\"{}\")", command));
+        }
+        assert!(Gurgle::compile("4d6r1ro2").is_err());
+        assert!(Gurgle::compile("4d6r").is_err());
+        for command in &["4d6r7", "4d6r0", "4d6r1r7", "(1d4)d%r101"] {
+            assert_eq!(
+                Gurgle::compile(command).unwrap_err(),
+                CompileError::RerollOutOfRange,
+                "{}",
+                command
+            );
+        }
+        assert!(Gurgle::compile("4d6r6").is_ok());
+
+        let variance = Gurgle::compile("1d2ro1").unwrap().variance().unwrap();
+        assert!((variance - 0.1875).abs() < 1e-9);
+        assert_eq!(Gurgle::compile("1d6r1").unwrap().mode(), Some(2));
+        assert_eq!(Gurgle::compile("1d6r1!").unwrap().mode(), None);
+    }
+
+    #[test]
+    fn test_explode_d1_not_hang() {
+        let depth = Config::default().max_explode_depth;
+        #[allow(clippy::cast_possible_wrap)] // because default depth is small
+        let per_die = depth as i64 + 1;
+        for (command, value) in &[
+            ("1d1!", per_die),
+            ("1d1!!", per_die),
+            ("1d1!p", 1),
+            ("100d1!", 100 * per_die),
+        ] {
+            let gurgle = Gurgle::compile(command).unwrap();
+            assert_eq!(gurgle.roll().value(), *value);
+        }
+
+        let gurgle = Gurgle::compile("3d1!").unwrap();
+        let result = gurgle.roll();
+        let dice = result.expr().as_leaf().unwrap().as_dice().unwrap();
+        assert_eq!(dice.rolled_times(), 3);
+        assert_eq!(dice.len() as u64, 3 * (depth + 1));
+        #[cfg(feature = "detail")]
+        assert!(result.to_string().contains("1!+1!"));
+    }
+
+    #[test]
+    fn test_roll_checked() {
+        use crate::checker::Compare;
+
+        let gurgle = Gurgle::compile("1d20+5 > 100 @seed=7").unwrap();
+        let easy = Checker::Compare {
+            compare: Compare::Gte,
+            target: 6,
+            tolerance: 0,
+        };
+        let hard = Checker::Compare {
+            compare: Compare::Gt,
+            target: 25,
+            tolerance: 0,
+        };
+
+        let compiled = gurgle.roll();
+        let with_easy = gurgle.roll_checked(&easy);
+        let with_hard = gurgle.roll_checked(&hard);
+        assert_eq!(with_easy.value(), compiled.value());
+        assert_eq!(with_hard.value(), compiled.value());
+        assert_eq!(compiled.success(), Some(false));
+        assert_eq!(with_easy.success(), Some(true));
+        assert_eq!(with_hard.success(), Some(false));
+        assert_eq!(with_easy.checker(), Some(&easy));
+    }
+
+    #[test]
+    fn test_variance() {
+        let variance = |s: &str| Gurgle::compile(s).unwrap().variance().unwrap();
+        assert!((variance("1d6") - 35.0 / 12.0).abs() < 1e-9);
+        assert!((variance("2d6") - 70.0 / 12.0).abs() < 1e-9);
+        assert!((variance("2d6-1d6+3") - 105.0 / 12.0).abs() < 1e-9);
+        assert!((variance("1d6*2") - 35.0 / 3.0).abs() < 1e-9);
+        assert!(variance("10").abs() < 1e-9);
+        assert!((variance("(1d4)d6") - 1085.0 / 48.0).abs() < 1e-9);
+        assert!((variance("2d2max") - 3.0 / 16.0).abs() < 1e-9);
+
+        let std_dev = Gurgle::compile("1d6").unwrap().std_dev().unwrap();
+        assert!((std_dev - (35.0_f64 / 12.0).sqrt()).abs() < 1e-9);
+        assert_eq!(Gurgle::compile("2d6!avg").unwrap().variance(), None);
+    }
+
+    #[test]
+    fn test_checker_tolerance() {
+        let gurgle = Gurgle::compile("3d6 =~1 10").unwrap();
+        let checker = gurgle.checker().unwrap();
+        assert!(std::matches!(
+            checker,
+            Checker::Compare { tolerance: 1, .. }
+        ));
+        for (result, success) in &[(8, false), (9, true), (10, true), (11, true), (12, false)] {
+            assert_eq!(checker.check(*result), *success);
+        }
+        assert_eq!(format!("{:?}", gurgle), "Gurgle(\"3d6=~1 10\")");
+
+        assert!(std::matches!(
+            Gurgle::compile("3d6=10").unwrap().checker(),
+            Some(Checker::Compare { tolerance: 0, .. })
+        ));
+        assert!(std::matches!(
+            Gurgle::compile("3d6 >=~1 10").unwrap_err(),
+            CompileError::InvalidSyntax(_)
+        ));
+    }
+
+    #[test]
+    fn test_roll_many_values() {
+        let gurgle = Gurgle::compile("3d6+(1d4)d6-2").unwrap();
+        let (min, max) = gurgle.expr().bounds();
+        let values = gurgle.roll_many_values(1000);
+        assert_eq!(values.len(), 1000);
+        assert!(values.iter().all(|v| (min..=max).contains(v)));
+        assert!(gurgle.roll_many_values(0).is_empty());
+
+        let seeded = Gurgle::compile("100d1000 @seed=3").unwrap();
+        let values = seeded.roll_many_values(10);
+        assert_eq!(values, seeded.roll_many_values(10));
+        assert_eq!(values[0], seeded.roll().value());
+        assert_ne!(values[0], values[1]);
+    }
+
+    #[test]
+    fn test_parity_checker() {
+        let even = Gurgle::compile("1d6 even").unwrap();
+        let checker = even.checker().unwrap();
+        for result in 1..=6 {
+            assert_eq!(checker.check(result), result % 2 == 0);
+        }
+        assert!(checker.check(0));
+        assert!(checker.check(-2));
+        assert_eq!(format!("{:?}", even), "Gurgle(\"1d6 even\")");
+        #[cfg(feature = "detail")]
+        assert!(even.roll().to_string().contains("target is even"));
+
+        let odd = Gurgle::compile("1d6-4 odd").unwrap();
+        let checker = odd.checker().unwrap();
+        assert!(checker.check(-3));
+        assert!(!checker.check(-2));
+        for _ in 0..10 {
+            let result = odd.roll();
+            assert_eq!(result.success(), Some(result.value() % 2 != 0));
+        }
+    }
+
+    #[test]
+    fn test_try_roll() {
+        let config = Config::default().max_intermediate_magnitude(1_000_000);
+        let huge = Gurgle::compile_with_config("1000*1000*1000-1000*1000*1000+1", &config).unwrap();
+        assert_eq!(
+            huge.try_roll().unwrap_err(),
+            RollError::IntermediateMagnitudeLimitExceeded
+        );
+        assert_eq!(huge.roll().value(), 1);
+
+        let edge = Gurgle::compile_with_config("1000*1000-(999*1000)", &config).unwrap();
+        assert_eq!(edge.try_roll().unwrap().value(), 1000);
+
+        let dice = Gurgle::compile_with_config("2d6*1000", &config).unwrap();
+        assert!((2000..=12000).contains(&dice.try_roll().unwrap().value()));
+
+        let overflow = Gurgle::compile("65536*65536*65536*65536-1").unwrap();
+        assert_eq!(
+            overflow.try_roll().unwrap_err(),
+            RollError::IntermediateMagnitudeLimitExceeded
+        );
+
+        let repeat = Gurgle::compile("2#(1/(1d1-1))").unwrap();
+        assert_eq!(repeat.try_roll().unwrap_err(), RollError::DivisionByZero);
+        let times = Gurgle::compile("(1/(1d1-1)+1)d6").unwrap();
+        assert_eq!(times.try_roll().unwrap_err(), RollError::DivisionByZero);
+        let repeat = Gurgle::compile("2#(65536*65536*65536*65536)").unwrap();
+        assert_eq!(
+            repeat.try_roll().unwrap_err(),
+            RollError::IntermediateMagnitudeLimitExceeded
+        );
+        assert_eq!(
+            repeat.roll().try_value(),
+            Err(RollError::IntermediateMagnitudeLimitExceeded)
+        );
+        let sum = Gurgle::compile_with_config("2#(1000*1000)", &config).unwrap();
+        assert_eq!(
+            sum.try_roll().unwrap_err(),
+            RollError::IntermediateMagnitudeLimitExceeded
+        );
+    }
+
+    #[test]
+    fn test_consumed_rng() {
+        let constant = Gurgle::compile("5").unwrap();
+        assert!(!constant.roll().consumed_rng());
+        let constant = Gurgle::compile("(1+2)*3>5").unwrap();
+        assert!(!constant.roll().consumed_rng());
+
+        let dice = Gurgle::compile("1d6").unwrap();
+        assert!(dice.roll().consumed_rng());
+        let dice = Gurgle::compile("1+(2*1d6)").unwrap();
+        assert!(dice.roll().consumed_rng());
+    }
+
+    #[test]
+    fn test_roll_array() {
+        let ability = Gurgle::compile("4d6max+2d6>=10").unwrap();
+        let array = ability.roll_array(6);
+        assert_eq!(array.len(), 6);
+        for result in &array {
+            assert!((3..=18).contains(&result.value()));
+            assert_eq!(result.success().unwrap(), result.value() >= 10);
+        }
+        assert!(ability.roll_array(0).is_empty());
+
+        let seeded = Gurgle::compile("4d6max+2d6 @seed=42").unwrap();
+        let values =
+            |array: Vec<GurgleRoll<'_>>| array.iter().map(GurgleRoll::value).collect::<Vec<_>>();
+        assert_eq!(values(seeded.roll_array(6)), seeded.roll_many_values(6));
+
+        let stats = Gurgle::compile("6#4d6kh3").unwrap();
+        let result = stats.roll();
+        let roll::RollTreeNode::Leaf(roll::ItemRoll::Repeat(repeat)) = result.expr() else {
+            panic!("not a repeat: {:?}", result.expr());
+        };
+        assert_eq!(repeat.rolls().len(), 6);
+        for roll in repeat.rolls() {
+            assert!((3..=18).contains(&roll.value()));
+        }
+        for result in Gurgle::compile("4d6kh3").unwrap().roll_array(6) {
+            assert!((3..=18).contains(&result.value()));
+        }
+    }
+
+    #[test]
+    fn test_dice_sizes() {
+        let d6 = Gurgle::compile("3d6+1").unwrap();
+        assert!(d6.only_uses_sizes(&[6]));
+        assert!(d6.contains_dice_size(6));
+        assert!(!d6.contains_dice_size(4));
+
+        let mixed = Gurgle::compile("3d6+1d4").unwrap();
+        assert!(!mixed.only_uses_sizes(&[6]));
+        assert!(mixed.only_uses_sizes(&[4, 6]));
+        assert!(mixed.contains_dice_size(4));
+
+        let chained = Gurgle::compile("(1d4)d6*(2+1d8)").unwrap();
+        assert!(chained.contains_dice_size(4));
+        assert!(chained.contains_dice_size(8));
+        assert!(!chained.only_uses_sizes(&[6, 8]));
+
+        let fudge = Gurgle::compile("4dF+1d3").unwrap();
+        assert!(fudge.contains_dice_size(3));
+        assert!(fudge.only_uses_sizes(&[3]));
+        let fudge = Gurgle::compile("4dF+1d6").unwrap();
+        assert!(!fudge.contains_dice_size(3));
+        assert!(fudge.only_uses_sizes(&[6]));
+
+        let constant = Gurgle::compile("1+2").unwrap();
+        assert!(constant.only_uses_sizes(&[6]));
+        assert!(!constant.contains_dice_size(6));
+    }
+
+    #[test]
+    fn test_roll_with_rng() {
+        use nanorand::WyRand;
+
+        let gurgle = Gurgle::compile("3d6+(1d4)d8max>10").unwrap();
+        let a = gurgle.roll_with_rng(&mut WyRand::new_seed(42));
+        let b = gurgle.roll_with_rng(&mut WyRand::new_seed(42));
+        assert_eq!(a.value(), b.value());
+        assert_eq!(a.success(), b.success());
+
+        let seeded = Gurgle::compile("3d6+(1d4)d8max @seed=42").unwrap();
+        assert_eq!(seeded.roll().value(), a.value());
+
+        let dice = Dice::new(10, 6);
+        let points = |rng: &mut WyRand| dice.roll_with_rng(rng).points().to_vec();
+        assert_eq!(
+            points(&mut WyRand::new_seed(7)),
+            points(&mut WyRand::new_seed(7))
+        );
+
+        let mut rng = WyRand::new_seed(7);
+        let item = gurgle.expr().roll_with_rng(&mut rng).value();
+        assert_eq!(
+            item,
+            gurgle
+                .expr()
+                .roll_with_rng(&mut WyRand::new_seed(7))
+                .value()
+        );
+    }
+
+    #[cfg(feature = "detail")]
+    #[test]
+    fn test_styled_spans() {
+        use crate::detail::SpanKind;
+
+        let gurgle = Gurgle::compile("2d6>5").unwrap();
+        let result = gurgle.roll();
+        let spans = result.styled_spans();
+        let count = |kind| spans.iter().filter(|s| s.kind == kind).count();
+        assert_eq!(count(SpanKind::DiceFace), 2);
+        assert_eq!(count(SpanKind::Success) + count(SpanKind::Failure), 1);
+        assert_eq!(count(SpanKind::Total), 1);
+        let text = spans.iter().map(|s| s.text.as_str()).collect::<String>();
+        assert_eq!(text, result.to_string());
+
+        let gurgle = Gurgle::compile("(1d4)d6max*(2+3d6!)").unwrap();
+        let result = gurgle.roll();
+        let spans = result.styled_spans();
+        let text = spans.iter().map(|s| s.text.as_str()).collect::<String>();
+        assert_eq!(text, result.to_string());
+        assert!(spans
+            .iter()
+            .any(|s| s.kind == SpanKind::Operator && s.text == "*"));
+    }
+
+    #[test]
+    fn test_scaled_avg() {
+        use crate::{expr::PostProcessor, roll::DiceRoll};
+
+        let roll = DiceRoll::new(vec![1, 2, 2], 2, PostProcessor::ScaledAvg(10), None);
+        assert_eq!(roll.value(), 16);
+        let roll = DiceRoll::new(vec![1, 2, 2], 2, PostProcessor::Avg, None);
+        assert_eq!(roll.value(), 1);
+
+        let gurgle = Gurgle::compile("3d2avg10").unwrap();
+        assert_eq!(format!("{:?}", gurgle), "Gurgle(\"3d2avg10\")");
+        for _ in 0..100 {
+            assert!((10..=20).contains(&gurgle.roll().value()));
+        }
+        assert_eq!(gurgle.mode(), Some(13));
+        assert!(Gurgle::compile("3d2avg").unwrap() != gurgle);
+        assert_eq!(
+            Gurgle::compile("3d2avg65537").unwrap_err(),
+            CompileError::NumberItemOutOfRange
+        );
+        assert_eq!("avg10".parse(), Ok(PostProcessor::ScaledAvg(10)));
+        assert!("avgx".parse::<PostProcessor>().is_err());
+    }
+
+    #[test]
+    fn test_keep_drop() {
+        use crate::{expr::PostProcessor, roll::DiceRoll};
+
+        let points = vec![1, 5, 3, 6];
+        let roll = |pp| DiceRoll::new(points.clone(), 6, pp, None);
+        assert_eq!(roll(PostProcessor::KeepHighest(3)).value(), 14);
+        assert_eq!(roll(PostProcessor::DropLowest(1)).value(), 14);
+        assert_eq!(roll(PostProcessor::KeepLowest(2)).value(), 4);
+        assert_eq!(roll(PostProcessor::DropHighest(1)).value(), 9);
+        assert_eq!(
+            roll(PostProcessor::KeepHighest(3)).kept(),
+            vec![false, true, true, true]
+        );
+        assert_eq!(roll(PostProcessor::Sum).kept(), vec![true; 4]);
+        // not enough points
+        assert_eq!(roll(PostProcessor::KeepHighest(5)).value(), 15);
+        assert_eq!(roll(PostProcessor::DropLowest(9)).value(), 0);
+
+        let ties = DiceRoll::new(vec![3, 3, 3], 6, PostProcessor::KeepHighest(2), None);
+        assert_eq!(ties.kept(), vec![true, true, false]);
+        let ties = DiceRoll::new(vec![3, 3, 3], 6, PostProcessor::KeepLowest(2), None);
+        assert_eq!(ties.kept(), vec![true, true, false]);
+
+        let ability = Gurgle::compile("4d6kh3").unwrap();
+        assert_eq!(format!("{:?}", ability), "Gurgle(\"4d6kh3\")");
+        for _ in 0..100 {
+            assert!((3..=18).contains(&ability.roll().value()));
+        }
+        for command in &["4d6kl3", "4d6dh1", "4d6dl1", "(1d4)d6kh2", "4d6!kh3"] {
+            let gurgle = Gurgle::compile(command).unwrap();
+            assert_eq!(format!("{:?}", gurgle), format!("Gurgle(\"{}\")", command));
+        }
+
+        #[cfg(feature = "detail")]
+        assert_eq!(
+            roll(PostProcessor::KeepHighest(3)).to_string(),
+            "(Kh3[~1~,5,3,6]=14)"
+        );
+    }
+
+    #[test]
+    fn test_keep_drop_count() {
+        assert_eq!(
+            Gurgle::compile("4d6kh101").unwrap_err(),
+            CompileError::DiceRollTimesLimitExceeded
+        );
+        assert!(Gurgle::compile("4d6kh").is_err());
+        for command in &[
+            "3d6kh0",
+            "3d6kl0",
+            "3d6dh3",
+            "3d6dl4",
+            "(1d4)d6dh4",
+            "3d6!!dl3",
+        ] {
+            assert_eq!(
+                Gurgle::compile(command).unwrap_err(),
+                CompileError::SelectCountOutOfRange,
+                "{}",
+                command
+            );
+        }
+        for command in &["3d6kh4", "3d6dh2", "(1d4)d6dh3", "3d6!dh3", "3d6!pdl3"] {
+            assert!(Gurgle::compile(command).is_ok(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_checkers() {
+        use crate::checker::Compare;
+
+        let commands = "3d6>10; 2d8"
+            .split(';')
+            .map(Gurgle::compile)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            checkers(&commands),
+            vec![
+                Some(&Checker::Compare {
+                    compare: Compare::Gt,
+                    target: 10,
+                    tolerance: 0,
+                }),
+                None
+            ]
+        );
+        assert!(checkers(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_repeat() {
+        use crate::expr::PostProcessor;
+
+        let points = || Sequence(vec![12, 5, 17].into_iter());
+        for (command, value) in &[
+            ("3#1d20 max", 17),
+            ("3#1d20 min", 5),
+            ("3#1d20 avg", 11),
+            ("3#1d20", 34),
+            ("3#1d20+1", 35),
+        ] {
+            let gurgle = Gurgle::compile(command).unwrap();
+            assert_eq!(format!("{:?}", gurgle), format!("Gurgle(\"{}\")", command));
+            assert_eq!(gurgle.roll_with_rng(&mut points()).value(), *value);
+        }
+
+        let gurgle = Gurgle::compile("3#1d20 max").unwrap();
+        let result = gurgle.roll_with_rng(&mut points());
+        let repeat = result.expr().as_leaf().unwrap().as_repeat().unwrap();
+        assert_eq!(repeat.rolls().len(), 3);
+        assert_eq!(repeat.aggregate(), PostProcessor::Max);
+        #[cfg(feature = "detail")]
+        assert_eq!(result.to_string(), "3#(Max[(12),(5),(17)]=17) = 17");
+
+        let total: i64 = (0..1000).map(|_| gurgle.roll().value()).sum();
+        assert!(total > 11_500);
+        assert_eq!(gurgle.mode(), Some(20));
+        assert_eq!(Gurgle::compile("2#(1d4+1) min").unwrap().mode(), Some(2));
+
+        assert_eq!(
+            Gurgle::compile("0#1d6").unwrap_err(),
+            CompileError::RepeatCountNotPositive
+        );
+        assert_eq!(
+            Gurgle::compile("20#10d6").unwrap_err(),
+            CompileError::DiceRollTimesLimitExceeded
+        );
+        assert_eq!(
+            Gurgle::compile("100#(100#(100#(100#(100#1))))").unwrap_err(),
+            CompileError::ItemCountLimitExceeded
+        );
+        assert_eq!(Gurgle::compile("3#(2#1d6+1)").unwrap().item_count(), 9);
+        assert!(Gurgle::compile("3#1d20 kh1").is_err());
+    }
+
+    #[test]
+    fn test_negative_result() {
+        let gurgle = Gurgle::compile("1d4-1000<-990").unwrap();
+        let result = gurgle.roll_with_rng(&mut Sequence(vec![3].into_iter()));
+        assert_eq!(result.value(), -997);
+        assert_eq!(result.success(), Some(true));
+        #[cfg(feature = "detail")]
+        {
+            assert_eq!(
+                result.to_string(),
+                "(3) - 1000 = -997, target is<-990, success"
+            );
+            assert_eq!(
+                result.steps(),
+                vec![
+                    "1d4: rolled 3 = 3",
+                    "3 - 1000 = -997",
+                    "-997 < -990: success"
+                ]
+            );
+        }
+
+        let config = Config::default().max_number_item_value(u64::MAX);
+        let gurgle = Gurgle::compile_with_config("-9223372036854775808-1d4", &config).unwrap();
+        assert_eq!(gurgle.roll().value(), i64::MIN);
+        for command in &["-9223372036854775808", "-9223372036854775808*2"] {
+            assert_eq!(
+                Gurgle::compile(command).unwrap_err(),
+                CompileError::NumberItemOutOfRange
+            );
+        }
+    }
+
+    #[test]
+    fn test_fudge() {
+        let fate = Gurgle::compile("4dF+1").unwrap();
+        assert_eq!(format!("{:?}", fate), "Gurgle(\"4dF+1\")");
+        let result = fate.roll_with_rng(&mut Sequence(vec![1, 1, 2, 3].into_iter()));
+        assert_eq!(result.value(), 0);
+        let dice = result.expr().as_tree().unwrap().left.as_leaf().unwrap();
+        let dice = dice.as_dice().unwrap();
+        assert!(dice.is_fudge());
+        assert_eq!(dice.value(), -1);
+        #[cfg(feature = "detail")]
+        {
+            assert_eq!(result.to_string(), "(F[-,-,0,+]=-1) + 1 = 0");
+            assert_eq!(result.steps()[0], "4dF: rolled -,-,0,+ = -1");
+        }
+
+        assert_eq!(fate.expr().bounds(), (-3, 5));
+        assert_eq!(fate.mode(), Some(1));
+        assert!((Gurgle::compile("4dF").unwrap().variance().unwrap() - 8.0 / 3.0).abs() < 1e-9);
+        for _ in 0..100 {
+            assert!((-3..=5).contains(&fate.roll().value()));
+        }
+        assert_eq!(Gurgle::compile("(1d4)dF").unwrap().expr().bounds(), (-4, 4));
+        for command in &["4dFmax", "4dF!", "4dFr1", "4df"] {
+            assert!(Gurgle::compile(command).is_err());
+        }
+    }
+
+    #[test]
+    fn test_divide_and_modulo() {
+        for (command, value) in &[
+            ("7/2", 3),
+            ("-7/2", -4),
+            ("7/-2", -4),
+            ("-7/-2", 3),
+            ("6/-2", -3),
+            ("7%3", 1),
+            ("-7%2", 1),
+            ("7%-2", -1),
+            ("-6%4", 2),
+            ("1+7/2*2", 7),
+            ("7%4%2", 1),
+            ("7/0", 0),
+            ("7%0", 0),
+        ] {
+            assert_eq!(roll(command).unwrap(), *value, "{}", command);
+        }
+
+        let gurgle = Gurgle::compile("8/(4/2)").unwrap();
+        assert_eq!(format!("{:?}", gurgle), "Gurgle(\"8/(4/2)\")");
+        assert_eq!(gurgle.roll().value(), 4);
+
+        let zero = Gurgle::compile("1d6/(1d1-1)").unwrap();
+        assert_eq!(zero.roll().value(), 0);
+        assert_eq!(zero.try_roll().unwrap_err(), RollError::DivisionByZero);
+        let zero = Gurgle::compile("1d6%(1d1-1)").unwrap();
+        assert_eq!(zero.try_roll().unwrap_err(), RollError::DivisionByZero);
+        assert!(Gurgle::compile("1d6/2").unwrap().try_roll().is_ok());
+
+        for command in &["1d20/1d4", "1d20%1d6", "(1d6-3)/(1d5-3)", "1d6%(1d5-3)"] {
+            let gurgle = Gurgle::compile(command).unwrap();
+            let (min, max) = gurgle.expr().bounds();
+            let dist = gurgle.distribution().unwrap();
+            assert!(dist.keys().all(|v| (min..=max).contains(v)), "{}", command);
+            assert!((dist.values().sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+        assert_eq!(Gurgle::compile("1d4%(1d1-1)").unwrap().mode(), Some(0));
+        assert_eq!(
+            Gurgle::compile("(1d6-3)/(1d5-3)").unwrap().expr().bounds(),
+            (-3, 3)
+        );
+    }
+
+    #[test]
+    fn test_max_pool_size() {
+        for (command, size) in &[
+            ("2d6+50d4", 50),
+            ("3d6!", 3),
+            ("(1d4+1)d6+2d8", 5),
+            ("3#4d6kh3", 4),
+            ("4dF", 4),
+            ("42", 0),
+        ] {
+            let gurgle = Gurgle::compile(command).unwrap();
+            assert_eq!(gurgle.max_pool_size(), *size, "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_average_target() {
+        use crate::checker::Compare;
+
+        for (command, target) in &[
+            ("3d6 >= avg(1d6)", 3),
+            ("3d6>=avg(2d4+5)", 10),
+            ("1d20 < avg(0-1d4)", -3),
+            ("1d20 = avg((1d4)d6)", 8),
+        ] {
+            let gurgle = Gurgle::compile(command).unwrap();
+            assert_eq!(
+                gurgle.checker().map(|c| match c {
+                    Checker::Compare { target, .. } => *target,
+                    _ => unreachable!(),
+                }),
+                Some(*target),
+                "{}",
+                command
+            );
+        }
+
+        let gurgle = Gurgle::compile("3d6 >= avg(1d6)").unwrap();
+        assert_eq!(format!("{:?}", gurgle), "Gurgle(\"3d6>=3\")");
+        assert_eq!(
+            gurgle.checker(),
+            Some(&Checker::Compare {
+                compare: Compare::Gte,
+                target: 3,
+                tolerance: 0,
+            })
+        );
+        assert!(gurgle.roll().success().unwrap());
+
+        assert!(Gurgle::compile("3d6 >= avg()").is_err());
+        assert!(Gurgle::compile("3d6 >= avg 1d6").is_err());
+        let config = Config::default().max_item_count(2);
+        assert_eq!(
+            Gurgle::compile_with_config("3d6 >= avg(1d6+1)", &config).unwrap_err(),
+            CompileError::ItemCountLimitExceeded
+        );
+    }
+
+    #[test]
+    fn test_percentile() {
+        for (command, normalized) in &[
+            ("d%", "1d100"),
+            ("D%", "1d100"),
+            ("3d%", "3d100"),
+            ("d%+d%", "1d100+1d100"),
+            ("2d%kh1", "2d100kh1"),
+            ("(1d4)d%", "(1d4)d100"),
+            ("d%%7", "1d100%7"),
+            ("d% > 50", "1d100>50"),
+        ] {
+            let gurgle = Gurgle::compile(command).unwrap();
+            assert_eq!(
+                format!("{:?}", gurgle),
+                format!("Gurgle(\"{}\")", normalized)
+            );
+            assert_eq!(gurgle.expr(), Gurgle::compile(normalized).unwrap().expr());
+        }
+        let gurgle = Gurgle::compile("d%").unwrap();
+        for _ in 0..100 {
+            assert!((1..=100).contains(&gurgle.roll().value()));
+        }
+
+        let config = Config::default().max_dice_sides(99);
+        for command in &["d%", "3d%"] {
+            assert_eq!(
+                Gurgle::compile_with_config(command, &config).unwrap_err(),
+                CompileError::DiceSidedCountLimitExceeded
+            );
+        }
+        for command in &["d", "d6", "d%6", "%"] {
+            assert!(Gurgle::compile(command).is_err(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_count_successes() {
+        use crate::{checker::Compare, expr::PostProcessor, roll::DiceRoll};
+
+        let pool = Gurgle::compile("5d10f8 >= 3").unwrap();
+        assert_eq!(format!("{:?}", pool), "Gurgle(\"5d10f8>=3\")");
+        let result = pool.roll_with_rng(&mut Sequence(vec![3, 9, 8, 10, 1].into_iter()));
+        assert_eq!(result.value(), 3);
+        assert_eq!(result.success(), Some(true));
+        let dice = result.expr().as_leaf().unwrap().as_dice().unwrap();
+        assert_eq!(dice.successes(), vec![false, true, true, true, false]);
+        #[cfg(feature = "detail")]
+        assert_eq!(
+            result.to_string(),
+            "(Count>=8[3,9*,8*,10*,1]=3) = 3, target is>=3, success"
+        );
+
+        let roll = |pp| DiceRoll::new(vec![1, 2, 3, 6], 6, pp, None);
+        for (compare, target, value) in &[
+            (Compare::Lt, 3, 2),
+            (Compare::Lte, 3, 3),
+            (Compare::Gt, 3, 1),
+            (Compare::Eq, 6, 1),
+        ] {
+            let pp = PostProcessor::CountSuccesses {
+                compare: *compare,
+                target: *target,
+            };
+            assert_eq!(roll(pp).value(), *value);
+        }
+        assert_eq!(roll(PostProcessor::Sum).successes(), vec![false; 4]);
+    }
+
+    #[test]
+    fn test_count_successes_syntax() {
+        let pool = Gurgle::compile("5d10f8").unwrap();
+
+        for command in &["5d10f<3", "5d10f=10", "3d6!!f6", "(1d4)d6f5", "4d6r1f>4"] {
+            let gurgle = Gurgle::compile(command).unwrap();
+            assert_eq!(format!("{:?}", gurgle), format!("Gurgle(\"{}\")", command));
+        }
+        assert_eq!(Gurgle::compile("5d10f>=8").unwrap().expr(), pool.expr());
+        for command in &["5d10f11", "5d10f0", "5d10f<=99999999999"] {
+            assert_eq!(
+                Gurgle::compile(command).unwrap_err(),
+                CompileError::SuccessTargetOutOfRange,
+                "{}",
+                command
+            );
+        }
+        assert!(Gurgle::compile("5d10f").is_err());
+        assert!(Gurgle::compile("5d10f99999999999999999999").is_err());
+
+        let dist = Gurgle::compile("2d6f5").unwrap().distribution().unwrap();
+        assert!((dist[&2] - 1.0 / 9.0).abs() < 1e-9);
+        assert!((dist[&0] - 4.0 / 9.0).abs() < 1e-9);
+        assert_eq!(Gurgle::compile("2d6f5").unwrap().expr().bounds(), (0, 2));
+        assert_eq!(Gurgle::compile("2d6!f6").unwrap().distribution(), None);
+        assert_eq!(Gurgle::compile("2d6!f6").unwrap().expr().bounds().0, 0);
+    }
+
+    #[test]
+    fn test_roll_in() {
+        let mut arena = RollArena::new();
+        for command in &[
+            "4d6kh3+2d8!-(1d4)d6r1 @seed=42",
+            "3#1d20 max+3#(2d6)avg @seed=7",
+            "4dF+5d10f8-4d6dl1 @seed=1",
+            "2d6/1d3+1d100%(1d6-3) @seed=3",
+        ] {
+            let gurgle = Gurgle::compile(command).unwrap();
+            for _ in 0..10 {
+                assert_eq!(
+                    gurgle.roll_in(&mut arena),
+                    gurgle.roll().value(),
+                    "{}",
+                    command
+                );
+            }
+        }
+
+        let gurgle = Gurgle::compile("4d6+2d8").unwrap();
+        for _ in 0..100 {
+            assert!((6..=40).contains(&gurgle.roll_in(&mut arena)));
+        }
+    }
+
+    #[test]
+    fn test_min_max_result() {
+        use crate::expr::{Explode, ExplodeMode};
+
+        for (command, min, max) in &[
+            ("2d10-3d2-1", -5, 16),
+            ("1d6*(1d4-3)", -12, 6),
+            ("(1d4-3)*(1d4-3)", -2, 4),
+            ("0-1d6-2d6kh1", -12, -2),
+            ("10-2d6max", 4, 9),
+            ("4dF+1", -3, 5),
+            ("3#1d20 max-1d4", -3, 19),
+            ("20/(1d4)", 5, 20),
+        ] {
+            let gurgle = Gurgle::compile(command).unwrap();
+            assert_eq!(
+                (gurgle.min_result(), gurgle.max_result()),
+                (*min, *max),
+                "{}",
+                command
+            );
+            for _ in 0..100 {
+                assert!(
+                    (*min..=*max).contains(&gurgle.roll().value()),
+                    "{}",
+                    command
+                );
+            }
+        }
+
+        let dice = Dice::new(3, 6).with_explode(Explode {
+            mode: ExplodeMode::Standard,
+            max_depth: 2,
+            cap: None,
+        });
+        assert_eq!((dice.min_value(), dice.max_value()), (3, 54));
+        let tree = Gurgle::compile("2d10-3d2").unwrap().expr().clone();
+        assert_eq!(tree.as_tree().unwrap().min_value(), -4);
+        assert_eq!(
+            tree.as_tree().unwrap().right.as_leaf().unwrap().max_value(),
+            6
+        );
+    }
+
+    #[test]
+    fn test_expected_value() {
+        let mean = |command| Gurgle::compile(command).unwrap().expected_value();
+
+        for (command, expected) in &[
+            ("3d6", 10.5),
+            ("4d6+2*1d8", 23.0),
+            ("(1d4)d6", 8.75),
+            ("1d6*1d6-1", 11.25),
+            ("2d6max", 161.0 / 36.0),
+            ("4dF", 0.0),
+            ("3#1d4 sum", 7.5),
+        ] {
+            assert!((mean(command) - expected).abs() < 1e-9, "{}", command);
+        }
+
+        // keep highest is approximated
+        let advantage = mean("2d20kh1");
+        assert!((advantage - 13.825).abs() < 0.05, "{}", advantage);
+        assert!((advantage - mean("2d20kh1")).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)] // because only magnitude of saturated value is compared
+    fn test_saturated_moments() {
+        let saturated = Gurgle::compile("65536*65536*65536*65536*65536").unwrap();
+        assert_eq!(saturated.roll().value(), i64::MAX);
+        assert!((saturated.expected_value() - i64::MAX as f64).abs() < 1.0);
+        assert!(saturated.variance().unwrap().abs() < 1.0);
+
+        let half = Gurgle::compile("(1d2-1)*65536*65536*65536*65536").unwrap();
+        assert!((half.expected_value() - i64::MAX as f64 / 2.0).abs() < 1.0);
+        let repeat = Gurgle::compile("2#(1d2*65536*65536*65536*32768)").unwrap();
+        assert!((repeat.expected_value() - i64::MAX as f64).abs() < 1.0);
+        assert_eq!(repeat.expr().bounds().0, i64::MAX);
+    }
+
+    #[test]
+    fn test_roll_with_modifier() {
+        let value = Gurgle::compile("2d6 @seed=1").unwrap().roll().value();
+        let gurgle = Gurgle::compile(&format!("2d6>={} @seed=1", value + 2)).unwrap();
+
+        let raw = gurgle.roll();
+        assert_eq!(raw.success(), Some(false));
+        assert_eq!(raw.modifier_delta(), 0);
+
+        let buffed = gurgle.roll_with_modifier(|x| x + 2);
+        assert_eq!(buffed.success(), Some(true));
+        assert_eq!(buffed.raw_value(), value);
+        assert_eq!(buffed.value(), value + 2);
+        assert_eq!(buffed.modifier_delta(), 2);
+        #[cfg(feature = "detail")]
+        {
+            let detail = buffed.to_string();
+            let applied = format!("{} + 2 = {}", value, value + 2);
+            assert!(detail.contains(&format!(" = {},", applied)), "{}", detail);
+            let spans: String = buffed
+                .styled_spans()
+                .iter()
+                .map(|s| s.text.as_str())
+                .collect();
+            assert_eq!(spans, detail);
+            assert_eq!(buffed.steps()[1], applied);
+        }
+
+        let number = Gurgle::compile("5").unwrap();
+        let nerfed = number.roll_with_modifier(|x| x - 7);
+        assert_eq!(nerfed.value(), -2);
+        #[cfg(feature = "detail")]
+        assert_eq!(nerfed.to_string(), "5 - 7 = -2");
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        assert_eq!(
+            Gurgle::compile("3d6max + 2d4 + 1 > 10")
+                .unwrap()
+                .to_string(),
+            "3d6max+2d4+1>10"
+        );
+        for command in &[
+            "3d6max + 2d4 + 1 > 10",
+            "(1d4)d6r1 * (2 - 1d20kh1) / 3 % 7",
+            "4d6!!dl1 + 2d10!pf>=8 @seed=42",
+            "3#(2d6+1) max =~1 7",
+            "d% <= avg(2d50)",
+            "4dF + 1d100avg10 odd",
+            "1d20+5 : 10=hit, 20=crit",
+        ] {
+            let gurgle = Gurgle::compile(command).unwrap();
+            let again = Gurgle::compile(&gurgle.to_string()).unwrap();
+            assert_eq!(again, gurgle, "{} => {}", command, gurgle);
+            assert_eq!(again.to_string(), gurgle.to_string());
+        }
+
+        let dice = Dice::new_with_pp(3, 6, crate::expr::PostProcessor::KeepHighest(2));
+        assert_eq!(dice.to_string(), "3d6kh2");
+        assert_eq!(
+            Gurgle::compile(&dice.to_string()).unwrap().expr(),
+            &AstTreeNode::Leaf(crate::expr::Item::Dice(dice))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        use serde::de::{value::StrDeserializer, Deserialize, IntoDeserializer};
+
+        fn assert_serde<T: serde::Serialize + serde::de::DeserializeOwned>() {}
+        fn assert_serialize<T: serde::Serialize>() {}
+        assert_serde::<Gurgle>();
+        assert_serde::<Config>();
+        assert_serde::<AstTreeNode>();
+        assert_serde::<Checker>();
+        assert_serde::<TieredChecker>();
+        assert_serialize::<GurgleRoll<'_>>();
+        assert_serialize::<roll::RollTreeNode>();
+
+        let de = |s: &'static str| -> StrDeserializer<'_, serde::de::value::Error> {
+            s.into_deserializer()
+        };
+        let gurgle = Gurgle::deserialize(de("3d6max + 2d4 + 1 > 10")).unwrap();
+        assert_eq!(gurgle, Gurgle::compile("3d6max+2d4+1>10").unwrap());
+        assert!(Gurgle::deserialize(de("1d6+")).is_err());
+        // limits are checked when deserializing
+        assert!(Gurgle::deserialize(de("1d100000")).is_err());
+    }
+
+    #[test]
+    fn test_crit_probability() {
+        let crit = |command: &str, sided| Gurgle::compile(command).unwrap().crit_probability(sided);
+
+        assert!((crit("1d20", 20) - 0.05).abs() < 1e-9);
+        assert!((crit("2d20kh1+3", 20) - 0.0975).abs() < 1e-9);
+        assert!((crit("3#1d20", 20) - 0.142_625).abs() < 1e-9);
+        assert!((crit("1d20+1d6", 6) - 1.0 / 6.0).abs() < 1e-9);
+        // roll 1 or 2 d4 with the same chance
+        assert!((crit("(1d2)d4", 4) - 0.343_75).abs() < 1e-9);
+        assert!(crit("1d20+5", 6).abs() < 1e-9);
+        assert!(crit("4dF", 3).abs() < 1e-9);
+        assert!((crit("1d1", 1) - 1.0).abs() < 1e-9);
+
+        let loaded = AstTreeNode::Leaf(Item::Dice(
+            Dice::new_weighted(1, 4, vec![1, 1, 1, 5]).unwrap(),
+        ));
+        let miss = loaded.crit_miss(4, &mut dist::Budget::new(dist::DEFAULT_BUDGET));
+        assert!((miss - 0.375).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_canonical_ast() {
+        let canonical = |s| Gurgle::compile(s).unwrap().to_canonical_ast();
+
+        assert_eq!(canonical("1+2*3"), "1+(2*3)");
+        assert_eq!(canonical("1 + 2 - 3"), "(1+2)-3");
+        assert_eq!(canonical("(1 + 2) * 3 > 5"), "(1+2)*3");
+        assert_eq!(canonical("(1d4+1)d6r1!max"), "(1d4+1)d6r1!max");
+        assert_eq!(canonical("3#1d20 max + 2#(1d4+1)"), "3#(1d20)max+2#(1d4+1)");
+
+        for command in &[
+            "1+2*3",
+            "3d6max + 2d4 + 1 - -2",
+            "(1d4)d6r1 * (2 - 1d20kh1) / 3 % 7",
+            "4d6!!dl1 + 2d10!pf>=8 + 4dF + d%",
+            "3#(2d6+1) max * 3#5 avg",
+        ] {
+            let gurgle = Gurgle::compile(&format!("{} @seed=1", command)).unwrap();
+            let ast = gurgle.to_canonical_ast();
+            assert!(!ast.contains(' '), "{}", ast);
+            let again = Gurgle::compile(&format!("{} @seed=1", ast)).unwrap();
+            assert_eq!(again.expr(), gurgle.expr(), "{}", ast);
+            assert_eq!(again.to_canonical_ast(), ast);
+            assert_eq!(again.roll().value(), gurgle.roll().value(), "{}", ast);
+        }
+        assert_eq!(
+            Gurgle::compile("(1+2)+(3*4)").unwrap().expr(),
+            Gurgle::compile("1+2+3*4").unwrap().expr()
+        );
+        assert_eq!(Gurgle::compile("1-(2-3)").unwrap().to_string(), "1-(2-3)");
+        assert_eq!(Gurgle::compile("(1+2)*3").unwrap().to_string(), "(1+2)*3");
+    }
+
+    #[test]
+    #[cfg(feature = "detail")]
+    fn test_display_with() {
+        use crate::detail::OutputSpans;
+
+        const COMMAND: &str = "3d6+1 > 100 : 1=low @seed=3";
+        let render = |lang: OutputSpans| {
+            std::thread::spawn(move || {
+                let gurgle = Gurgle::compile(COMMAND).unwrap();
+                let outputs: Vec<_> = (0..100)
+                    .map(|_| gurgle.roll().display_with(&lang).to_string())
+                    .collect();
+                assert!(outputs.iter().all(|o| o == &outputs[0]));
+                outputs[0].clone()
+            })
+        };
+        // two languages are used at the same time
+        let (en, zh) = (
+            render(OutputSpans::new_en()),
+            render(OutputSpans::new_zh_cn()),
+        );
+        let (en, zh) = (en.join().unwrap(), zh.join().unwrap());
+        assert!(en.ends_with(", target is>100, failed, low"), "{}", en);
+        assert!(zh.ends_with("，目标为>100，失败，low"), "{}", zh);
+
+        let gurgle = Gurgle::compile(COMMAND).unwrap();
+        let result = gurgle.roll();
+        let lang = OutputSpans::new_zh_cn();
+        let spans: String = result
+            .styled_spans_with(&lang)
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect();
+        assert_eq!(spans, zh);
+        let steps = result.steps_with(&lang);
+        assert_eq!(
+            steps[steps.len() - 2],
+            format!("{} > 100: 失败", result.value())
+        );
+    }
+
+    #[test]
+    fn test_explode_cap() {
+        let gurgle = Gurgle::compile("1d6!x2").unwrap();
+        let result = gurgle.roll_with_rng(&mut Sequence(vec![6; 10].into_iter()));
+        assert_eq!(result.value(), 18);
+        let dice = result.expr().as_leaf().unwrap().as_dice().unwrap();
+        assert_eq!(dice.points(), &[6, 6, 6]);
+        assert_eq!(gurgle.max_result(), 18);
+        assert_eq!(format!("{:?}", gurgle), "Gurgle(\"1d6!x2\")");
+        assert_eq!(gurgle.to_canonical_ast(), "1d6!x2");
+
+        let config = Config::default().max_explode_depth(3);
+        for (command, value) in &[("1d1!x5", 4), ("1d1!!x1", 2), ("1d1!px0", 1), ("1d1!", 4)] {
+            let gurgle = Gurgle::compile_with_config(command, &config).unwrap();
+            assert_eq!(gurgle.roll().value(), *value, "{}", command);
+            assert_eq!(gurgle.to_string(), *command);
+        }
+
+        // `x` is multiply when not following explode mode directly
+        assert_eq!(Gurgle::compile("1d1! x 2").unwrap().to_string(), "1d1!*2");
+        assert!(Gurgle::compile("1d6!x99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn test_not_equal_checker() {
+        use crate::checker::Compare;
+
+        let gurgle = Gurgle::compile("1d20 != 1").unwrap();
+        assert_eq!(
+            gurgle.checker(),
+            Some(&Checker::Compare {
+                compare: Compare::Ne,
+                target: 1,
+                tolerance: 0,
+            })
+        );
+        assert_eq!(gurgle.to_string(), "1d20 !=1");
+        assert_eq!(Gurgle::compile(&gurgle.to_string()).unwrap(), gurgle);
+        let checker = gurgle.checker().unwrap();
+        assert!(!checker.check(1));
+        assert!(checker.check(0));
+        assert!(checker.check(2));
+        assert!(Checker::Compare {
+            compare: Compare::Eq,
+            target: 1,
+            tolerance: 0
+        }
+        .check(1));
+        assert_eq!("!=".parse::<Compare>(), Ok(Compare::Ne));
+        assert_eq!(Compare::Ne.to_string(), "!=");
+
+        let always = Gurgle::compile("3 != 4").unwrap();
+        assert_eq!(always.roll().success(), Some(true));
+        assert_eq!(
+            Gurgle::compile("3 != 3").unwrap().roll().success(),
+            Some(false)
+        );
+        assert!(Gurgle::compile("1d6 =! 3").is_err());
+    }
+
+    #[test]
+    fn test_not_equal_after_dice() {
+        use crate::checker::Compare;
+
+        // `!` followed by `=` is a not equal checker, not explosion
+        let explodes = |g: &Gurgle| {
+            g.expr()
+                .as_leaf()
+                .unwrap()
+                .as_dice()
+                .unwrap()
+                .explode
+                .is_some()
+        };
+        let ne = Gurgle::compile("1d20!=1").unwrap();
+        assert!(std::matches!(
+            ne.checker(),
+            Some(Checker::Compare {
+                compare: Compare::Ne,
+                target: 1,
+                ..
+            })
+        ));
+        assert!(!explodes(&ne));
+        let exploding = Gurgle::compile("1d20! != 1").unwrap();
+        assert!(explodes(&exploding) && exploding.checker() == ne.checker());
+        assert_eq!(Gurgle::compile(&exploding.to_string()).unwrap(), exploding);
+    }
+
+    #[test]
+    fn test_batch_summary() {
+        let save = Gurgle::compile("1d20>=11 @seed=5").unwrap();
+        let batch = save.roll_batch(1000);
+        let summary = batch.summary();
+        assert_eq!(summary.total, 1000);
+        assert!(
+            (summary.pass_rate - 0.5).abs() < 0.06,
+            "{}",
+            summary.pass_rate
+        );
+        assert_eq!(summary.margins.len(), 1000);
+        for (roll, margin) in batch.rolls().iter().zip(&summary.margins) {
+            assert_eq!(*margin, Some(roll.value() - 11));
+            assert_eq!(roll.success(), Some(margin.unwrap() >= 0));
+        }
+        let passed = summary.margins.iter().filter(|m| m.unwrap() >= 0).count();
+        assert_eq!(summary.pass_count, passed);
+
+        let unchecked = Gurgle::compile("1d20").unwrap().roll_batch(10).summary();
+        assert_eq!(unchecked.pass_count, 0);
+        assert!(unchecked.pass_rate.abs() < f64::EPSILON);
+        assert_eq!(unchecked.margins, vec![None; 10]);
+        assert_eq!(Gurgle::compile("1d20 odd").unwrap().roll().margin(), None);
+        assert!(save.roll_batch(0).summary().pass_rate.abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_digit_separator() {
+        use crate::checker::Compare;
+
+        let gurgle = Gurgle::compile("3d6 >= 1_000").unwrap();
+        assert_eq!(
+            gurgle.checker(),
+            Some(&Checker::Compare {
+                compare: Compare::Gte,
+                target: 1000,
+                tolerance: 0,
+            })
+        );
+        assert_eq!(gurgle.to_string(), "3d6>=1000");
+        assert_eq!(
+            Gurgle::compile("1_0d1_00+2_0").unwrap().to_string(),
+            "10d100+20"
+        );
+        assert!(Gurgle::compile("1__000").is_err());
+        assert!(Gurgle::compile("1000_").is_err());
+
+        assert_eq!(
+            Gurgle::compile("3d6 >= 1,000").unwrap_err(),
+            CompileError::DigitSeparatorNotAllowed
+        );
+        let comma = Config::default().digit_separator(DigitSeparator::UnderscoreOrComma);
+        let gurgle = Gurgle::compile_with_config("1d20 + 1,000 : 1,010=hit", &comma).unwrap();
+        assert_eq!(gurgle.to_string(), "1d20+1000 : 1010=hit");
+        assert!(Gurgle::compile_with_config("1,00", &comma).is_err());
+        assert!(Gurgle::compile_with_config("1,0000", &comma).is_err());
+
+        let disabled = Config::default().digit_separator(DigitSeparator::Disabled);
+        assert_eq!(
+            Gurgle::compile_with_config("1_000", &disabled).unwrap_err(),
+            CompileError::DigitSeparatorNotAllowed
+        );
+        assert_ne!(Config::default(), disabled);
+        assert_eq!(
+            Config::default().diff(&disabled.max_paren_depth(1)),
+            vec![
+                ConfigFieldDiff {
+                    field: "digit_separator",
+                    left: ConfigFieldValue::DigitSeparator(DigitSeparator::Underscore),
+                    right: ConfigFieldValue::DigitSeparator(DigitSeparator::Disabled),
+                },
+                ConfigFieldDiff {
+                    field: "max_paren_depth",
+                    left: ConfigFieldValue::Limit(16),
+                    right: ConfigFieldValue::Limit(1),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_syntax_error_location() {
+        let err = match Gurgle::compile("3d6+2p10+1") {
+            Err(CompileError::InvalidSyntax(err)) => err,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(err.offset(), 5);
+        assert_eq!(err.span(), (5, 5));
+        assert_eq!(err.line_col(), (1, 6));
+        assert!(err.expected().iter().any(|e| e == "op_add"));
+        assert_eq!(
+            err.message(),
+            err.to_string().trim_end_matches(" at line 1, column 6")
+        );
+        assert!(!err.to_string().contains('\n'));
+    }
+
+    #[test]
+    fn test_dice_rolls() {
+        let gurgle = Gurgle::compile("1d20+(2d6*(3+1d4))-2#1d8+(1d4)d6").unwrap();
+        let sided: Vec<_> = gurgle.expr().dices().map(|d| d.sided).collect();
+        assert_eq!(sided, vec![20, 6, 4, 8, 6]);
+
+        let result = gurgle.roll();
+        let sided: Vec<_> = result.dice_rolls().map(DiceRoll::sided).collect();
+        assert_eq!(sided, vec![20, 6, 4, 8, 8, 6]);
+        let advantaged = result.apply_advantage_to(3).unwrap();
+        assert!(
+            advantaged.dice_rolls().nth(3).unwrap().value()
+                >= result.dice_rolls().nth(3).unwrap().value()
+        );
+
+        assert_eq!(
+            Gurgle::compile("1+2").unwrap().roll().dice_rolls().count(),
+            0
+        );
+        assert_eq!(Gurgle::compile("1+2").unwrap().expr().dices().count(), 0);
+    }
+
+    #[test]
+    fn test_shortfall() {
+        let roll = |command: &str, points: Vec<u64>| {
+            let gurgle = Gurgle::compile(command).unwrap();
+            gurgle
+                .roll_with_rng(&mut Sequence(points.into_iter()))
+                .shortfall()
+        };
+
+        assert_eq!(roll("3d6 > 10", vec![2, 3, 3]), Some(3));
+        assert_eq!(roll("3d6 >= 10", vec![2, 3, 3]), Some(2));
+        assert_eq!(roll("3d6 > 10", vec![4, 4, 3]), None);
+        assert_eq!(roll("1d20 <= 10", vec![11]), Some(-1));
+        assert_eq!(roll("1d20 < 10", vec![11]), Some(-2));
+        assert_eq!(roll("1d20 =~2 10", vec![5]), Some(3));
+        assert_eq!(roll("1d20 =~2 10", vec![15]), Some(-3));
+        assert_eq!(roll("1d20 != 10", vec![10]), Some(1));
+        assert_eq!(roll("1d20 even", vec![3]), Some(1));
+        assert_eq!(roll("1d20", vec![3]), None);
+        assert_eq!(roll("1d20 > 20", vec![3]), None);
+        assert_eq!(roll("1d20 != 20", vec![20]), Some(-1));
+        assert_eq!(roll("1d20 odd", vec![20]), Some(-1));
+    }
+
+    #[test]
+    fn test_finalize() {
+        fn assert_plain<T: Send + Sync + Clone + 'static>(_: &T) {}
+
+        let gurgle = Gurgle::compile("2d6+1d4+3 >= 10 : 10=hit, 15=crit").unwrap();
+        let result = gurgle.roll();
+        let value = result.value();
+        let success = result.success();
+        let tier = result.tier().map(str::to_owned);
+        let points: Vec<_> = result.dice_rolls().map(|d| d.points().to_vec()).collect();
+
+        let frozen = result.finalize();
+        assert_plain(&frozen);
+        let cloned = frozen.clone();
+        drop(gurgle);
+        assert_eq!(cloned, frozen);
+        assert_eq!(frozen.value(), value);
+        assert_eq!(frozen.raw_value(), value);
+        assert_eq!(frozen.modifier_delta(), 0);
+        assert_eq!(frozen.success(), success);
+        assert_eq!(frozen.tier(), tier.as_deref());
+        assert_eq!(frozen.dice_points(), &points[..]);
+        assert_eq!(points.len(), 2);
+
+        let frozen = std::thread::spawn(move || frozen).join().unwrap();
+        assert_eq!(frozen.value(), value);
+    }
+
+    #[test]
+    fn test_try_value() {
+        let config = Config::default().max_number_item_value(u64::MAX);
+        let gurgle = Gurgle::compile_with_config("1d6+4000000000*4000000000", &config).unwrap();
+        let result = gurgle.roll();
+        assert_eq!(result.value(), i64::MAX);
+        assert_eq!(
+            result.try_value(),
+            Err(RollError::IntermediateMagnitudeLimitExceeded)
+        );
+        assert_eq!(
+            result.expr().try_value(),
+            Err(RollError::IntermediateMagnitudeLimitExceeded)
+        );
+
+        let gurgle = Gurgle::compile("1d6*(2-2)+3/(1-1)").unwrap();
+        assert_eq!(gurgle.roll().try_value(), Err(RollError::DivisionByZero));
+
+        let gurgle = Gurgle::compile("3d6*1000*1000").unwrap();
+        let result = gurgle.roll();
+        assert_eq!(result.try_value(), Ok(result.value()));
+    }
+
+    #[test]
+    fn test_avg_of_zero_dice() {
+        use crate::expr::PostProcessor;
+
+        for pp in [
+            PostProcessor::Avg,
+            PostProcessor::ScaledAvg(10),
+            PostProcessor::Max,
+            PostProcessor::KeepHighest(1),
+        ] {
+            let dice = Dice {
+                pp,
+                ..Dice::new(0, 6)
+            };
+            let result = dice.roll_with_rng(&mut nanorand::WyRand::new_seed(0));
+            assert!(result.points().is_empty());
+            assert_eq!(result.value(), 0, "{}", pp);
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let evaluation = evaluate("3d6 + 1 > 10", &Config::default()).unwrap();
+        assert_eq!(evaluation.command, "3d6+1>10");
+        assert!((4..=19).contains(&evaluation.value));
+        assert_eq!(evaluation.success, Some(evaluation.value > 10));
+        assert_eq!(evaluation.tier, None);
+        #[cfg(feature = "detail")]
+        assert!(evaluation
+            .detail
+            .unwrap()
+            .contains(&format!("= {}", evaluation.value)));
+        #[cfg(not(feature = "detail"))]
+        assert_eq!(evaluation.detail, None);
+
+        let evaluation = evaluate("1d20 : 1=low, 11=high @seed=3", &Config::default()).unwrap();
+        let expected = Gurgle::compile("1d20 : 1=low, 11=high @seed=3")
+            .unwrap()
+            .roll()
+            .value();
+        assert_eq!(evaluation.value, expected);
+        assert_eq!(
+            evaluation.tier.as_deref(),
+            Some(if expected >= 11 { "high" } else { "low" })
+        );
+        assert_eq!(evaluation.success, None);
+
+        let strict = Config::default().max_dice_sides(10);
+        assert_eq!(
+            evaluate("3d20", &strict).unwrap_err(),
+            CompileError::DiceSidedCountLimitExceeded
+        );
+    }
+
+    #[test]
+    fn test_command_repeat() {
+        let stats = Gurgle::compile("6#(3d6+2) each").unwrap();
+        assert_eq!(stats.repeat(), Some(6));
+        assert_eq!(stats.to_string(), "6#(3d6+2) each");
+        assert_eq!(Gurgle::compile(&stats.to_string()).unwrap(), stats);
+        let batch = stats.roll_repeated();
+        let rolls = batch.rolls();
+        assert_eq!(rolls.len(), 6);
+        assert!(rolls.iter().all(|r| (5..=20).contains(&r.value())));
+        assert!((5..=20).contains(&stats.roll().value()));
+
+        let checked = Gurgle::compile("4#1d20 each >= 11 @seed=7").unwrap();
+        assert_eq!(checked.to_string(), "4#1d20 each>=11 @seed=7");
+        let batch = checked.roll_repeated();
+        let rolls = batch.rolls();
+        for roll in rolls {
+            assert_eq!(roll.success(), Some(roll.value() >= 11));
+        }
+        let values: Vec<_> = rolls.iter().map(GurgleRoll::value).collect();
+        assert_eq!(checked.roll_many_values(4), values);
+
+        // without `each`, it's still a summed repeat item
+        let summed = Gurgle::compile("6#(3d6+2)").unwrap();
+        assert_eq!(summed.repeat(), None);
+        assert_eq!(summed.roll_repeated().rolls().len(), 1);
+        assert!(Gurgle::compile("2#(1d6) each + 1").is_err());
+
+        assert_eq!(
+            Gurgle::compile("9999#(3d6) each").unwrap_err(),
+            CompileError::CommandRepeatLimitExceeded
+        );
+        assert_eq!(
+            Gurgle::compile("0#(3d6) each").unwrap_err(),
+            CompileError::RepeatCountNotPositive
+        );
+        let config = Config::default().max_command_repeat(100);
+        assert!(Gurgle::compile_with_config("99#(3d6) each", &config).is_ok());
+
+        #[cfg(feature = "detail")]
+        {
+            let batch = Gurgle::compile("3#1d1 each").unwrap();
+            let lines = batch.roll_repeated().to_string();
+            assert_eq!(lines.lines().count(), 3);
+            assert!(lines.lines().all(|l| l == batch.roll().to_string()));
+        }
+    }
+
+    #[test]
+    fn test_item_label() {
+        let gurgle = Gurgle::compile("2d10+5 [to hit] + 2d6[ damage ] >= 10").unwrap();
+        assert_eq!(gurgle.to_string(), "2d10+5 [to hit]+2d6 [damage]>=10");
+        assert_eq!(gurgle.to_canonical_ast(), "(2d10+5)+2d6");
+        assert_eq!(Gurgle::compile(&gurgle.to_string()).unwrap(), gurgle);
+
+        let labels: Vec<_> = gurgle
+            .expr()
+            .as_tree()
+            .unwrap()
+            .right
+            .as_leaf()
+            .iter()
+            .map(|i| i.label())
+            .collect();
+        assert_eq!(labels, [Some("damage")]);
+
+        // helpers look through the label
+        let damage = gurgle.expr().as_tree().unwrap().right.as_leaf().unwrap();
+        assert!(damage.is_dice());
+        assert_eq!(damage.as_dice().map(expr::Dice::sided), Some(6));
+        let group = Gurgle::compile("(1d4+1) [base]").unwrap();
+        assert!(group.expr().as_leaf().unwrap().is_expr());
+
+        let result = gurgle.roll_with_rng(&mut Sequence(vec![4, 5, 3, 4].into_iter()));
+        assert_eq!(result.value(), 21);
+        assert_eq!(result.expr().try_value(), Ok(21));
+        assert_eq!(result.dice_rolls().count(), 2);
+        let damage = result.expr().as_tree().unwrap().right.as_leaf().unwrap();
+        assert_eq!(damage.as_dice().map(roll::DiceRoll::value), Some(7));
+        #[cfg(feature = "detail")]
+        assert_eq!(
+            result.to_string(),
+            "(4+5) + 5 [to hit] + (3+4) [damage] = 21, target is>=10, success"
+        );
+
+        // labels are ignored by value computation, also around parentheses and repeats
+        let group = Gurgle::compile("(1d1+2) [base] * 2#1d1 [twice]").unwrap();
+        assert_eq!(group.roll().value(), 6);
+        assert_eq!(group.max_result(), 6);
+
+        // empty label is the same as no label
+        assert_eq!(
+            Gurgle::compile("1d6 []").unwrap(),
+            Gurgle::compile("1d6").unwrap()
+        );
+        assert!(Gurgle::compile("1d6 [a [b] c]").is_err());
+        assert!(Gurgle::compile("1d6 [a").is_err());
+    }
+
+    #[test]
+    fn test_critical() {
+        let crit = |s: &str, points: Vec<u64>| {
+            let gurgle = Gurgle::compile(s).unwrap();
+            let result = gurgle.roll_with_rng(&mut Sequence(points.into_iter()));
+            result.critical()
+        };
+
+        assert_eq!(crit("1d20+5", vec![20]), Some(Crit::Max));
+        assert_eq!(crit("1d20+5", vec![1]), Some(Crit::Min));
+        assert_eq!(crit("1d20+5", vec![19]), None);
+        // only the first round is checked
+        assert_eq!(crit("1d20+1d20", vec![7, 20]), None);
+        assert_eq!(crit("3d6", vec![6, 6, 6]), Some(Crit::Max));
+        assert_eq!(crit("3d6", vec![6, 6, 5]), None);
+        assert_eq!(crit("2d20kh1", vec![20, 3]), Some(Crit::Max));
+        assert_eq!(crit("2d20kh1", vec![1, 3]), None);
+        assert_eq!(crit("2d20kl1", vec![1, 20]), Some(Crit::Min));
+        assert_eq!(crit("1d6!!", vec![6, 2]), Some(Crit::Max));
+        // extra die of penetrate showing 2 has point 1, but it is not a natural min
+        assert_eq!(crit("2d6!pkl1", vec![6, 2, 4]), None);
+        assert_eq!(crit("2d6!pkl1", vec![6, 1, 4]), Some(Crit::Min));
+        assert_eq!(crit("4dF", vec![3, 3, 3, 3]), Some(Crit::Max));
+        assert_eq!(crit("4dF", vec![1, 1, 1, 1]), Some(Crit::Min));
+        assert_eq!(crit("1d1", vec![1]), Some(Crit::Max));
+        assert_eq!(crit("10", vec![]), None);
+
+        let gurgle = Gurgle::compile("1d20+2d6").unwrap();
+        let result = gurgle.roll_with_rng(&mut Sequence(vec![9, 1, 1].into_iter()));
+        assert_eq!(result.critical_of(1), Some(Crit::Min));
+        assert_eq!(result.critical_of(2), None);
+        let dice = result.dice_rolls().nth(1).unwrap();
+        assert!(dice.is_all_min());
+        assert!(!dice.is_all_max());
+        assert_eq!(dice.natural_min(), [true, true]);
+    }
+
+    #[test]
+    fn test_sorted_points() {
+        use crate::roll::SortOrder;
+
+        let gurgle = Gurgle::compile("5d6dl1").unwrap();
+        let result = gurgle.roll_with_rng(&mut Sequence(vec![3, 1, 6, 2, 3].into_iter()));
+        let dice = result.dice_rolls().next().unwrap();
+        assert_eq!(dice.sorted_points(SortOrder::Ascending), [1, 2, 3, 3, 6]);
+        assert_eq!(dice.sorted_points(SortOrder::Descending), [6, 3, 3, 2, 1]);
+        assert_eq!(dice.points(), [3, 1, 6, 2, 3]);
+        assert_eq!(dice.value(), 14);
+
+        #[cfg(feature = "detail")]
+        {
+            assert_eq!(dice.to_string(), "(Dl1[3,~1~,6,2,3]=14)");
+            assert_eq!(
+                dice.display_sorted(SortOrder::Ascending).to_string(),
+                "(Dl1[~1~,2,3,3,6]=14)"
+            );
+            assert_eq!(
+                dice.display_sorted(SortOrder::Descending).to_string(),
+                "(Dl1[6,3,3,2,~1~]=14)"
+            );
+        }
+    }
+
+    #[test]
+    fn test_compile_many() {
+        let commands = Gurgle::compile_many("3d6; 1d20+5 [to hit; melee] >= 15;2d8;").unwrap();
+        let shown: Vec<_> = commands.iter().map(ToString::to_string).collect();
+        assert_eq!(shown, ["3d6", "1d20+5 [to hit; melee]>=15", "2d8"]);
+
+        let values = roll_many("1d1; ;2d1+1").unwrap();
+        assert_eq!(values, [1, 3]);
+
+        let err = Gurgle::compile_many("3d6; 1d20+ ; 2d8").unwrap_err();
+        let CompileError::InCommand {
+            index,
+            offset,
+            error,
+        } = &err
+        else {
+            panic!("unexpected error {:?}", err);
+        };
+        assert_eq!((*index, *offset), (1, 4));
+        assert!(core::matches!(
+            error.as_ref(),
+            CompileError::InvalidSyntax(_)
+        ));
+        assert!(err
+            .to_string()
+            .starts_with("command 2 is invalid: invalid gurgle syntax"));
+
+        let config = Config::default().max_roll_times(10);
+        assert_eq!(
+            Gurgle::compile_many_with_config("5d6;5d6;11d6", &config).unwrap_err(),
+            CompileError::InCommand {
+                index: 2,
+                offset: 8,
+                error: Box::new(CompileError::DiceRollTimesLimitExceeded),
+            }
+        );
+
+        assert!(core::matches!(
+            Gurgle::compile_many(" ; ").unwrap_err(),
+            CompileError::InCommand { index: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn test_distribution() {
+        let dist = Gurgle::compile("3d6").unwrap().distribution().unwrap();
+        assert_eq!(dist.len(), 16);
+        assert_eq!(dist.keys().next(), Some(&3));
+        assert!((dist[&10] - 27.0 / 216.0).abs() < 1e-9);
+        assert!((dist.values().sum::<f64>() - 1.0).abs() < 1e-9);
+
+        let shifted = Gurgle::compile("2d6-1d4+3")
+            .unwrap()
+            .distribution()
+            .unwrap();
+        assert_eq!(shifted.keys().next(), Some(&1));
+        assert_eq!(shifted.keys().last(), Some(&14));
+        assert!((shifted[&14] - 1.0 / 144.0).abs() < 1e-9);
+
+        let constant = Gurgle::compile("5").unwrap().distribution().unwrap();
+        assert_eq!(constant.into_iter().collect::<Vec<_>>(), [(5, 1.0)]);
+
+        let config = Config::default().max_roll_times(200);
+        assert!(Gurgle::compile_with_config("100d1000*100d1000", &config)
+            .unwrap()
+            .distribution()
+            .is_none());
+        let small = Config::default().max_distribution_steps(10);
+        let limited = Gurgle::compile_with_config("3d6", &small).unwrap();
+        assert!(limited.distribution().is_none());
+        assert!(limited.mode().is_none());
+    }
+
+    #[test]
+    fn test_paren_depth_limit() {
+        let nested = |depth| format!("{}1d6{}", "(".repeat(depth), ")".repeat(depth));
+
+        assert!(Gurgle::compile(&nested(16)).is_ok());
+        assert_eq!(
+            Gurgle::compile(&nested(17)).unwrap_err(),
+            CompileError::ParenthesesDepthLimitExceeded
+        );
+        // rejected before parsing, so parser does not overflow the stack
+        assert_eq!(
+            Gurgle::compile(&nested(100_000)).unwrap_err(),
+            CompileError::ParenthesesDepthLimitExceeded
+        );
+
+        let config = Config::default().max_paren_depth(2);
+        assert!(Gurgle::compile_with_config("((1d6+1)*2)+(1d4)d6 >= avg(1d6)", &config).is_ok());
+        for command in &[
+            "(((1d6)d6))",
+            "(1+((1d4)d6))",
+            "1d6 >= avg(((1d6)))",
+            "6#(((1d6))*2) each",
+        ] {
+            assert_eq!(
+                Gurgle::compile_with_config(command, &config).unwrap_err(),
+                CompileError::ParenthesesDepthLimitExceeded,
+                "{}",
+                command
+            );
+        }
+        // parentheses in labels are not counted
+        assert!(Gurgle::compile_with_config("1d6 [(((x)))]", &config).is_ok());
+    }
+
+    #[test]
+    fn test_uppercase_dice() {
+        for (command, normalized) in &[
+            ("3D6", "3d6"),
+            ("3d6", "3d6"),
+            ("2D6+1d4-1D4", "2d6+1d4-1d4"),
+            ("(1D4)D6", "(1d4)d6"),
+            ("4D6kh3", "4d6kh3"),
+            ("3D6!max >= 10", "3d6!max>=10"),
+            ("3#1D20 max", "3#1d20 max"),
+        ] {
+            let gurgle = Gurgle::compile(command).unwrap();
+            assert_eq!(gurgle.to_string(), *normalized, "{}", command);
+            assert_eq!(Gurgle::compile(normalized).unwrap(), gurgle, "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_range_checker() {
+        let gurgle = Gurgle::compile("3d6 in 8..12").unwrap();
+        assert_eq!(gurgle.checker(), Some(&Checker::Range { min: 8, max: 12 }));
+        assert_eq!(gurgle.to_string(), "3d6 in 8..12");
+        assert_eq!(Gurgle::compile(&gurgle.to_string()).unwrap(), gurgle);
+
+        let checker = gurgle.checker().unwrap();
+        assert!(!checker.check(7));
+        assert!(checker.check(8));
+        assert!(checker.check(12));
+        assert!(!checker.check(13));
+
+        let single = Gurgle::compile("1d1 in 1..1").unwrap();
+        let single = single.roll();
+        assert_eq!(single.success(), Some(true));
+        assert_eq!(single.margin(), None);
+        #[cfg(feature = "detail")]
+        assert!(single.to_string().ends_with("target is in 1..1, success"));
+
+        let low = Gurgle::compile("1d1 in 3..5").unwrap();
+        let low = low.roll();
+        // `1d1` never rolls into the range
+        assert_eq!((low.success(), low.shortfall()), (Some(false), None));
+        let low = Gurgle::compile("1d6 in 3..5").unwrap();
+        let low = low.roll_with_rng(&mut Sequence(vec![1].into_iter()));
+        assert_eq!((low.success(), low.shortfall()), (Some(false), Some(2)));
+        let high = Gurgle::compile("1d20-5 in -3..5").unwrap();
+        let high = high.roll_with_rng(&mut Sequence(vec![15].into_iter()));
+        assert_eq!(high.shortfall(), Some(-5));
+
+        assert_eq!(
+            Gurgle::compile("3d6 in 12..8").unwrap_err(),
+            CompileError::EmptyCheckRange
+        );
+        assert_eq!(
+            Gurgle::compile("3d6 in 1..100000").unwrap_err(),
+            CompileError::NumberItemOutOfRange
+        );
+        assert!(Gurgle::compile("3d6 in 8..").is_err());
+        assert!(Gurgle::compile("3d6 in 8..12 : 10=mid").is_ok());
+    }
+
+    #[test]
+    fn test_roll_stats() {
+        let gurgle = Gurgle::compile("3d6+1 @seed=5").unwrap();
+        let values: Vec<_> = gurgle.roll_n(500).collect();
+        assert_eq!(values.len(), 500);
+        assert!(values.iter().all(|v| (4..=19).contains(v)));
+        assert_eq!(gurgle.roll_many_values(500), values);
+        assert_eq!(
+            gurgle
+                .roll_array(3)
+                .iter()
+                .map(GurgleRoll::value)
+                .collect::<Vec<_>>(),
+            values[..3]
+        );
+
+        let stats = gurgle.roll_stats(500);
+        assert_eq!(stats.count, 500);
+        assert_eq!(stats.min, values.iter().min().copied());
+        assert_eq!(stats.max, values.iter().max().copied());
+        assert_eq!(stats.histogram.values().sum::<usize>(), 500);
+        assert_eq!(
+            stats.histogram[&values[0]],
+            values.iter().filter(|v| **v == values[0]).count()
+        );
+        assert!((stats.mean - 11.5).abs() < 0.5);
+
+        let constant = Gurgle::compile("2d1").unwrap().roll_stats(10);
+        assert_eq!(
+            (constant.min, constant.max, constant.mean),
+            (Some(2), Some(2), 2.0)
+        );
+
+        let empty = gurgle.roll_stats(0);
+        assert_eq!((empty.count, empty.min, empty.mean), (0, None, 0.0));
+        assert!(empty.histogram.is_empty());
+    }
+
+    #[test]
+    fn test_success_probability() {
+        let hit = Gurgle::compile("1d20 >= 15").unwrap();
+        assert!((hit.success_probability().unwrap() - 0.3).abs() < 1e-9);
+        let even = Gurgle::compile("2d6 even").unwrap();
+        assert!((even.success_probability().unwrap() - 0.5).abs() < 1e-9);
+        let range = Gurgle::compile("3d6 in 9..11").unwrap();
+        assert!((range.success_probability().unwrap() - 79.0 / 216.0).abs() < 1e-9);
+        let never = Gurgle::compile("1d6 > 6").unwrap();
+        assert_eq!(never.success_probability(), Some(0.0));
+        let impossible = Gurgle::compile("3d6 > 20")
+            .unwrap()
+            .success_probability()
+            .unwrap();
+        assert_eq!(impossible.to_bits(), 0.0_f64.to_bits());
+
+        assert!(Gurgle::compile("3d6")
+            .unwrap()
+            .success_probability()
+            .is_none());
+        let config = Config::default().max_roll_times(200);
+        assert!(
+            Gurgle::compile_with_config("100d1000*100d1000 > 50000", &config)
+                .unwrap()
+                .success_probability()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_advantage() {
+        let adv = Gurgle::compile("1d20adv+5").unwrap();
+        let result = adv.roll_with_rng(&mut Sequence(vec![18, 7].into_iter()));
+        assert_eq!(result.value(), 23);
+        let dis = Gurgle::compile("d20dis").unwrap();
+        let result = dis.roll_with_rng(&mut Sequence(vec![18, 7].into_iter()));
+        assert_eq!(result.value(), 7);
+        assert_eq!(dis.expr(), Gurgle::compile("1d20dis").unwrap().expr());
+
+        assert_eq!(adv.to_canonical_ast(), "1d20adv+5");
+        assert_eq!(adv.to_string(), "1d20adv+5");
+        assert_eq!((adv.min_result(), adv.max_result()), (6, 25));
+        let two = Gurgle::compile("2d6adv").unwrap();
+        let result = two.roll_with_rng(&mut Sequence(vec![1, 6, 5, 2].into_iter()));
+        assert_eq!(result.value(), 11);
+        let dist = Gurgle::compile("1d20adv").unwrap().distribution().unwrap();
+        assert!((dist[&20] - 39.0 / 400.0).abs() < 1e-9);
+        let dist = Gurgle::compile("1d20dis").unwrap().distribution().unwrap();
+        assert!((dist[&20] - 1.0 / 400.0).abs() < 1e-9);
+
+        let config = Config::default().max_roll_times(3);
+        assert!(Gurgle::compile_with_config("1d20adv", &config).is_ok());
+        assert_eq!(
+            Gurgle::compile_with_config("2d20adv", &config).unwrap_err(),
+            CompileError::DiceRollTimesLimitExceeded
+        );
+        assert_eq!(
+            Gurgle::compile("(1d4)d20adv").unwrap_err(),
+            CompileError::AdvantageRollTimesNotFixed
+        );
+
+        #[cfg(feature = "detail")]
+        {
+            let result = adv.roll_with_rng(&mut Sequence(vec![18, 7].into_iter()));
+            assert_eq!(result.to_string(), "(Adv[18,~7~]=18) + 5 = 23");
+            assert_eq!(
+                result.steps(),
+                vec!["1d20adv: rolled 18,~7~ = 18", "18 + 5 = 23"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_advantage_pairs() {
+        let two = Gurgle::compile("2d6adv").unwrap();
+        // every die is paired with the next one, not the highest half of all points
+        let result = two.roll_with_rng(&mut Sequence(vec![6, 5, 1, 2].into_iter()));
+        assert_eq!(result.value(), 8);
+        let dice = result.dice_rolls().next().unwrap();
+        assert_eq!(dice.kept(), [true, false, false, true]);
+        let mut arena = RollArena::new();
+        let value = two
+            .expr()
+            .value_in(&mut Sequence(vec![6, 5, 1, 2].into_iter()), &mut arena);
+        assert_eq!(value, 8);
+        assert_eq!((two.min_result(), two.max_result()), (2, 12));
+        let two_dis = Gurgle::compile("2d6dis").unwrap();
+        let result = two_dis.roll_with_rng(&mut Sequence(vec![6, 5, 1, 2].into_iter()));
+        assert_eq!(result.value(), 6);
+
+        // extra dice of explosion belong to the die which explodes
+        let exploding = Gurgle::compile("1d6!adv").unwrap();
+        let result = exploding.roll_with_rng(&mut Sequence(vec![6, 3, 2].into_iter()));
+        assert_eq!(result.value(), 9);
+        let dice = result.dice_rolls().next().unwrap();
+        assert_eq!(dice.kept(), [true, true, false]);
+        let value = exploding
+            .expr()
+            .value_in(&mut Sequence(vec![2, 6, 3].into_iter()), &mut arena);
+        assert_eq!(value, 9);
+        let result = exploding.roll_with_rng(&mut Sequence(vec![6, 3, 6, 4].into_iter()));
+        assert_eq!(result.value(), 10);
+        let dist = exploding.distribution().unwrap();
+        assert!((dist.values().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!((dist[&1] - 1.0 / 36.0).abs() < 1e-9);
+        let dist = two.distribution().unwrap();
+        assert!((dist[&2] - 1.0 / 1296.0).abs() < 1e-9);
+        assert_eq!(
+            Gurgle::compile("1d6!padv")
+                .unwrap()
+                .roll_with_rng(&mut Sequence(vec![6, 3, 5].into_iter()))
+                .value(),
+            8
+        );
+
+        #[cfg(feature = "detail")]
+        {
+            let result = exploding.roll_with_rng(&mut Sequence(vec![6, 3, 2].into_iter()));
+            assert_eq!(result.to_string(), "(Adv[6!,3,~2~]=9) = 9");
+            assert_eq!(result.steps(), vec!["1d6!adv: rolled 6!,3,~2~ = 9"]);
+        }
+    }
+
+    #[test]
+    fn test_reroll_in_place() {
+        use nanorand::WyRand;
+
+        let points = |roll: &GurgleRoll<'_>| {
+            roll.dice_rolls()
+                .map(|d| d.points().to_vec())
+                .collect::<Vec<_>>()
+        };
+        let gurgle =
+            Gurgle::compile("(1d4)d6r1!+3#(1d8ro2) [hit] - 2d10kh1*2 >= 15 @seed=1").unwrap();
+        let mut roll = gurgle.roll_with_rng(&mut WyRand::new_seed(0));
+        for seed in 1..50 {
+            roll.reroll_with_rng(&mut WyRand::new_seed(seed));
+            let fresh = gurgle.roll_with_rng(&mut WyRand::new_seed(seed));
+            assert_eq!(roll.value(), fresh.value(), "seed {}", seed);
+            assert_eq!(roll.success(), fresh.success());
+            assert_eq!(points(&roll), points(&fresh));
+            #[cfg(feature = "detail")]
+            assert_eq!(roll.to_string(), fresh.to_string());
+        }
+
+        let number = Gurgle::compile("1d1+2").unwrap();
+        let mut buffed = number.roll_with_modifier(|x| x + 2);
+        assert_eq!(buffed.value(), 5);
+        buffed.reroll();
+        assert_eq!((buffed.value(), buffed.modifier_delta()), (3, 0));
+    }
+
+    #[test]
+    fn test_roll_summary() {
+        let gurgle = Gurgle::compile("(1d4)d6+2#(1d8+1d1) [x] - 3dF >= 100").unwrap();
+        let rolls = vec![2, 3, 5, 4, 1, 7, 1, 1, 2, 3];
+        let result = gurgle.roll_with_rng(&mut Sequence(rolls.into_iter()));
+        let summary = result.summary();
+        assert_eq!(summary.rounds, 7);
+        assert_eq!(summary.dice, 10);
+        assert_eq!(
+            summary.dice_by_sided.into_iter().collect::<Vec<_>>(),
+            [(1, 2), (4, 1), (6, 2), (8, 2)]
+        );
+        assert_eq!(summary.fudge_dice, 3);
+        assert_eq!(summary.points_sum, 23);
+        assert_eq!(summary.parentheses, 3);
+        assert_eq!(summary.rerolled_dice, 0);
+        assert_eq!(result.expr().summary(), result.summary());
+
+        let exploding = Gurgle::compile("2d6!kh1").unwrap();
+        let result = exploding.roll_with_rng(&mut Sequence(vec![6, 2, 3].into_iter()));
+        let summary = result.summary();
+        assert_eq!((summary.dice, summary.points_sum), (3, 11));
+        assert_eq!(result.value(), 6);
+
+        let reroll = Gurgle::compile("3d6r1").unwrap();
+        let result = reroll.roll_with_rng(&mut Sequence(vec![1, 1, 4, 2, 1, 6].into_iter()));
+        let summary = result.summary();
+        assert_eq!((summary.dice, summary.rerolled_dice), (3, 3));
+        assert_eq!(summary.points_sum, 12);
+
+        let number = Gurgle::compile("1+2").unwrap();
+        assert_eq!(number.roll().summary(), crate::roll::RollSummary::default());
+    }
+
+    #[test]
+    fn test_clamp() {
+        let floor = Gurgle::compile("4d6mi2").unwrap();
+        let result = floor.roll_with_rng(&mut Sequence(vec![1, 3, 6, 1].into_iter()));
+        let dice = result.dice_rolls().next().unwrap();
+        assert_eq!(dice.points(), &[2, 3, 6, 2]);
+        assert_eq!(dice.clamped(), &[Some(1), None, None, Some(1)]);
+        assert_eq!(result.value(), 13);
+        #[cfg(feature = "detail")]
+        assert_eq!(result.to_string(), "(1→2+3+6+1→2) = 13");
+        assert_eq!((floor.min_result(), floor.max_result()), (8, 24));
+
+        let ceil = Gurgle::compile("4d6ma5kh3").unwrap();
+        let result = ceil.roll_with_rng(&mut Sequence(vec![6, 6, 1, 2].into_iter()));
+        assert_eq!(result.value(), 12);
+        assert_eq!(ceil.to_canonical_ast(), "4d6ma5kh3");
+        assert_eq!(
+            Gurgle::compile("1d6mi2ma5").unwrap().to_string(),
+            "1d6mi2ma5"
+        );
+        assert_eq!(Gurgle::compile("2d6mi3max").unwrap().min_result(), 3);
+        assert!(Gurgle::compile("3d6mi6")
+            .unwrap()
+            .roll_n(5)
+            .all(|v| v == 18));
+
+        let dist = Gurgle::compile("1d6mi2ma5")
+            .unwrap()
+            .distribution()
+            .unwrap();
+        assert_eq!(dist.keys().copied().collect::<Vec<_>>(), [2, 3, 4, 5]);
+        assert!((dist[&2] - 2.0 / 6.0).abs() < 1e-9);
+        assert!(Gurgle::compile("1d6!mi2").unwrap().distribution().is_none());
+    }
+
+    #[test]
+    fn test_clamp_syntax() {
+        for command in &["1d6mi7", "1d6ma0", "1d6mi4ma3"] {
+            assert_eq!(
+                Gurgle::compile(command).unwrap_err(),
+                CompileError::ClampOutOfRange,
+                "{}",
+                command
+            );
+        }
+        assert!(Gurgle::compile("1d6ma5mi2").is_err());
+        assert_eq!(
+            Gurgle::compile("4d6min").unwrap().expr(),
+            &AstTreeNode::Leaf(Item::Dice(Dice::new_with_pp(
+                4,
+                6,
+                crate::expr::PostProcessor::Min
+            )))
+        );
+    }
+
+    #[test]
+    fn test_compound_checker() {
+        use crate::checker::Compare;
+
+        let both = Gurgle::compile("1d20 >= 15 and != 17").unwrap();
+        assert_eq!(
+            both.checker(),
+            Some(&Checker::And(
+                Box::new(Checker::Compare {
+                    compare: Compare::Gte,
+                    target: 15,
+                    tolerance: 0,
+                }),
+                Box::new(Checker::Compare {
+                    compare: Compare::Ne,
+                    target: 17,
+                    tolerance: 0,
+                }),
+            ))
+        );
+        assert_eq!(both.to_string(), "1d20>=15 and !=17");
+        assert_eq!(Gurgle::compile(&both.to_string()).unwrap(), both);
+        let neither = Gurgle::compile("1d20 != 5 and != 17").unwrap();
+        assert_eq!(neither.to_string(), "1d20 !=5 and !=17");
+        assert_eq!(Gurgle::compile(&neither.to_string()).unwrap(), neither);
+        assert!((both.success_probability().unwrap() - 0.25).abs() < 1e-9);
+
+        let any = Gurgle::compile("1d20 < 5 or > 15").unwrap();
+        assert_eq!(any.to_string(), "1d20<5 or >15");
+        assert!((any.success_probability().unwrap() - 0.45).abs() < 1e-9);
+
+        // `and` binds tighter than `or`
+        let mixed = Gurgle::compile("1d20 even or >= 15 and != 17").unwrap();
+        let checker = mixed.checker().unwrap();
+        assert!(matches!(checker, Checker::Or(_, right) if matches!(**right, Checker::And(..))));
+        assert_eq!(mixed.to_string(), "1d20 even or >=15 and !=17");
+        assert!(checker.check(2));
+        assert!(checker.check(15));
+        assert!(!checker.check(17));
+        assert!(!checker.check(3));
+
+        assert!(Gurgle::compile("1d20 >= 15 and").is_err());
+        assert!(Gurgle::compile("1d20 or >= 15").is_err());
+    }
+
+    #[test]
+    fn test_compound_checker_roll() {
+        let roll = |command: &str, point: u64| {
+            let gurgle = Gurgle::compile(command).unwrap();
+            let result = gurgle.roll_with_rng(&mut Sequence(vec![point].into_iter()));
+            (
+                result.success().unwrap(),
+                result.margin(),
+                result.shortfall(),
+            )
+        };
+
+        assert_eq!(roll("1d20 >= 15 and != 17", 16), (true, None, None));
+        assert_eq!(roll("1d20 >= 15 and != 17", 17), (false, None, Some(1)));
+        assert_eq!(roll("1d20 >= 15 and != 17", 12), (false, None, Some(3)));
+        assert_eq!(roll("1d20 >= 15 and != 15", 12), (false, None, Some(4)));
+        assert_eq!(
+            roll("1d20 even and > 17 or < 3", 17),
+            (false, None, Some(1))
+        );
+        assert_eq!(roll("1d20 even and > 19", 17), (false, None, Some(3)));
+        assert_eq!(roll("1d20 even and odd", 17), (false, None, None));
+        assert_eq!(roll("1d20 < 5 or > 15", 7), (false, None, Some(-3)));
+        assert_eq!(roll("1d20 < 5 or > 15", 14), (false, None, Some(2)));
+        // no value passes both sides
+        assert_eq!(roll("1d20 <= 5 and >= 10", 7), (false, None, None));
+
+        #[cfg(feature = "detail")]
+        {
+            let gurgle = Gurgle::compile("1d20 >= 15 and =~1 10").unwrap();
+            let result = gurgle.roll_with_rng(&mut Sequence(vec![12].into_iter()));
+            assert_eq!(
+                result.steps(),
+                vec!["1d20: rolled 12 = 12", "12 >= 15 and = 10 ±1: failed"]
+            );
+        }
     }
 }
@@ -0,0 +1,115 @@
+//! random number source of dice rolling
+
+use crate::roll::DiceRoll;
+
+/// Random number source used when rolling dice
+///
+/// Every `nanorand::Rng` is a `RollRng`, so a seeded `nanorand::WyRand` can be used for reproducible results.
+pub trait RollRng {
+    /// Get a random point in `1..=sided`
+    fn roll_die(&mut self, sided: u64) -> u64;
+
+    /// Called once for every dice round after it's fully rolled
+    fn round_rolled(&mut self, _roll: &DiceRoll) {}
+}
+
+/// Random number source of a command, seeded by its seed if given, not boxed so rolling does not allocate
+#[cfg(feature = "std")]
+pub enum CommandRng {
+    /// seed is given in command
+    Seeded(nanorand::WyRand),
+    /// thread local random source, used when no seed is given
+    Thread(nanorand::tls::TlsWyRand),
+    /// global seeded random source of tests, see `test_support::seed_global`
+    #[cfg(feature = "test-util")]
+    Global(crate::test_support::GlobalRng),
+}
+
+#[cfg(feature = "std")]
+impl CommandRng {
+    /// Random number source of a command with `seed`
+    pub fn new(seed: Option<u64>) -> Self {
+        seed.map_or_else(Self::default_source, |seed| {
+            Self::Seeded(nanorand::WyRand::new_seed(seed))
+        })
+    }
+
+    /// Seed of continuing a seeded random source after what it has rolled, without changing it
+    pub fn next_seed(&self) -> Option<u64> {
+        match self {
+            Self::Seeded(rng) => Some(nanorand::Rng::generate(&mut rng.clone())),
+            _ => None,
+        }
+    }
+
+    /// Random number source used when no seed is given
+    pub fn default_source() -> Self {
+        #[cfg(feature = "test-util")]
+        if crate::test_support::is_seeded() {
+            return Self::Global(crate::test_support::GlobalRng);
+        }
+        Self::Thread(nanorand::tls_rng())
+    }
+}
+
+#[cfg(feature = "std")]
+impl RollRng for CommandRng {
+    fn roll_die(&mut self, sided: u64) -> u64 {
+        match self {
+            Self::Seeded(rng) => rng.roll_die(sided),
+            Self::Thread(rng) => rng.roll_die(sided),
+            #[cfg(feature = "test-util")]
+            Self::Global(rng) => rng.roll_die(sided),
+        }
+    }
+}
+
+/// Random number source which gives points in order, for tests
+#[cfg(all(test, feature = "std"))]
+pub struct Sequence(pub alloc::vec::IntoIter<u64>);
+
+#[cfg(all(test, feature = "std"))]
+impl RollRng for Sequence {
+    fn roll_die(&mut self, sided: u64) -> u64 {
+        let point = self.0.next().unwrap();
+        assert!(
+            (1..=sided).contains(&point),
+            "point {} of d{}",
+            point,
+            sided
+        );
+        point
+    }
+}
+
+impl<R: nanorand::Rng> RollRng for R {
+    fn roll_die(&mut self, sided: u64) -> u64 {
+        self.generate_range(1..=sided)
+    }
+}
+
+/// Random number source wrapper which reports every rolled dice round to a callback
+#[cfg(feature = "std")]
+pub struct RoundObserved<'r, R: ?Sized, F> {
+    rng: &'r mut R,
+    f: F,
+}
+
+#[cfg(feature = "std")]
+impl<'r, R: RollRng + ?Sized, F: FnMut(&DiceRoll)> RoundObserved<'r, R, F> {
+    pub const fn new(rng: &'r mut R, f: F) -> Self {
+        Self { rng, f }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: RollRng + ?Sized, F: FnMut(&DiceRoll)> RollRng for RoundObserved<'_, R, F> {
+    fn roll_die(&mut self, sided: u64) -> u64 {
+        self.rng.roll_die(sided)
+    }
+
+    fn round_rolled(&mut self, roll: &DiceRoll) {
+        self.rng.round_rolled(roll);
+        (self.f)(roll);
+    }
+}
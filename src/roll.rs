@@ -1,45 +1,160 @@
 //! rolling result
 
-use std::sync::atomic::{AtomicPtr, Ordering};
+use alloc::{borrow::ToOwned, boxed::Box, collections::BTreeMap, string::String, vec, vec::Vec};
+use core::{
+    convert::TryFrom,
+    iter::FromIterator,
+    ops::{Range, RangeInclusive},
+};
+
+// value caches can't be shared between threads without std
+#[cfg(not(feature = "std"))]
+use core::cell::OnceCell;
+#[cfg(feature = "std")]
+use once_cell::sync::OnceCell;
 
 use crate::{
-    checker::Checker,
-    expr::{Operator, PostProcessor},
+    checker::{Checker, TieredChecker},
+    error::RollError,
+    expr::{AstTreeNode, Clamp, ExplodeMode, Operator, PostProcessor},
+    rng::RollRng,
     tree::{BinaryTree, BinaryTreeNode},
 };
+#[cfg(feature = "std")]
+use crate::{expr::roll_face, rng};
+
+/// Reusable buffers for rolling many times and only getting values, see [`Gurgle::roll_in`]
+///
+/// [`Gurgle::roll_in`]: ../struct.Gurgle.html#method.roll_in
+#[derive(Debug, Default)]
+pub struct RollArena {
+    pub(crate) points: Vec<u64>,
+    pub(crate) exploded: Vec<bool>,
+    pub(crate) discarded: Vec<u64>,
+    sorted: Vec<u64>,
+}
+
+impl RollArena {
+    /// Create a new arena with empty buffers, they grow when rolling
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get value of points in points buffer with post processor `pp`, rolled by a die which explodes in `explode` mode
+    pub(crate) fn points_value(
+        &mut self, pp: PostProcessor, explode: Option<ExplodeMode>, fudge: bool,
+    ) -> i64 {
+        let round = Round {
+            points: &self.points,
+            exploded: &self.exploded,
+            explode,
+        };
+        round.value(pp, fudge, &mut self.sorted)
+    }
+}
 
-// Safety:
-// 1. You should only change `cache` value by calling this method
-unsafe fn cache_it<T, F>(cache: &AtomicPtr<T>, f: F) -> T
-where
-    T: Copy,
-    F: FnOnce() -> T,
-{
-    let x = cache.load(Ordering::SeqCst);
-    if x.is_null() {
-        let value = f();
-        let p = Box::into_raw(Box::new(value));
-        match cache.compare_exchange(
-            std::ptr::null::<T>() as *mut T,
-            p,
-            Ordering::SeqCst,
-            Ordering::SeqCst,
-        ) {
-            // a success exchange, return value is a null ptr, so no need to deallocate
-            Ok(_) => {}
-            // Safety:
-            // Because of function safety requirement,
-            // cache value is stored only in this method, by `Box::into_raw`, so the ptr is valid
-            Err(p) => drop(Box::from_raw(p)),
-        }
-        value
-    } else {
-        // Safety:
-        // Because of function safety requirement,
-        // cache value is stored only in this method, by `Box::into_raw`, so ptr is valid.
-        // And if cache has a value, it will not change again, so gotten value is alive(until cache itself be dropped),
-        // so it's ok to dereference it.
-        *x
+/// Points of a round, with explosion marks of them to know which die every point belongs to
+struct Round<'a> {
+    points: &'a [u64],
+    exploded: &'a [bool],
+    explode: Option<ExplodeMode>,
+}
+
+impl Round<'_> {
+    /// Get point ranges of kept dice of an advantage round, every two dice are a pair and one of them is kept
+    ///
+    /// Extra points of standard and penetrate explosion belong to the die which explodes.
+    /// The higher die is kept if `higher`, otherwise the lower one, the left one is kept if they are equal.
+    fn advantage_kept(&self, higher: bool) -> impl Iterator<Item = Range<usize>> + '_ {
+        let chained = core::matches!(
+            self.explode,
+            Some(ExplodeMode::Standard | ExplodeMode::Penetrate)
+        );
+        let mut ends = (0..self.points.len())
+            .filter(move |i| !chained || !self.exploded[*i])
+            .map(|i| i + 1);
+        let mut start = 0;
+        core::iter::from_fn(move || {
+            let first = start..ends.next()?;
+            let second = first.end..ends.next().unwrap_or(first.end);
+            start = second.end;
+            let (x, y) = (self.sum(&first), self.sum(&second));
+            Some(if (higher && x >= y) || (!higher && x <= y) {
+                first
+            } else {
+                second
+            })
+        })
+    }
+
+    fn sum(&self, range: &Range<usize>) -> u64 {
+        self.points[range.clone()].iter().sum()
+    }
+
+    /// Get value of this round with post processor `pp`, `sorted` is a buffer for selections like `kh3`
+    ///
+    /// An empty round has value zero whatever `pp` is.
+    #[allow(clippy::cast_possible_wrap)] // because limit checked roll times is small
+    fn value(&self, pp: PostProcessor, fudge: bool, sorted: &mut Vec<u64>) -> i64 {
+        let points = self.points;
+        let len = points.len() as u64;
+        let sum = || points.iter().sum::<u64>();
+        let value = match pp {
+            PostProcessor::Sum => sum(),
+            PostProcessor::Avg => sum().checked_div(len).unwrap_or_default(),
+            PostProcessor::Max => points.iter().max().copied().unwrap_or_default(),
+            PostProcessor::Min => points.iter().min().copied().unwrap_or_default(),
+            PostProcessor::ScaledAvg(factor) => sum()
+                .saturating_mul(factor)
+                .checked_div(len)
+                .unwrap_or_default(),
+            PostProcessor::KeepHighest(_)
+            | PostProcessor::KeepLowest(_)
+            | PostProcessor::DropHighest(_)
+            | PostProcessor::DropLowest(_) => {
+                sorted.clear();
+                sorted.extend_from_slice(points);
+                sorted.sort_unstable();
+                let selected = pp.selected(len).unwrap_or_default() as usize;
+                match pp {
+                    PostProcessor::KeepHighest(_) | PostProcessor::DropLowest(_) => {
+                        sorted[sorted.len() - selected..].iter().sum()
+                    }
+                    _ => sorted[..selected].iter().sum(),
+                }
+            }
+            PostProcessor::Advantage | PostProcessor::Disadvantage => self
+                .advantage_kept(pp == PostProcessor::Advantage)
+                .map(|r| self.sum(&r))
+                .sum(),
+            PostProcessor::CountSuccesses { .. } => {
+                points.iter().filter(|p| pp.succeeds(**p)).count() as u64
+            }
+        };
+        let value = i64::try_from(value).unwrap_or(i64::MAX);
+        if fudge {
+            // every point is 2 more than its fudge face
+            value - len as i64 * 2
+        } else {
+            value
+        }
+    }
+}
+
+/// Aggregate `count` values of a repeated item, like `3#1d20 max`, zero if there is no value
+#[allow(clippy::cast_possible_wrap)] // because limit checked times is small
+pub(crate) fn aggregate_values<I: Iterator<Item = i64>>(
+    aggregate: PostProcessor, values: I, count: u64,
+) -> i64 {
+    match aggregate {
+        PostProcessor::Avg => values
+            .fold(0, i64::saturating_add)
+            .checked_div_euclid(count as i64)
+            .unwrap_or_default(),
+        PostProcessor::Max => values.max().unwrap_or_default(),
+        PostProcessor::Min => values.min().unwrap_or_default(),
+        _ => values.fold(0, i64::saturating_add),
     }
 }
 
@@ -47,33 +162,246 @@ where
 ///
 /// [`Dice`]: ../struct.Dice.html
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct DiceRoll {
+    times: Option<Box<RollTreeNode>>,
     points: Vec<u64>,
+    sided: u64,
     pp: PostProcessor,
-    cache: AtomicPtr<u64>,
+    explode: Option<ExplodeMode>,
+    exploded: Vec<bool>,
+    rerolled: Vec<Vec<u64>>,
+    clamped: Vec<Option<u64>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    clamp: Option<Clamp>,
+    fudge: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    weights: Option<Vec<u32>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cache: OnceCell<i64>,
 }
 
 impl DiceRoll {
-    pub(crate) fn new(points: Vec<u64>, pp: PostProcessor) -> Self {
+    pub(crate) fn new(
+        points: Vec<u64>, sided: u64, pp: PostProcessor, times: Option<Box<RollTreeNode>>,
+    ) -> Self {
         Self {
+            times,
+            exploded: vec![false; points.len()],
+            rerolled: vec![Vec::new(); points.len()],
+            clamped: vec![None; points.len()],
+            clamp: None,
             points,
+            sided,
             pp,
-            cache: AtomicPtr::default(),
+            explode: None,
+            fudge: false,
+            weights: None,
+            cache: OnceCell::new(),
+        }
+    }
+
+    pub(crate) const fn with_fudge(mut self, fudge: bool) -> Self {
+        self.fudge = fudge;
+        self
+    }
+
+    /// Set face weights of the loaded dice which is rolled, so rolling again respects them
+    pub(crate) fn with_weights(mut self, weights: Option<Vec<u32>>) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Clear points of this round to roll it again, returns buffers of points, explosion marks and rerolled points
+    pub(crate) fn clear_points(
+        &mut self, explode: Option<ExplodeMode>,
+    ) -> (&mut Vec<u64>, &mut Vec<bool>, &mut Vec<Vec<u64>>) {
+        self.explode = explode;
+        self.cache = OnceCell::new();
+        self.points.clear();
+        self.exploded.clear();
+        self.rerolled.clear();
+        self.clamped.clear();
+        (&mut self.points, &mut self.exploded, &mut self.rerolled)
+    }
+
+    /// Clamp every point by `clamp`, and remember original points
+    pub(crate) fn apply_clamp(&mut self, clamp: Option<Clamp>) {
+        self.clamp = clamp;
+        self.clamped.clear();
+        for point in &mut self.points {
+            let original = *point;
+            *point = clamp.map_or(original, |c| c.apply(original));
+            self.clamped.push(if *point == original {
+                None
+            } else {
+                Some(original)
+            });
         }
     }
 
+    pub(crate) fn times_roll_mut(&mut self) -> Option<&mut RollTreeNode> {
+        self.times.as_deref_mut()
+    }
+
+    /// Get rolling result of roll times expression, if this round's roll times is not fixed
+    #[must_use]
+    pub fn times_roll(&self) -> Option<&RollTreeNode> {
+        self.times.as_deref()
+    }
+
+    /// Get sided count of rolled dice
+    #[must_use]
+    pub const fn sided(&self) -> u64 {
+        self.sided
+    }
+
     /// Get post processor
     #[must_use]
     pub const fn post_processor(&self) -> PostProcessor {
         self.pp
     }
 
+    /// Check if dice of this round are fudge dice, whose point 1, 2 and 3 mean -1, 0 and +1
+    #[must_use]
+    pub const fn is_fudge(&self) -> bool {
+        self.fudge
+    }
+
+    /// Get explode mode, `None` if dice of this round do not explode
+    #[must_use]
+    pub const fn explode_mode(&self) -> Option<ExplodeMode> {
+        self.explode
+    }
+
     /// Get rolling dice output points
+    ///
+    /// For exploding dice, extra dice are new points after the point which explodes,
+    /// except [`Compound`] mode, which add them to the same point.
+    ///
+    /// [`Compound`]: ../expr/enum.ExplodeMode.html#variant.Compound
     #[must_use]
     pub fn points(&self) -> &[u64] {
         &self.points
     }
 
+    /// Get a sorted copy of points, like `[1, 2, 3, 6]` for `4d6` rolled `3, 1, 6, 2` in [`Ascending`] order
+    ///
+    /// Equal points keep their rolling order. It's only for showing, [`points`] and [`value`] are not changed.
+    ///
+    /// [`Ascending`]: enum.SortOrder.html#variant.Ascending
+    /// [`points`]: #method.points
+    /// [`value`]: #method.value
+    #[must_use]
+    pub fn sorted_points(&self, order: SortOrder) -> Vec<u64> {
+        self.sorted_indices(order)
+            .into_iter()
+            .map(|i| self.points[i])
+            .collect()
+    }
+
+    pub(crate) fn sorted_indices(&self, order: SortOrder) -> Vec<usize> {
+        let mut indices = (0..self.points.len()).collect::<Vec<_>>();
+        match order {
+            SortOrder::Ascending => indices.sort_by_key(|i| self.points[*i]),
+            SortOrder::Descending => indices.sort_by(|a, b| self.points[*b].cmp(&self.points[*a])),
+        }
+        indices
+    }
+
+    /// Check if every point is kept by selection post processor like `kh3`, has the same length as [`points`]
+    ///
+    /// For advantage and disadvantage, all points of the kept die in every pair are kept, including extra points
+    /// added by explode. All points are kept for other post processors. Among equal points, the left one is
+    /// selected first.
+    ///
+    /// [`points`]: #method.points
+    #[must_use]
+    pub fn kept(&self) -> Vec<bool> {
+        if self.pp.is_advantage() {
+            let mut kept = vec![false; self.points.len()];
+            for range in self
+                .round()
+                .advantage_kept(self.pp == PostProcessor::Advantage)
+            {
+                kept[range].iter_mut().for_each(|k| *k = true);
+            }
+            return kept;
+        }
+        let len = self.points.len() as u64;
+        let Some(selected) = self.pp.selected(len) else {
+            return vec![true; self.points.len()];
+        };
+        let (highest, count) = match self.pp {
+            PostProcessor::KeepHighest(_) | PostProcessor::DropLowest(_) => (true, selected),
+            _ => (false, selected),
+        };
+        let mut order = (0..self.points.len()).collect::<Vec<_>>();
+        if highest {
+            order.sort_by(|a, b| self.points[*b].cmp(&self.points[*a]));
+        } else {
+            order.sort_by_key(|i| self.points[*i]);
+        }
+        let mut kept = vec![false; self.points.len()];
+        for i in order.into_iter().take(count as usize) {
+            kept[i] = true;
+        }
+        kept
+    }
+
+    /// Check if every point counts as a success, has the same length as [`points`]
+    ///
+    /// Only a dice pool like `5d10f8` has successes, all points are not successes for other post processors.
+    ///
+    /// [`points`]: #method.points
+    #[must_use]
+    pub fn successes(&self) -> Vec<bool> {
+        self.points.iter().map(|p| self.pp.succeeds(*p)).collect()
+    }
+
+    /// Check if every point explodes, has the same length as [`points`]
+    ///
+    /// In [`Compound`] mode, it means the point contains extra dice.
+    ///
+    /// [`points`]: #method.points
+    /// [`Compound`]: ../expr/enum.ExplodeMode.html#variant.Compound
+    #[must_use]
+    pub fn exploded(&self) -> &[bool] {
+        &self.exploded
+    }
+
+    /// Get points discarded by reroll before every point, has the same length as [`points`]
+    ///
+    /// For `4d6r1`, a die rolled 1 and then 5 has point 5, and `[1]` as discarded points.
+    ///
+    /// [`points`]: #method.points
+    #[must_use]
+    pub fn rerolled(&self) -> &[Vec<u64>] {
+        &self.rerolled
+    }
+
+    /// Get original point of every point changed by clamp, has the same length as [`points`]
+    ///
+    /// For `4d6mi2`, a die rolled 1 has point 2, and `Some(1)` as original point. It's `None` if not changed.
+    ///
+    /// [`points`]: #method.points
+    #[must_use]
+    pub fn clamped(&self) -> &[Option<u64>] {
+        &self.clamped
+    }
+
+    /// Get how many dice are rolled in this round, not counting extra dice of explosions
+    #[must_use]
+    pub fn rolled_times(&self) -> usize {
+        match self.explode {
+            Some(ExplodeMode::Standard | ExplodeMode::Penetrate) => {
+                // every explosion generates exactly one extra point
+                self.points.len() - self.exploded.iter().filter(|e| **e).count()
+            }
+            Some(ExplodeMode::Compound) | None => self.points.len(),
+        }
+    }
+
     /// Get points count(rolling dice times)
     #[allow(clippy::len_without_is_empty)] // because it can't be empty
     #[must_use]
@@ -81,28 +409,251 @@ impl DiceRoll {
         self.points.len()
     }
 
-    #[allow(clippy::missing_panics_doc)] // because this can't panic
+    /// Count how many dice got a point greater than or equal to `threshold`
+    ///
+    /// Like hits in Shadowrun, where `threshold` is 5.
+    #[must_use]
+    pub fn hits(&self, threshold: u64) -> usize {
+        self.points.iter().filter(|p| **p >= threshold).count()
+    }
+
+    /// Check if this roll is a glitch, that is, half or more of dice got point 1
+    #[must_use]
+    pub fn is_glitch(&self) -> bool {
+        let ones = self.points.iter().filter(|p| **p == 1).count();
+        !self.points.is_empty() && ones * 2 >= self.points.len()
+    }
+
+    /// Check if this roll is a critical glitch, that is, a [glitch] with no [hits]
+    ///
+    /// [glitch]: #method.is_glitch
+    /// [hits]: #method.hits
+    #[must_use]
+    pub fn is_critical_glitch(&self, threshold: u64) -> bool {
+        self.is_glitch() && self.hits(threshold) == 0
+    }
+
+    /// Get the glitch of a success counting pool, like `6d6f5`, `None` if it's not a glitch or not such a pool
+    ///
+    /// Hits are the successes counted by the pool, which is also the value a checker like `6d6f5 >= 2` checks.
+    /// See [`is_glitch`] and [`is_critical_glitch`] for the rules.
+    ///
+    /// [`is_glitch`]: #method.is_glitch
+    /// [`is_critical_glitch`]: #method.is_critical_glitch
+    #[must_use]
+    pub fn glitch(&self) -> Option<Glitch> {
+        if !core::matches!(self.pp, PostProcessor::CountSuccesses { .. }) || !self.is_glitch() {
+            None
+        } else if self.points.iter().any(|p| self.pp.succeeds(*p)) {
+            Some(Glitch::Glitch)
+        } else {
+            Some(Glitch::Critical)
+        }
+    }
+
+    /// Check if every point is a natural max, that is, the die shows its max face, has the same length as [`points`]
+    ///
+    /// A point which explodes is a natural max too, even if extra dice are added to it in [`Compound`] mode,
+    /// or minus 1 in [`Penetrate`] mode. For fudge dice, max face is +1.
+    ///
+    /// [`points`]: #method.points
+    /// [`Compound`]: ../expr/enum.ExplodeMode.html#variant.Compound
+    /// [`Penetrate`]: ../expr/enum.ExplodeMode.html#variant.Penetrate
+    #[must_use]
+    pub fn natural_max(&self) -> Vec<bool> {
+        self.points
+            .iter()
+            .zip(&self.exploded)
+            .map(|(p, e)| *e || *p >= self.sided)
+            .collect()
+    }
+
+    /// Check if every point is a natural min, that is, the die shows face 1, has the same length as [`points`]
+    ///
+    /// An extra die of [`Penetrate`] mode has point minus 1, so it is a natural min only at point 0.
+    /// For fudge dice, min face is -1.
+    ///
+    /// [`points`]: #method.points
+    /// [`Penetrate`]: ../expr/enum.ExplodeMode.html#variant.Penetrate
+    #[must_use]
+    pub fn natural_min(&self) -> Vec<bool> {
+        let penetrate = self.explode == Some(ExplodeMode::Penetrate);
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                // in penetrate mode, point after an exploded one is an extra die, which has minus 1
+                let extra = penetrate && i > 0 && self.exploded[i - 1];
+                *p == u64::from(!extra)
+            })
+            .collect()
+    }
+
+    /// Check if every [kept] point is a [natural max], like rolling 20 for `1d20`, or kept 20 for `2d20kh1`
+    ///
+    /// [kept]: #method.kept
+    /// [natural max]: #method.natural_max
+    #[must_use]
+    pub fn is_all_max(&self) -> bool {
+        Self::all_kept(&self.kept(), &self.natural_max())
+    }
+
+    /// Check if every [kept] point is a [natural min], like rolling 1 for `1d20`, or kept 1 for `2d20kl1`
+    ///
+    /// [kept]: #method.kept
+    /// [natural min]: #method.natural_min
+    #[must_use]
+    pub fn is_all_min(&self) -> bool {
+        Self::all_kept(&self.kept(), &self.natural_min())
+    }
+
+    fn all_kept(kept: &[bool], natural: &[bool]) -> bool {
+        kept.iter().any(|k| *k) && kept.iter().zip(natural).all(|(k, n)| !*k || *n)
+    }
+
+    /// Get the critical of this round, see [`GurgleRoll::critical`]
+    ///
+    /// Returns `None` if neither all kept points are natural max nor natural min.
+    ///
+    /// [`GurgleRoll::critical`]: struct.GurgleRoll.html#method.critical
     #[must_use]
-    fn calculate_value(&self) -> u64 {
-        match self.pp {
-            PostProcessor::Sum => self.points.iter().sum(),
-            PostProcessor::Avg => self.points.iter().sum::<u64>() / self.points.len() as u64,
-            PostProcessor::Max => *self.points.iter().max().unwrap(),
-            PostProcessor::Min => *self.points.iter().min().unwrap(),
+    pub fn critical(&self) -> Option<Crit> {
+        if self.is_all_max() {
+            Some(Crit::Max)
+        } else if self.is_all_min() {
+            Some(Crit::Min)
+        } else {
+            None
+        }
+    }
+
+    fn round(&self) -> Round<'_> {
+        Round {
+            points: &self.points,
+            exploded: &self.exploded,
+            explode: self.explode,
         }
     }
 
     /// Get the final rolling result value, with post processor executed
-    pub fn value(&self) -> u64 {
-        // Safety: `cache` only used in `cache_it` function
-        unsafe { cache_it(&self.cache, || self.calculate_value()) }
+    ///
+    /// For fudge dice, it's the sum of -1, 0 and +1 faces, so it can be negative.
+    pub fn value(&self) -> i64 {
+        *self
+            .cache
+            .get_or_init(|| self.round().value(self.pp, self.fudge, &mut Vec::new()))
+    }
+
+    #[cfg(feature = "std")]
+    fn with_advantage<R: RollRng + ?Sized>(&self, adv: &mut Advantage<'_, R>) -> Self {
+        let times = self.times.as_ref().map(|t| {
+            // rounds in roll times expr are not addressable, because changing them changes roll times
+            let mut copy = Advantage {
+                remain: None,
+                rng: &mut *adv.rng,
+            };
+            Box::new(t.with_advantage(&mut copy))
+        });
+        let mut points = self.points.clone();
+        let mut clamped = self.clamped.clone();
+        if adv.take() {
+            let penetrate = self.explode == Some(ExplodeMode::Penetrate);
+            for (i, (point, original)) in points.iter_mut().zip(&mut clamped).enumerate() {
+                // new point is an extra die of penetrate explosion too, and clamped like the first roll,
+                // a point which explodes has the max value, so it's never replaced and explosion marks are kept
+                let face = roll_face(adv.rng, self.sided, self.weights.as_deref());
+                let face = if penetrate && i > 0 && self.exploded[i - 1] {
+                    face - 1
+                } else {
+                    face
+                };
+                let candidate = self.clamp.map_or(face, |c| c.apply(face));
+                if candidate > *point {
+                    *point = candidate;
+                    *original = Some(face).filter(|f| *f != candidate);
+                }
+            }
+        }
+        Self {
+            explode: self.explode,
+            exploded: self.exploded.clone(),
+            rerolled: self.rerolled.clone(),
+            clamped,
+            clamp: self.clamp,
+            fudge: self.fudge,
+            weights: self.weights.clone(),
+            ..Self::new(points, self.sided, self.pp, times)
+        }
+    }
+}
+
+/// State of applying advantage when walking through rolling result tree
+#[cfg(feature = "std")]
+struct Advantage<'r, R: ?Sized> {
+    /// how many addressable dice rounds remain before the target one, `None` if target is passed
+    remain: Option<usize>,
+    rng: &'r mut R,
+}
+
+#[cfg(feature = "std")]
+impl<R: ?Sized> Advantage<'_, R> {
+    /// Visit an addressable dice round, returns if it's the target
+    const fn take(&mut self) -> bool {
+        match self.remain {
+            Some(0) => {
+                self.remain = None;
+                true
+            }
+            Some(n) => {
+                self.remain = Some(n - 1);
+                false
+            }
+            None => false,
+        }
     }
 }
 
+/// Order of sorted dice points, see [`DiceRoll::sorted_points`]
+///
+/// [`DiceRoll::sorted_points`]: struct.DiceRoll.html#method.sorted_points
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortOrder {
+    /// smallest point first
+    Ascending,
+    /// largest point first
+    Descending,
+}
+
+/// Critical of a dice round, see [`GurgleRoll::critical`]
+///
+/// [`GurgleRoll::critical`]: struct.GurgleRoll.html#method.critical
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Crit {
+    /// every kept die shows its max face, like a natural 20
+    Max,
+    /// every kept die shows face 1, like a natural 1
+    Min,
+}
+
+/// Glitch of a success counting pool, see [`DiceRoll::glitch`]
+///
+/// [`DiceRoll::glitch`]: struct.DiceRoll.html#method.glitch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Glitch {
+    /// half or more dice show 1, but there is at least one hit
+    Glitch,
+    /// half or more dice show 1, and there is no hit
+    Critical,
+}
+
 /// Rolling result of a gurgle expression tree [`Item`]
 ///
 /// [`Item`]: ../ast/enum.Item.html
+#[allow(clippy::large_enum_variant)] // because dice is the most common item
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ItemRoll {
     /// rolling result of a dice item
     Dice(DiceRoll),
@@ -110,51 +661,426 @@ pub enum ItemRoll {
     Number(i64),
     /// rolling result of another sub expr, which is commonly wrapped by parentheses
     Parentheses(Box<RollTreeNode>),
+    /// rolling result of a repeated item
+    Repeat(RepeatRoll),
+    /// rolling result of a labeled item, with the label
+    Labeled(Box<Self>, String),
 }
 
 impl ItemRoll {
+    /// Try treat this item as a dice rolling result
+    #[must_use]
+    pub fn as_dice(&self) -> Option<&DiceRoll> {
+        match self.unlabeled() {
+            Self::Dice(dice) => Some(dice),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn with_advantage<R: RollRng + ?Sized>(&self, adv: &mut Advantage<'_, R>) -> Self {
+        match self {
+            Self::Dice(dice) => Self::Dice(dice.with_advantage(adv)),
+            Self::Number(x) => Self::Number(*x),
+            Self::Parentheses(e) => Self::Parentheses(Box::new(e.with_advantage(adv))),
+            Self::Repeat(r) => Self::Repeat(RepeatRoll::new(
+                r.rolls.iter().map(|x| x.with_advantage(adv)).collect(),
+                r.aggregate,
+            )),
+            Self::Labeled(item, label) => {
+                Self::Labeled(Box::new(item.with_advantage(adv)), label.clone())
+            }
+        }
+    }
+
     /// Get rolling result value
     #[must_use]
     pub fn value(&self) -> i64 {
         match self {
-            #[allow(clippy::cast_possible_wrap)] // because out number can't be so big
-            Self::Dice(dice) => dice.value() as i64,
+            Self::Dice(dice) => dice.value(),
             Self::Number(x) => *x,
             Self::Parentheses(e) => e.value(),
+            Self::Repeat(r) => r.value(),
+            Self::Labeled(item, _) => item.value(),
+        }
+    }
+
+    // checking magnitude of every operand and result, including ones in repeats and roll times expr
+    fn checked_value(&self, max: u64) -> Result<i64, RollError> {
+        let value = match self {
+            Self::Dice(dice) => {
+                if let Some(times) = dice.times_roll() {
+                    times.checked_value(max)?;
+                }
+                dice.value()
+            }
+            Self::Number(x) => *x,
+            Self::Parentheses(e) => e.checked_value(max)?,
+            Self::Repeat(r) => r.checked_value(max)?,
+            Self::Labeled(item, _) => item.checked_value(max)?,
+        };
+        if value.unsigned_abs() > max {
+            return Err(RollError::IntermediateMagnitudeLimitExceeded);
+        }
+        Ok(value)
+    }
+
+    /// Get label of this item, if it has one
+    #[must_use]
+    pub fn label(&self) -> Option<&str> {
+        match self {
+            Self::Labeled(_, label) => Some(label),
+            _ => None,
+        }
+    }
+
+    fn unlabeled(&self) -> &Self {
+        match self {
+            Self::Labeled(item, _) => item.unlabeled(),
+            _ => self,
+        }
+    }
+
+    /// Try treat this item as a repeat rolling result
+    #[must_use]
+    pub fn as_repeat(&self) -> Option<&RepeatRoll> {
+        match self.unlabeled() {
+            Self::Repeat(r) => Some(r),
+            _ => None,
+        }
+    }
+
+    fn has_dice(&self) -> bool {
+        match self {
+            Self::Dice(_) => true,
+            Self::Number(_) => false,
+            Self::Parentheses(e) => e.has_dice(),
+            Self::Repeat(r) => r.rolls.iter().any(Self::has_dice),
+            Self::Labeled(item, _) => item.has_dice(),
+        }
+    }
+}
+
+/// Rolling result of a repeated item, like `3#1d20 max`
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RepeatRoll {
+    rolls: Vec<ItemRoll>,
+    aggregate: PostProcessor,
+}
+
+impl RepeatRoll {
+    pub(crate) const fn new(rolls: Vec<ItemRoll>, aggregate: PostProcessor) -> Self {
+        Self { rolls, aggregate }
+    }
+
+    /// Get rolling result of every repetition
+    #[must_use]
+    pub fn rolls(&self) -> &[ItemRoll] {
+        &self.rolls
+    }
+
+    pub(crate) const fn rolls_mut(&mut self) -> &mut Vec<ItemRoll> {
+        &mut self.rolls
+    }
+
+    /// Get how results of repetitions are aggregated
+    #[must_use]
+    pub const fn aggregate(&self) -> PostProcessor {
+        self.aggregate
+    }
+
+    /// Get aggregated value of all repetitions
+    #[must_use]
+    pub fn value(&self) -> i64 {
+        aggregate_values(
+            self.aggregate,
+            self.rolls.iter().map(ItemRoll::value),
+            self.rolls.len() as u64,
+        )
+    }
+
+    #[allow(clippy::cast_possible_wrap)] // because limit checked times is small
+    fn checked_value(&self, max: u64) -> Result<i64, RollError> {
+        let values = self
+            .rolls
+            .iter()
+            .map(|x| x.checked_value(max))
+            .collect::<Result<Vec<_>, _>>()?;
+        let count = values.len() as u64;
+        if core::matches!(self.aggregate, PostProcessor::Max | PostProcessor::Min) {
+            return Ok(aggregate_values(self.aggregate, values.into_iter(), count));
+        }
+        let sum = values
+            .into_iter()
+            .try_fold(0, |sum, x| operate(Operator::Add, sum, x, true))?;
+        if sum.unsigned_abs() > max {
+            return Err(RollError::IntermediateMagnitudeLimitExceeded);
         }
+        Ok(match self.aggregate {
+            PostProcessor::Avg => sum.checked_div_euclid(count as i64).unwrap_or_default(),
+            _ => sum,
+        })
     }
 }
 
 /// Rolling result tree
-pub type RollTree = BinaryTree<ItemRoll, Operator, AtomicPtr<i64>>;
+pub type RollTree = BinaryTree<ItemRoll, Operator, OnceCell<i64>>;
+
+// if not `checked`, overflow saturates and dividing by zero gives zero, so result is always `Ok`
+const fn operate(mid: Operator, left: i64, right: i64, checked: bool) -> Result<i64, RollError> {
+    if !checked {
+        return Ok(mid.saturating_apply(left, right));
+    }
+    match mid.checked_apply(left, right) {
+        Some(x) => Ok(x),
+        None if right == 0 && core::matches!(mid, Operator::Divide | Operator::Modulo) => {
+            Err(RollError::DivisionByZero)
+        }
+        None => Err(RollError::IntermediateMagnitudeLimitExceeded),
+    }
+}
 
 impl RollTree {
-    fn calculate_value(&self) -> i64 {
-        match self.mid {
-            Operator::Add => self.left.value() + self.right.value(),
-            Operator::Minus => self.left.value() - self.right.value(),
-            Operator::Multiply => self.left.value() * self.right.value(),
+    // walk the tree without recursion, because a long chain like `1+1+...+1` or deeply nested parentheses
+    // like `1*(1*(...))` is a very deep tree.
+    // If `max` is provided, every operand and result magnitude should not exceed it, and no cache is used.
+    fn evaluate(&self, max: Option<u64>) -> Result<i64, RollError> {
+        enum Step<'a> {
+            Visit(&'a RollTreeNode),
+            Apply(&'a RollTree),
         }
+
+        let check = |x: i64| match max {
+            Some(max) if x.unsigned_abs() > max => {
+                Err(RollError::IntermediateMagnitudeLimitExceeded)
+            }
+            _ => Ok(x),
+        };
+
+        let mut steps = vec![
+            Step::Apply(self),
+            Step::Visit(&self.right),
+            Step::Visit(&self.left),
+        ];
+        let mut values = Vec::new();
+        while let Some(step) = steps.pop() {
+            match step {
+                Step::Visit(RollTreeNode::Leaf(leaf)) => match leaf.unlabeled() {
+                    ItemRoll::Parentheses(e) => steps.push(Step::Visit(e)),
+                    leaf => values.push(match max {
+                        Some(max) => leaf.checked_value(max)?,
+                        None => leaf.value(),
+                    }),
+                },
+                Step::Visit(RollTreeNode::Tree(tree)) => match tree.extra.get() {
+                    Some(value) if max.is_none() => values.push(*value),
+                    _ => steps.extend([
+                        Step::Apply(tree),
+                        Step::Visit(&tree.right),
+                        Step::Visit(&tree.left),
+                    ]),
+                },
+                Step::Apply(tree) => {
+                    let right = values.pop().unwrap();
+                    let left = values.pop().unwrap();
+                    let value = if max.is_some() {
+                        check(operate(tree.mid, left, right, true)?)?
+                    } else if core::ptr::eq(tree, self) {
+                        operate(tree.mid, left, right, false)?
+                    } else {
+                        let value = operate(tree.mid, left, right, false)?;
+                        *tree.extra.get_or_init(|| value)
+                    };
+                    values.push(value);
+                }
+            }
+        }
+        Ok(values.pop().unwrap())
     }
 
     /// Get rolling result value
+    ///
+    /// Arithmetic overflow saturates and dividing by zero gives zero, see [`try_value`] for checked one.
+    ///
+    /// [`try_value`]: #method.try_value
     pub fn value(&self) -> i64 {
-        // Safety: `cache` only used in `cache_it` function
-        unsafe { cache_it(&self.extra, || self.calculate_value()) }
+        *self.extra.get_or_init(|| self.evaluate(None).unwrap())
+    }
+
+    /// Get rolling result value, with checked arithmetic
+    ///
+    /// ## Errors
+    ///
+    /// [`IntermediateMagnitudeLimitExceeded`] if any arithmetic overflows,
+    /// [`DivisionByZero`] if any divisor is zero.
+    ///
+    /// [`IntermediateMagnitudeLimitExceeded`]: ../error/enum.RollError.html#variant.IntermediateMagnitudeLimitExceeded
+    /// [`DivisionByZero`]: ../error/enum.RollError.html#variant.DivisionByZero
+    pub fn try_value(&self) -> Result<i64, RollError> {
+        self.evaluate(Some(u64::MAX))
     }
 }
 
 /// Rolling result tree node, can be a leaf or a sub tree
-pub type RollTreeNode = BinaryTreeNode<ItemRoll, Operator, AtomicPtr<i64>>;
+pub type RollTreeNode = BinaryTreeNode<ItemRoll, Operator, OnceCell<i64>>;
 
 impl RollTreeNode {
-    /// Get rolling result value
+    /// Get rolling result value, see [`RollTree::value`]
+    ///
+    /// [`RollTree::value`]: type.RollTree.html#method.value
     pub fn value(&self) -> i64 {
         match self {
             Self::Leaf(leaf) => leaf.value(),
             Self::Tree(tree) => tree.value(),
         }
     }
+
+    /// Get rolling result value, with checked arithmetic
+    ///
+    /// ## Errors
+    ///
+    /// See [`RollTree::try_value`].
+    ///
+    /// [`RollTree::try_value`]: type.RollTree.html#method.try_value
+    pub fn try_value(&self) -> Result<i64, RollError> {
+        self.checked_value(u64::MAX)
+    }
+
+    fn has_dice(&self) -> bool {
+        match self {
+            Self::Leaf(leaf) => leaf.has_dice(),
+            Self::Tree(tree) => tree.left.has_dice() || tree.right.has_dice(),
+        }
+    }
+
+    pub(crate) fn checked_value(&self, max: u64) -> Result<i64, RollError> {
+        match self {
+            Self::Leaf(leaf) => leaf.checked_value(max),
+            Self::Tree(tree) => tree.evaluate(Some(max)),
+        }
+    }
+
+    /// Get every dice round rolling result in this tree, from left to right in command
+    ///
+    /// Rounds inside parentheses and repeats are included, but rounds in roll times expr(like the `1d4` in `(1d4)d6`)
+    /// are not, so the index of a round is the same as used in [`GurgleRoll::apply_advantage_to`].
+    ///
+    /// [`GurgleRoll::apply_advantage_to`]: struct.GurgleRoll.html#method.apply_advantage_to
+    pub fn dice_rolls(&self) -> impl Iterator<Item = &DiceRoll> {
+        DiceRolls {
+            stack: vec![DiceRollsStep::Node(self)],
+        }
+    }
+
+    /// Get raw rolling metadata of this tree, like how many dice are rolled, see [`RollSummary`]
+    ///
+    /// [`RollSummary`]: struct.RollSummary.html
+    #[must_use]
+    #[allow(clippy::cast_possible_wrap)] // because limit checked points are small
+    pub fn summary(&self) -> RollSummary {
+        let mut summary = RollSummary::default();
+        // walk the tree without recursion, see `RollTree::evaluate`
+        let mut stack = vec![DiceRollsStep::Node(self)];
+        while let Some(step) = stack.pop() {
+            match step {
+                DiceRollsStep::Node(Self::Leaf(item)) => stack.push(DiceRollsStep::Item(item)),
+                DiceRollsStep::Node(Self::Tree(tree)) => {
+                    stack.push(DiceRollsStep::Node(&tree.right));
+                    stack.push(DiceRollsStep::Node(&tree.left));
+                }
+                DiceRollsStep::Item(ItemRoll::Dice(dice)) => {
+                    if let Some(times) = dice.times_roll() {
+                        // roll times expr is always written in parentheses
+                        summary.parentheses += 1;
+                        stack.push(DiceRollsStep::Node(times));
+                    }
+                    let count = dice.points().len();
+                    summary.rounds += 1;
+                    summary.dice += count;
+                    summary.rerolled_dice += dice.rerolled().iter().map(Vec::len).sum::<usize>();
+                    if dice.is_fudge() {
+                        summary.fudge_dice += count;
+                    } else {
+                        *summary.dice_by_sided.entry(dice.sided()).or_default() += count;
+                    }
+                    let sum = dice.points().iter().sum::<u64>() as i64;
+                    // every fudge point is 2 more than its face
+                    let sum = if dice.is_fudge() {
+                        sum - count as i64 * 2
+                    } else {
+                        sum
+                    };
+                    summary.points_sum = summary.points_sum.saturating_add(sum);
+                }
+                DiceRollsStep::Item(ItemRoll::Number(_)) => {}
+                DiceRollsStep::Item(ItemRoll::Parentheses(e)) => {
+                    summary.parentheses += 1;
+                    stack.push(DiceRollsStep::Node(e));
+                }
+                DiceRollsStep::Item(ItemRoll::Repeat(r)) => {
+                    stack.extend(r.rolls.iter().rev().map(DiceRollsStep::Item));
+                }
+                DiceRollsStep::Item(ItemRoll::Labeled(item, _)) => {
+                    stack.push(DiceRollsStep::Item(item));
+                }
+            }
+        }
+        summary
+    }
+
+    #[cfg(feature = "std")]
+    fn with_advantage<R: RollRng + ?Sized>(&self, adv: &mut Advantage<'_, R>) -> Self {
+        match self {
+            Self::Leaf(leaf) => Self::Leaf(leaf.with_advantage(adv)),
+            Self::Tree(tree) => Self::Tree(RollTree::new(
+                tree.left.with_advantage(adv),
+                tree.right.with_advantage(adv),
+                tree.mid,
+            )),
+        }
+    }
+}
+
+// walk the tree without recursion, see `RollTree::evaluate`
+struct DiceRolls<'a> {
+    stack: Vec<DiceRollsStep<'a>>,
+}
+
+enum DiceRollsStep<'a> {
+    Node(&'a RollTreeNode),
+    Item(&'a ItemRoll),
+}
+
+impl<'a> Iterator for DiceRolls<'a> {
+    type Item = &'a DiceRoll;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(step) = self.stack.pop() {
+            match step {
+                DiceRollsStep::Node(RollTreeNode::Leaf(item)) => {
+                    self.stack.push(DiceRollsStep::Item(item));
+                }
+                DiceRollsStep::Node(RollTreeNode::Tree(tree)) => {
+                    self.stack.push(DiceRollsStep::Node(&tree.right));
+                    self.stack.push(DiceRollsStep::Node(&tree.left));
+                }
+                DiceRollsStep::Item(ItemRoll::Dice(dice)) => return Some(dice),
+                DiceRollsStep::Item(ItemRoll::Number(_)) => {}
+                DiceRollsStep::Item(ItemRoll::Parentheses(e)) => {
+                    self.stack.push(DiceRollsStep::Node(e));
+                }
+                DiceRollsStep::Item(ItemRoll::Repeat(r)) => {
+                    self.stack
+                        .extend(r.rolls.iter().rev().map(DiceRollsStep::Item));
+                }
+                DiceRollsStep::Item(ItemRoll::Labeled(item, _)) => {
+                    self.stack.push(DiceRollsStep::Item(item));
+                }
+            }
+        }
+        None
+    }
 }
 
 /// Rolling result of [`Gurgle`] command
@@ -163,39 +1089,608 @@ impl RollTreeNode {
 #[derive(Debug)]
 pub struct GurgleRoll<'g> {
     result: RollTreeNode,
+    source: &'g AstTreeNode,
     checker: Option<&'g Checker>,
-    cache: AtomicPtr<i64>,
+    tiers: Option<&'g TieredChecker>,
+    delta: i64,
+    // seed of rolling again when the command has a seed, continuing the seeded random source
+    #[cfg(feature = "std")]
+    next_seed: Option<u64>,
+    cache: OnceCell<i64>,
 }
 
 impl<'g> GurgleRoll<'g> {
-    pub(crate) fn new(result: RollTreeNode, checker: Option<&'g Checker>) -> Self {
+    pub(crate) const fn new(
+        result: RollTreeNode, source: &'g AstTreeNode, checker: Option<&'g Checker>,
+        tiers: Option<&'g TieredChecker>,
+    ) -> Self {
         Self {
             result,
+            source,
             checker,
-            cache: AtomicPtr::default(),
+            tiers,
+            delta: 0,
+            #[cfg(feature = "std")]
+            next_seed: None,
+            cache: OnceCell::new(),
         }
     }
 
+    #[cfg(feature = "std")]
+    pub(crate) fn seeded_by(self, rng: &rng::CommandRng) -> Self {
+        Self {
+            next_seed: rng.next_seed(),
+            ..self
+        }
+    }
+
+    /// Apply modifier `f` to the rolled value, result value is `f(raw_value)`
+    #[cfg(feature = "std")]
+    pub(crate) fn with_modifier<F: Fn(i64) -> i64>(mut self, f: F) -> Self {
+        let raw = self.result.value();
+        self.delta = f(raw).saturating_sub(raw);
+        self
+    }
+
     /// Get rolling result expression
     #[must_use]
     pub const fn expr(&self) -> &RollTreeNode {
         &self.result
     }
 
+    /// Roll the command again in place, reusing memory of this result
+    ///
+    /// Every dice round is rolled again and all cached values are cleared, so it gives the same result as a new
+    /// roll with the same random source, without building a new result tree. Seed in command is ignored,
+    /// and modifier of [`Gurgle::roll_with_modifier`] is removed.
+    ///
+    /// [`Gurgle::roll_with_modifier`]: ../struct.Gurgle.html#method.roll_with_modifier
+    #[cfg(feature = "std")]
+    pub fn reroll(&mut self) {
+        self.reroll_with_rng(&mut rng::CommandRng::default_source());
+    }
+
+    /// Roll the command again in place with random source `rng`, see [`reroll`]
+    ///
+    /// [`reroll`]: #method.reroll
+    pub fn reroll_with_rng<R: RollRng + ?Sized>(&mut self, rng: &mut R) {
+        self.source.roll_again_with_rng(rng, &mut self.result);
+        self.delta = 0;
+        self.cache = OnceCell::new();
+    }
+
     /// Get the checker
     pub const fn checker(&self) -> Option<&'g Checker> {
         self.checker
     }
 
-    /// Get rolling result value
+    /// Get rolling result value, with modifier applied, see [`Gurgle::roll_with_modifier`]
+    ///
+    /// [`Gurgle::roll_with_modifier`]: ../struct.Gurgle.html#method.roll_with_modifier
     #[must_use]
     pub fn value(&self) -> i64 {
-        // Safety: cache only used in cache_it
-        unsafe { cache_it(&self.cache, || self.result.value()) }
+        *self
+            .cache
+            .get_or_init(|| self.result.value().saturating_add(self.delta))
+    }
+
+    /// Get rolling result value before modifier is applied
+    #[must_use]
+    pub fn raw_value(&self) -> i64 {
+        self.result.value()
+    }
+
+    /// Get rolling result value like [`value`], with checked arithmetic, including applying modifier
+    ///
+    /// Unlike [`Gurgle::try_roll`], [`Config::max_intermediate_magnitude`] is not checked, only overflow is.
+    ///
+    /// ## Errors
+    ///
+    /// See [`RollTree::try_value`].
+    ///
+    /// [`value`]: #method.value
+    /// [`Gurgle::try_roll`]: ../struct.Gurgle.html#method.try_roll
+    /// [`Config::max_intermediate_magnitude`]: ../struct.Config.html#structfield.max_intermediate_magnitude
+    /// [`RollTree::try_value`]: type.RollTree.html#method.try_value
+    pub fn try_value(&self) -> Result<i64, RollError> {
+        self.result
+            .try_value()?
+            .checked_add(self.delta)
+            .ok_or(RollError::IntermediateMagnitudeLimitExceeded)
+    }
+
+    /// Get how much modifier changed the rolling result value, zero if no modifier is applied
+    #[must_use]
+    pub const fn modifier_delta(&self) -> i64 {
+        self.delta
     }
 
     /// Check if this rolling result is success(passed)
     pub fn success(&self) -> Option<bool> {
         self.checker.map(|c| c.check(self.value()))
     }
+
+    /// Check if any dice is actually rolled to get this result, that is, random generator is used
+    #[must_use]
+    pub fn consumed_rng(&self) -> bool {
+        self.result.has_dice()
+    }
+
+    /// Get the tier this rolling result reached, if command has a tiered checker
+    pub fn tier(&self) -> Option<&'g str> {
+        self.tiers
+            .and_then(|t| t.tier(self.value()))
+            .map(|t| t.label.as_str())
+    }
+
+    /// Compute every value and get an owned snapshot of this rolling result, see [`FrozenRoll`]
+    ///
+    /// [`FrozenRoll`]: struct.FrozenRoll.html
+    #[must_use]
+    pub fn finalize(self) -> FrozenRoll {
+        FrozenRoll {
+            value: self.value(),
+            raw_value: self.raw_value(),
+            success: self.success(),
+            tier: self.tier().map(str::to_owned),
+            checker: self.checker.cloned(),
+            dice_points: self.dice_rolls().map(|d| d.points().to_vec()).collect(),
+        }
+    }
+
+    /// Get every dice round rolling result, from left to right in command, see [`RollTreeNode::dice_rolls`]
+    ///
+    /// [`RollTreeNode::dice_rolls`]: type.RollTreeNode.html#method.dice_rolls
+    pub fn dice_rolls(&self) -> impl Iterator<Item = &DiceRoll> {
+        self.result.dice_rolls()
+    }
+
+    /// Get raw rolling metadata, like how many dice are rolled, see [`RollSummary`]
+    ///
+    /// It's about dice, not the result value, so modifier and checker are not involved.
+    ///
+    /// [`RollSummary`]: struct.RollSummary.html
+    #[must_use]
+    pub fn summary(&self) -> RollSummary {
+        self.result.summary()
+    }
+
+    /// Get the critical of the first dice round, like a natural 20 or natural 1 of attack die in `1d20+5`
+    ///
+    /// It's [`Crit::Max`] if every kept die of the round shows its max face, [`Crit::Min`] if every kept die
+    /// shows face 1, whatever the total is. So `3d6` is critical only when all 3 dice are the same max or min face,
+    /// and `2d20kh1`(advantage) is a natural 20 if the kept die is 20. A round of 1 sided dice is always [`Crit::Max`].
+    ///
+    /// Returns `None` if the round is not critical, or there is no dice round. See [`critical_of`] for other rounds.
+    ///
+    /// [`Crit::Max`]: enum.Crit.html#variant.Max
+    /// [`Crit::Min`]: enum.Crit.html#variant.Min
+    /// [`critical_of`]: #method.critical_of
+    #[must_use]
+    pub fn critical(&self) -> Option<Crit> {
+        self.critical_of(0)
+    }
+
+    /// Get the worst glitch among all success counting pools, like `6d6f5`, see [`DiceRoll::glitch`]
+    ///
+    /// [`DiceRoll::glitch`]: struct.DiceRoll.html#method.glitch
+    #[must_use]
+    pub fn glitch(&self) -> Option<Glitch> {
+        self.dice_rolls().filter_map(DiceRoll::glitch).max()
+    }
+
+    /// Get the critical of the `dice_index`-th dice round, see [`critical`]
+    ///
+    /// Dice rounds are indexed like [`apply_advantage_to`].
+    ///
+    /// [`critical`]: #method.critical
+    /// [`apply_advantage_to`]: #method.apply_advantage_to
+    #[must_use]
+    pub fn critical_of(&self, dice_index: usize) -> Option<Crit> {
+        self.dice_rolls().nth(dice_index)?.critical()
+    }
+
+    /// Apply advantage to the `dice_index`-th dice round, get a new rolling result
+    ///
+    /// Every point of that round, including extra points added by explode, is rolled again, and the higher one
+    /// is kept, so the round value never goes down whatever its post processor(sum, avg, max or min) is.
+    /// New points are clamped like the first roll, extra points of penetrate explosion are minus 1 too,
+    /// and they do not explode again.
+    /// Dice of its roll times expr and all other rounds are kept as is.
+    ///
+    /// Dice rounds are indexed from zero, from left to right in command,
+    /// rounds in roll times expr(like the `1d4` in `(1d4)d6`) are not counted.
+    ///
+    /// If the command has a seed, and this result is rolled by [`Gurgle::roll`] or other methods respecting it,
+    /// new points continue the seeded random source, so the new result is reproducible too.
+    ///
+    /// Returns `None` if there is no such dice round.
+    ///
+    /// [`Gurgle::roll`]: ../struct.Gurgle.html#method.roll
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn apply_advantage_to(&self, dice_index: usize) -> Option<Self> {
+        let mut rng = rng::CommandRng::new(self.next_seed);
+        let mut adv = Advantage {
+            remain: Some(dice_index),
+            rng: &mut rng,
+        };
+        let result = self.result.with_advantage(&mut adv);
+        if adv.remain.is_some() {
+            return None;
+        }
+        Some(
+            Self {
+                delta: self.delta,
+                ..Self::new(result, self.source, self.checker, self.tiers)
+            }
+            .seeded_by(&rng),
+        )
+    }
+
+    /// Find which band the rolling result value falls in, and get its name
+    ///
+    /// Bands are checked in order, so the first one containing the result wins if they overlap.
+    /// Returns `None` if no band contains the result.
+    pub fn categorize<'b>(&self, bands: &[(RangeInclusive<i64>, &'b str)]) -> Option<&'b str> {
+        let value = self.value();
+        bands
+            .iter()
+            .find(|(range, _)| range.contains(&value))
+            .map(|(_, name)| *name)
+    }
+
+    /// Get how much the rolling result value is above the compare target of checker, negative if below
+    ///
+    /// Returns `None` if there is no checker, or checker is not a single compare, like `even` or `in 8..12`.
+    #[must_use]
+    pub fn margin(&self) -> Option<i64> {
+        match self.checker? {
+            Checker::Compare { target, .. } => Some(self.value().saturating_sub(*target)),
+            Checker::Parity(_) | Checker::Range { .. } | Checker::And(..) | Checker::Or(..) => None,
+        }
+    }
+
+    /// Get the smallest change of rolling result value which makes a failed check pass
+    ///
+    /// Result is positive if value is too low, like `3` for `3d6 > 10` rolled `8`, or negative if too high,
+    /// like `-1` for `1d20 <= 10` rolled `11`. Only values which the command can roll are considered,
+    /// that is, `value + shortfall` is between [`Gurgle::min_result`] and [`Gurgle::max_result`],
+    /// moved by modifier delta if any.
+    ///
+    /// Returns `None` if there is no checker, check passed, or no value the command can roll passes,
+    /// like `3d6 > 20` or `1d20 <= 5 and >= 10`.
+    ///
+    /// [`Gurgle::min_result`]: ../struct.Gurgle.html#method.min_result
+    /// [`Gurgle::max_result`]: ../struct.Gurgle.html#method.max_result
+    #[must_use]
+    pub fn shortfall(&self) -> Option<i64> {
+        let (min, max) = self.source.bounds();
+        self.checker?.shortfall(
+            self.value(),
+            min.saturating_add(self.delta),
+            max.saturating_add(self.delta),
+        )
+    }
+}
+
+/// Snapshot of a [`GurgleRoll`] with every value computed, see [`GurgleRoll::finalize`]
+///
+/// It owns all its data and has no lazy cache, so it can be cloned, stored or sent to other threads freely.
+///
+/// [`GurgleRoll`]: struct.GurgleRoll.html
+/// [`GurgleRoll::finalize`]: struct.GurgleRoll.html#method.finalize
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrozenRoll {
+    value: i64,
+    raw_value: i64,
+    success: Option<bool>,
+    tier: Option<String>,
+    checker: Option<Checker>,
+    dice_points: Vec<Vec<u64>>,
+}
+
+impl FrozenRoll {
+    /// Get rolling result value, with modifier applied
+    #[must_use]
+    pub const fn value(&self) -> i64 {
+        self.value
+    }
+
+    /// Get rolling result value before modifier is applied
+    #[must_use]
+    pub const fn raw_value(&self) -> i64 {
+        self.raw_value
+    }
+
+    /// Get how much modifier changed the rolling result value, zero if no modifier is applied
+    #[must_use]
+    pub const fn modifier_delta(&self) -> i64 {
+        self.value.saturating_sub(self.raw_value)
+    }
+
+    /// Check if this rolling result is success(passed), `None` if command has no checker
+    #[must_use]
+    pub const fn success(&self) -> Option<bool> {
+        self.success
+    }
+
+    /// Get the tier this rolling result reached, if command has a tiered checker
+    #[must_use]
+    pub fn tier(&self) -> Option<&str> {
+        self.tier.as_deref()
+    }
+
+    /// Get the checker
+    #[must_use]
+    pub const fn checker(&self) -> Option<&Checker> {
+        self.checker.as_ref()
+    }
+
+    /// Get points of every dice round, in the order of [`GurgleRoll::dice_rolls`]
+    ///
+    /// [`GurgleRoll::dice_rolls`]: struct.GurgleRoll.html#method.dice_rolls
+    #[must_use]
+    pub fn dice_points(&self) -> &[Vec<u64>] {
+        &self.dice_points
+    }
+}
+
+/// Rolling results of a batch of rolls of the same command, see [`Gurgle::roll_batch`]
+///
+/// [`Gurgle::roll_batch`]: ../struct.Gurgle.html#method.roll_batch
+#[derive(Debug)]
+pub struct BatchRoll<'g> {
+    rolls: Vec<GurgleRoll<'g>>,
+}
+
+impl<'g> BatchRoll<'g> {
+    #[cfg(feature = "std")]
+    pub(crate) const fn new(rolls: Vec<GurgleRoll<'g>>) -> Self {
+        Self { rolls }
+    }
+
+    /// Get every rolling result, in rolling order
+    #[must_use]
+    pub fn rolls(&self) -> &[GurgleRoll<'g>] {
+        &self.rolls
+    }
+
+    /// Get check result summary of this batch
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)] // because pass rate does not need to be exact
+    pub fn summary(&self) -> BatchSummary {
+        let total = self.rolls.len();
+        let pass_count = self
+            .rolls
+            .iter()
+            .filter(|r| r.success().unwrap_or_default())
+            .count();
+        BatchSummary {
+            pass_count,
+            total,
+            pass_rate: if total == 0 {
+                0.0
+            } else {
+                pass_count as f64 / total as f64
+            },
+            margins: self.rolls.iter().map(GurgleRoll::margin).collect(),
+        }
+    }
+}
+
+/// Check result summary of a [`BatchRoll`]
+///
+/// [`BatchRoll`]: struct.BatchRoll.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchSummary {
+    /// How many rolls passed the checker, always zero if command has no checker
+    pub pass_count: usize,
+    /// How many rolls are in the batch
+    pub total: usize,
+    /// `pass_count / total`, zero for an empty batch
+    pub pass_rate: f64,
+    /// Margin of every roll, in rolling order, see [`GurgleRoll::margin`]
+    ///
+    /// [`GurgleRoll::margin`]: struct.GurgleRoll.html#method.margin
+    pub margins: Vec<Option<i64>>,
+}
+
+/// Raw rolling metadata of a result, see [`GurgleRoll::summary`]
+///
+/// Dice in every sub expr are counted, including parentheses, repeats and roll times expr(like the `1d4` in `(1d4)d6`).
+///
+/// [`GurgleRoll::summary`]: struct.GurgleRoll.html#method.summary
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RollSummary {
+    /// How many dice rounds are rolled, like `3d6`
+    pub rounds: usize,
+    /// How many dice are rolled, extra dice of explosions are included, dice discarded by reroll are not
+    pub dice: usize,
+    /// How many dice are rolled for every sides count, fudge dice are not included
+    pub dice_by_sided: BTreeMap<u64, usize>,
+    /// How many fudge dice are rolled, like `4dF`
+    pub fudge_dice: usize,
+    /// Sum of all dice points, before any post processor like `kh3` or `max`, fudge dice faces are -1, 0 and +1
+    pub points_sum: i64,
+    /// How many sub exprs in parentheses are rolled, like the `(1d4+1)` in `2#(1d4+1)` and the `(1d4)` in `(1d4)d6`
+    pub parentheses: usize,
+    /// How many dice are discarded by reroll, like the first die of `1d6r1` rolled 1 and then 5
+    pub rerolled_dice: usize,
+}
+
+/// Statistics of result values of rolling a command many times, see [`Gurgle::roll_stats`]
+///
+/// [`Gurgle::roll_stats`]: ../struct.Gurgle.html#method.roll_stats
+#[derive(Debug, Clone, PartialEq)]
+pub struct RollStats {
+    /// How many times the command is rolled
+    pub count: usize,
+    /// The lowest result value, `None` if not rolled at all
+    pub min: Option<i64>,
+    /// The highest result value, `None` if not rolled at all
+    pub max: Option<i64>,
+    /// Average of result values, zero if not rolled at all
+    pub mean: f64,
+    /// How many times every result value is rolled
+    pub histogram: BTreeMap<i64, usize>,
+}
+
+impl FromIterator<i64> for RollStats {
+    #[allow(clippy::cast_precision_loss)] // because mean does not need to be exact
+    fn from_iter<I: IntoIterator<Item = i64>>(values: I) -> Self {
+        let mut count = 0;
+        let mut sum = 0_i128;
+        let mut histogram = BTreeMap::new();
+        for value in values {
+            count += 1;
+            sum += i128::from(value);
+            *histogram.entry(value).or_insert(0) += 1;
+        }
+        Self {
+            count,
+            min: histogram.keys().next().copied(),
+            max: histogram.keys().next_back().copied(),
+            mean: if count == 0 {
+                0.0
+            } else {
+                sum as f64 / count as f64
+            },
+            histogram,
+        }
+    }
+}
+
+/// Everything about a single roll of a command, see [`evaluate`]
+///
+/// [`evaluate`]: ../fn.evaluate.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evaluation {
+    /// Canonical command string, like `3d6+1>10`
+    pub command: String,
+    /// Rolling result value
+    pub value: i64,
+    /// If the roll passed the checker, `None` if command has no checker
+    pub success: Option<bool>,
+    /// The tier the roll reached, `None` if command has no tiered checker or no tier is reached
+    pub tier: Option<String>,
+    /// Detail output of the roll in global language, like `(4+3+1) + 1 = 9, target is >10, failed`,
+    /// `None` if feature `detail` is not enabled
+    pub detail: Option<String>,
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_chain_value_not_recurse() {
+        let count = 5000;
+        let mut node = RollTreeNode::Leaf(ItemRoll::Number(1));
+        for _ in 1..count {
+            node = RollTreeNode::Tree(RollTree::new(
+                node,
+                RollTreeNode::Leaf(ItemRoll::Number(1)),
+                Operator::Add,
+            ));
+        }
+
+        // a recursive calculation needs far more stack than this for such a deep tree
+        let (node, value) = std::thread::Builder::new()
+            .stack_size(32 * 1024)
+            .spawn(move || {
+                let value = node.value();
+                (node, value)
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(value, count);
+        assert_eq!(node.as_tree().unwrap().left.value(), count - 1);
+    }
+
+    #[test]
+    fn test_nested_parentheses_value_not_recurse() {
+        let depth = 5000;
+        let mut node = RollTreeNode::Tree(RollTree::new(
+            RollTreeNode::Leaf(ItemRoll::Number(1)),
+            RollTreeNode::Leaf(ItemRoll::Number(1)),
+            Operator::Add,
+        ));
+        // build `(1*(1*(...(1+1))))`, a deep right-leaning tree through parentheses
+        for _ in 1..depth {
+            node = RollTreeNode::Tree(RollTree::new(
+                RollTreeNode::Leaf(ItemRoll::Number(1)),
+                RollTreeNode::Leaf(ItemRoll::Parentheses(Box::new(node))),
+                Operator::Multiply,
+            ));
+        }
+        let node = RollTreeNode::Leaf(ItemRoll::Parentheses(Box::new(node)));
+
+        let (node, value, checked) = std::thread::Builder::new()
+            .stack_size(32 * 1024)
+            .spawn(move || {
+                let checked = node.checked_value(1);
+                let value = node.value();
+                (node, value, checked)
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(value, 2);
+        assert_eq!(checked, Err(RollError::IntermediateMagnitudeLimitExceeded));
+        assert_eq!(node.checked_value(2), Ok(2));
+    }
+
+    #[test]
+    fn test_empty_round_value() {
+        use crate::checker::Compare;
+
+        for pp in [
+            PostProcessor::Sum,
+            PostProcessor::Avg,
+            PostProcessor::Max,
+            PostProcessor::Min,
+            PostProcessor::ScaledAvg(10),
+            PostProcessor::KeepHighest(3),
+            PostProcessor::KeepLowest(3),
+            PostProcessor::DropHighest(1),
+            PostProcessor::DropLowest(1),
+            PostProcessor::CountSuccesses {
+                compare: Compare::Gte,
+                target: 5,
+            },
+        ] {
+            assert_eq!(DiceRoll::new(vec![], 6, pp, None).value(), 0, "{}", pp);
+            assert_eq!(RollArena::new().points_value(pp, None, false), 0, "{}", pp);
+            assert_eq!(
+                DiceRoll::new(vec![], 6, pp, None).kept(),
+                Vec::<bool>::new()
+            );
+        }
+        for aggregate in [
+            PostProcessor::Sum,
+            PostProcessor::Avg,
+            PostProcessor::Max,
+            PostProcessor::Min,
+        ] {
+            assert_eq!(aggregate_values(aggregate, core::iter::empty(), 0), 0);
+        }
+    }
+
+    #[test]
+    fn test_advantage_respects_weights() {
+        use crate::{expr::Dice, rng::Sequence};
+
+        // only face 1 and 4 can be rolled, a fair die would roll 2 again
+        let dice = Dice::new_weighted(1, 4, vec![1, 0, 0, 1]).unwrap();
+        let roll = dice.roll_with_rng(&mut Sequence(vec![1].into_iter()));
+        assert_eq!(roll.points(), [1]);
+        let mut adv = Advantage {
+            remain: Some(0),
+            rng: &mut Sequence(vec![2].into_iter()),
+        };
+        assert_eq!(roll.with_advantage(&mut adv).points(), [4]);
+    }
 }
@@ -0,0 +1,37 @@
+//! serde support of types which can't be derived
+
+use alloc::string::String;
+
+use serde::{
+    de::{Deserialize, Deserializer, Error},
+    ser::{Serialize, SerializeStruct, Serializer},
+};
+
+use crate::{roll::GurgleRoll, Gurgle};
+
+/// `Gurgle` is serialized as its canonical command string
+impl Serialize for Gurgle {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// `Gurgle` is deserialized by compiling the command string with default config, so limits are still checked
+impl<'de> Deserialize<'de> for Gurgle {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let command = String::deserialize(deserializer)?;
+        Self::compile(&command).map_err(D::Error::custom)
+    }
+}
+
+/// `GurgleRoll` is serialized with its rolling result tree, result value, check result and reached tier
+impl Serialize for GurgleRoll<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("GurgleRoll", 4)?;
+        s.serialize_field("expr", self.expr())?;
+        s.serialize_field("value", &self.value())?;
+        s.serialize_field("success", &self.success())?;
+        s.serialize_field("tier", &self.tier())?;
+        s.end()
+    }
+}
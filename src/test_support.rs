@@ -0,0 +1,74 @@
+//! helpers for writing reproducible tests against gurgle, **for tests only**
+//!
+//! Only available when feature `test-util` is enabled, don't use it in production code.
+
+use std::cell::RefCell;
+
+use nanorand::{Rng, WyRand};
+
+use crate::rng::RollRng;
+
+thread_local! {
+    static GLOBAL: RefCell<Option<WyRand>> = const { RefCell::new(None) };
+}
+
+/// Make all following rolling in current thread deterministic, by using a random generator created from `seed`
+///
+/// Commands with a seed of their own(like `3d6 @seed=42`) still use their own seed.
+///
+/// It only affects current thread, because test harness runs every test in its own thread,
+/// so tests will not disturb each other.
+pub fn seed_global(seed: u64) {
+    GLOBAL.with(|g| g.borrow_mut().replace(WyRand::new_seed(seed)));
+}
+
+/// Undo [`seed_global`], following rolling in current thread become random again
+///
+/// [`seed_global`]: fn.seed_global.html
+pub fn reset_global() {
+    GLOBAL.with(|g| g.borrow_mut().take());
+}
+
+pub(crate) fn is_seeded() -> bool {
+    GLOBAL.with(|g| g.borrow().is_some())
+}
+
+/// Random number source which uses the generator set by [`seed_global`]
+///
+/// [`seed_global`]: fn.seed_global.html
+pub(crate) struct GlobalRng;
+
+impl RollRng for GlobalRng {
+    fn roll_die(&mut self, sided: u64) -> u64 {
+        GLOBAL.with(|g| {
+            g.borrow_mut().as_mut().map_or_else(
+                || nanorand::tls_rng().generate_range(1..=sided),
+                |rng| rng.generate_range(1..=sided),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Gurgle;
+
+    #[test]
+    fn test_seed_global() {
+        let gurgle = Gurgle::compile("10d1000+(1d4)d20").unwrap();
+        let roll_many = || (0..10).map(|_| gurgle.roll().value()).collect::<Vec<_>>();
+
+        seed_global(42);
+        let first = roll_many();
+        seed_global(42);
+        let second = roll_many();
+        assert_eq!(first, second);
+
+        seed_global(42);
+        assert_eq!(gurgle.expr().roll().value(), first[0]);
+
+        reset_global();
+        assert!(!is_seeded());
+    }
+}
@@ -1,5 +1,15 @@
+use alloc::boxed::Box;
+
 /// Common binary tree structure
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "T: serde::Serialize, Mid: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>, Mid: serde::Deserialize<'de>, Extra: Default"
+    ))
+)]
 pub struct BinaryTree<T, Mid = (), Extra = ()> {
     /// Left tree
     pub left: Box<BinaryTreeNode<T, Mid, Extra>>,
@@ -8,6 +18,7 @@ pub struct BinaryTree<T, Mid = (), Extra = ()> {
     /// right tree
     pub right: Box<BinaryTreeNode<T, Mid, Extra>>,
 
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub(crate) extra: Extra,
 }
 
@@ -30,6 +41,14 @@ where
 
 /// Node in the binary tree
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "T: serde::Serialize, Mid: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>, Mid: serde::Deserialize<'de>, Extra: Default"
+    ))
+)]
 pub enum BinaryTreeNode<T, Mid = (), Extra = ()> {
     /// A leaf node
     Leaf(T),
@@ -41,13 +60,13 @@ impl<T, Mid, Extra> BinaryTreeNode<T, Mid, Extra> {
     /// Check if this node is a leaf node
     #[must_use]
     pub const fn is_leaf(&self) -> bool {
-        std::matches!(self, Self::Leaf(_))
+        core::matches!(self, Self::Leaf(_))
     }
 
     /// Check if this node is a tree
     #[must_use]
     pub const fn is_tree(&self) -> bool {
-        std::matches!(self, Self::Tree(_))
+        core::matches!(self, Self::Tree(_))
     }
 
     /// Try treat this node as a leaf node and get leaf value
@@ -0,0 +1,51 @@
+//! memory usage of rolling, in its own test binary because it replaces the global allocator
+#![cfg(feature = "std")]
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use gurgle::Gurgle;
+
+/// System allocator which counts bytes currently allocated
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[test]
+fn test_rolling_does_not_leak() {
+    let gurgle = Gurgle::compile("(1d4)d6+2d8max*3-1d20 >= 10").unwrap();
+    let roll = |n| {
+        for _ in 0..n {
+            let result = gurgle.roll();
+            // fill every value cache in the result tree
+            assert_eq!(result.value(), result.expr().value());
+            #[cfg(feature = "detail")]
+            let _ = result.to_string();
+        }
+    };
+
+    // thread local random source and global language data are allocated when first used
+    roll(10);
+    let before = ALLOCATED.load(Ordering::SeqCst);
+    roll(10000);
+    let after = ALLOCATED.load(Ordering::SeqCst);
+
+    assert!(after <= before, "{} bytes leaked", after - before);
+}